@@ -18,9 +18,15 @@ pub fn impl_sim_state_macro(ast: &syn::DeriveInput) -> TokenStream {
     for field in fields {
         let field_name = field.ident.clone();
 
-        if field_name.is_some() {
+        if let Some(ident) = &field_name {
+            // Each field is given an independent random stream
+            // identified by the name of the field
+            let stream_name = ident.to_string();
             call_tokens.extend(quote!(
-                transactions.extend(self.#field_name.call(rng, env));
+                let mut field_rng = verbs_rs::rng::stream(
+                    seed, verbs_rs::rng::name_id(#stream_name)
+                );
+                transactions.extend(self.#field_name.call(&mut field_rng, env));
             ));
             record_tokens.extend(quote!(
                 self.#field_name.record(env);
@@ -34,6 +40,7 @@ pub fn impl_sim_state_macro(ast: &syn::DeriveInput) -> TokenStream {
                 &mut self, rng: &mut R, env: &mut Env<D, V>
             ) -> Vec<Transaction> {
                 let mut transactions = Vec::<Transaction>::new();
+                let seed = rng.next_u64();
                 #call_tokens
                 transactions
             }
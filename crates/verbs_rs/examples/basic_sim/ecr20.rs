@@ -1,6 +1,7 @@
 use alloy_sol_types::sol;
 
 sol!(
+    #[sol(extra_methods)]
     ABI,
     r#"[
         {
@@ -4,6 +4,7 @@ mod state;
 use alloy_primitives::{Address, Uint, U256};
 use state::{AgentState, SimpleAgent};
 use verbs_rs::agent::AgentVec;
+use verbs_rs::contract::DeployedContract;
 use verbs_rs::env::{Env, RandomValidator};
 use verbs_rs::sim_runner::run;
 use verbs_rs::{utils, LocalDB};
@@ -30,7 +31,8 @@ pub fn main() {
 
     let mut env = Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
 
-    let token_address = env.deploy_contract(
+    let token = DeployedContract::<ecr20::ABI::ABICalls>::deploy(
+        &mut env,
         admin_address,
         "ECR20",
         utils::constructor_data(ecr20::BYTECODE, None),
@@ -38,22 +40,22 @@ pub fn main() {
 
     let agents: Vec<SimpleAgent> = (0..args.n_agents)
         .into_iter()
-        .map(|x| SimpleAgent::new(x, args.n_agents, token_address))
+        .map(|x| SimpleAgent::new(x, args.n_agents, token.clone()))
         .collect();
 
     env.insert_accounts(start_balance, agents.iter().map(|a| a.address).collect());
 
     for agent in &agents {
-        env.direct_execute(
-            agent.address,
-            token_address,
-            ecr20::ABI::approveCall {
-                spender: agent.address,
-                tokens: U256::from(start_balance),
-            },
-            U256::ZERO,
-        )
-        .unwrap();
+        token
+            .execute(
+                &mut env,
+                agent.address,
+                ecr20::ABI::approveCall {
+                    spender: agent.address,
+                    tokens: U256::from(start_balance),
+                },
+            )
+            .unwrap();
     }
 
     let mut state = AgentState {
@@ -3,7 +3,7 @@ use alloy_primitives::{Address, Uint, U256};
 use rand::Rng;
 use rand::RngCore;
 use verbs_rs::agent::{Agent, AgentSet, AgentVec, RecordedAgent, SimState};
-use verbs_rs::contract::Transaction;
+use verbs_rs::contract::{DeployedContract, Transaction};
 use verbs_rs::env::Env;
 use verbs_rs::env::Validator;
 use verbs_rs::DB;
@@ -12,18 +12,18 @@ pub struct SimpleAgent {
     pub address: Address,
     current_balance: U256,
     n_agents: u64,
-    token_address: Address,
+    token: DeployedContract<ecr20::ABI::ABICalls>,
 }
 
 impl SimpleAgent {
-    pub fn new(idx: usize, n_agents: usize, token_address: Address) -> Self {
+    pub fn new(idx: usize, n_agents: usize, token: DeployedContract<ecr20::ABI::ABICalls>) -> Self {
         let idx_u64 = u64::try_from(idx).unwrap();
         let address = Address::from(Uint::from(idx_u64));
         SimpleAgent {
             address,
             current_balance: U256::ZERO,
             n_agents: u64::try_from(n_agents).unwrap(),
-            token_address,
+            token,
         }
     }
 }
@@ -34,14 +34,14 @@ impl Agent for SimpleAgent {
         rng: &mut R,
         network: &mut Env<D, V>,
     ) -> Vec<Transaction> {
-        self.current_balance = network
-            .direct_call(
+        self.current_balance = self
+            .token
+            .call(
+                network,
                 self.address,
-                self.token_address,
                 ecr20::ABI::balanceOfCall {
                     tokenOwner: self.address,
                 },
-                U256::ZERO,
             )
             .unwrap()
             .0
@@ -51,9 +51,8 @@ impl Agent for SimpleAgent {
             let receiver = rng.gen_range(0..self.n_agents);
             let receiver = Address::from(Uint::from(receiver));
             let send_amount = std::cmp::min(self.current_balance, U256::from(1000));
-            let send_call = Transaction::basic(
+            let send_call = self.token.transaction(
                 self.address,
-                self.token_address,
                 ecr20::ABI::transferCall {
                     to: receiver,
                     tokens: send_amount,
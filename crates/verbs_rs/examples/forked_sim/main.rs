@@ -1,27 +1,97 @@
 mod dai_abi;
 
 use alloy_primitives::{Address, U256};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use dai_abi::ABI as DAI_ABI;
+use std::path::{Path, PathBuf};
 use verbs_rs::env::{Env, RandomValidator};
 use verbs_rs::utils::address_from_hex;
-use verbs_rs::ForkDb;
+use verbs_rs::{ForkDb, ForkMode, LocalDB, RequestCache, StorageDump, DB};
+
+#[derive(ValueEnum, Clone, Debug)]
+enum Mode {
+    /// Make all requests to the remote endpoint
+    Live,
+    /// Record responses to the cassette
+    Record,
+    /// Serve requests only from the cassette
+    Replay,
+    /// Serve requests from the cassette, falling
+    /// back to the remote endpoint
+    Hybrid,
+}
 
 #[derive(Parser, Debug)]
 #[command(about, long_about = None)]
 struct Args {
-    /// Number of agents
-    #[arg(short, long)]
+    /// Alchemy API key (not required in replay mode)
+    #[arg(short, long, default_value = "")]
     key: String,
+
+    /// Fork mode
+    #[arg(short, long, value_enum, default_value_t = Mode::Live)]
+    mode: Mode,
+
+    /// Cassette file used to record/replay requests
+    #[arg(long, default_value = "cassette.jsonl")]
+    cassette: PathBuf,
+
+    /// Block number to fork from (required to
+    /// replay requests made at a fixed block)
+    #[arg(short, long)]
+    block: Option<u64>,
+
+    /// Path of a request cache file. If the file
+    /// exists the simulation runs offline from the
+    /// cache, otherwise the requests made to the
+    /// fork are saved to it.
+    #[arg(short, long)]
+    cache: Option<String>,
+
+    /// Path of a JSON storage dump file, used to
+    /// pre-load the full storage of contracts
+    #[arg(short, long)]
+    storage_dump: Option<PathBuf>,
 }
 
 pub fn main() {
     let args = Args::parse();
 
-    let url_str = format!("https://eth-mainnet.g.alchemy.com/v2/{}", args.key);
+    match args.cache {
+        Some(path) if Path::new(&path).exists() => {
+            let cache = RequestCache::load(&path).unwrap();
+            let mut env =
+                Env::<LocalDB, RandomValidator>::from_request_cache(&cache, RandomValidator {});
+            print_dai_info(&mut env);
+        }
+        cache => {
+            let url_str = format!("https://eth-mainnet.g.alchemy.com/v2/{}", args.key);
+            let mode = match args.mode {
+                Mode::Live => ForkMode::Live,
+                Mode::Record => ForkMode::Record(args.cassette),
+                Mode::Replay => ForkMode::Replay(args.cassette),
+                Mode::Hybrid => ForkMode::Hybrid(args.cassette),
+            };
+            let mut env = Env::<ForkDb, RandomValidator>::init_with_mode(
+                &url_str,
+                args.block,
+                mode,
+                RandomValidator {},
+            );
+            if let Some(path) = args.storage_dump {
+                let dump = StorageDump::load(path).unwrap();
+                env.import_storage_dump(&dump).unwrap();
+            }
+            print_dai_info(&mut env);
 
-    let mut env = Env::<ForkDb, RandomValidator>::init(&url_str, None, RandomValidator {});
+            if let Some(path) = cache {
+                env.get_request_history().save(path).unwrap();
+            }
+        }
+    }
+}
 
+fn print_dai_info<D: DB>(env: &mut Env<D, RandomValidator>) {
     let dai_address = address_from_hex("0x6B175474E89094C44Da98b954EedeAC495271d0F");
 
     let decimals = env
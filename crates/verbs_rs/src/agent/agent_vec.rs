@@ -6,12 +6,15 @@
 //! record and retrieve simulated agent data.
 //!
 
+use crate::agent::assert_unique_addresses;
 use crate::agent::traits::{Agent, AgentSet, RecordedAgent, RecordedAgentSet};
 use crate::contract::Transaction;
 use crate::env::{Env, Validator};
+use crate::rng;
 use crate::DB;
 use alloy_primitives::Address;
 use rand::RngCore;
+use serde::{Deserialize, Serialize};
 use std::mem;
 
 /// Implementation of agent set storing agents as a vector
@@ -28,7 +31,7 @@ use std::mem;
 /// use verbs_rs::agent::{Agent, RecordedAgent, AgentVec, AgentSet};
 /// use verbs_rs::contract::Transaction;
 ///
-/// struct DummyAgent{}
+/// struct DummyAgent(Address);
 ///
 /// impl Agent for DummyAgent {
 ///     fn update<D: DB, V: Validator, R: RngCore>(
@@ -38,7 +41,7 @@ use std::mem;
 ///     }
 ///
 ///     fn get_address(&self) -> Address {
-///         Address::ZERO
+///         self.0
 ///     }
 /// }
 ///
@@ -49,11 +52,12 @@ use std::mem;
 /// }
 ///
 /// let agent_vec = AgentVec::<bool, DummyAgent>::from(
-///     vec![DummyAgent{}, DummyAgent{}]
+///     vec![DummyAgent(Address::ZERO), DummyAgent(Address::repeat_byte(1))]
 /// );
 ///
 /// let addresses = agent_vec.get_addresses();
 /// ```
+#[derive(Serialize, Deserialize)]
 pub struct AgentVec<R, A: Agent + RecordedAgent<R>> {
     /// Vector of agents of a single type
     agents: Vec<A>,
@@ -84,7 +88,12 @@ impl<R, A: Agent + RecordedAgent<R>> AgentVec<R, A> {
     ///
     /// * `agents` - Vector af agents of this type
     ///
+    /// # Panics
+    ///
+    /// Panics if several agents share the same address
+    ///
     pub fn from(agents: Vec<A>) -> Self {
+        assert_unique_addresses(agents.iter().map(|x| x.get_address()));
         AgentVec {
             agents,
             records: Vec::<Vec<R>>::new(),
@@ -96,7 +105,17 @@ impl<R, A: Agent + RecordedAgent<R>> AgentVec<R, A> {
     ///
     /// * `agent` - Agents of this type
     ///
+    /// # Panics
+    ///
+    /// Panics if an agent in the set has the same address
+    ///
     pub fn add_agent(&mut self, agent: A) {
+        let address = agent.get_address();
+        assert!(
+            self.agents.iter().all(|x| x.get_address() != address),
+            "Duplicate agent address {}",
+            address
+        );
         self.agents.push(agent);
     }
     /// Get the recorded history of agents in this set.
@@ -120,6 +139,11 @@ impl<R: 'static, A: Agent + RecordedAgent<R> + 'static> AgentSet for AgentVec<R,
     /// the agents, and collecting any submitted transactions into
     /// a single vector.
     ///
+    /// Each agent is given an independent random generator derived
+    /// from a seed drawn from `rng` and the address of the agent
+    /// (agents with duplicate addresses are rejected when they
+    /// are added to the set).
+    ///
     /// # Arguments
     ///
     /// * `rng` - Random generator
@@ -130,9 +154,13 @@ impl<R: 'static, A: Agent + RecordedAgent<R> + 'static> AgentSet for AgentVec<R,
         rng: &mut RG,
         network: &mut Env<D, V>,
     ) -> Vec<Transaction> {
+        let seed = rng.next_u64();
         self.agents
             .iter_mut()
-            .flat_map(|x| x.update(rng, network))
+            .flat_map(|x| {
+                let mut agent_rng = rng::stream(seed, rng::address_id(x.get_address()));
+                x.update(&mut agent_rng, network)
+            })
             .collect()
     }
     /// Record the current state of the agents in this set
@@ -249,4 +277,74 @@ mod tests {
         assert_eq!(records[0], vec![0, 1]);
         assert_eq!(records[1], vec![1, 2]);
     }
+
+    struct RandomAgent {
+        address: Address,
+        value: u64,
+    }
+
+    impl traits::Agent for RandomAgent {
+        fn update<D: DB, V: Validator, RG: RngCore>(
+            &mut self,
+            rng: &mut RG,
+            _network: &mut crate::env::Env<D, V>,
+        ) -> Vec<crate::contract::Transaction> {
+            self.value = rng.next_u64();
+            Vec::default()
+        }
+
+        fn get_address(&self) -> Address {
+            self.address
+        }
+    }
+
+    impl traits::RecordedAgent<u64> for RandomAgent {
+        fn record<D: DB, V: Validator>(&mut self, _env: &mut Env<D, V>) -> u64 {
+            self.value
+        }
+    }
+
+    #[rstest]
+    fn test_agent_streams(mut env: Env<LocalDB, RandomValidator>) {
+        let agent = |i: u128| RandomAgent {
+            address: Address::from(Uint::from(i)),
+            value: 0,
+        };
+
+        let mut agents_a = AgentVec::from(vec![agent(1), agent(2)]);
+        let mut agents_b = AgentVec::from(vec![agent(3), agent(1), agent(2)]);
+
+        agents_a.call(&mut rng(), &mut env);
+        agents_b.call(&mut rng(), &mut env);
+        agents_a.record(&mut env);
+        agents_b.record(&mut env);
+
+        let records_a = agents_a.take_records();
+        let records_b = agents_b.take_records();
+
+        // Draws are unchanged by the additional agent
+        assert_eq!(records_a[0], records_b[0][1..]);
+        assert_ne!(records_a[0][0], records_a[0][1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate agent address")]
+    fn test_duplicate_addresses() {
+        let agent = || RandomAgent {
+            address: Address::repeat_byte(1),
+            value: 0,
+        };
+        AgentVec::<u64, RandomAgent>::from(vec![agent(), agent()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate agent address")]
+    fn test_add_duplicate_address() {
+        let agent = || RandomAgent {
+            address: Address::repeat_byte(1),
+            value: 0,
+        };
+        let mut agents = AgentVec::<u64, RandomAgent>::from(vec![agent()]);
+        agents.add_agent(agent());
+    }
 }
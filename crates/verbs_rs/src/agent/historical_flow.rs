@@ -0,0 +1,245 @@
+//! Replay of historical transactions as background flow
+
+use crate::agent::traits::AgentSet;
+use crate::contract::Transaction;
+use crate::env::{Env, Validator};
+use crate::{DatabaseError, ForkBackend, HistoricalBlock, DB};
+use alloy_primitives::{Address, U256};
+use rand::RngCore;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Error raised loading historical blocks
+#[derive(Debug, thiserror::Error)]
+pub enum HistoricalFlowError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+    #[error("block {0} was not found")]
+    MissingBlock(U256),
+}
+
+/// Historical transactions submitted as background flow
+///
+/// Agent set that submits the transactions of
+/// consecutive historical blocks (e.g. the mainnet
+/// blocks following the block a simulation was
+/// forked from), one block per simulation step,
+/// alongside the transactions of simulated agents.
+/// Once all the blocks have been submitted no
+/// further transactions are generated.
+///
+/// Transactions are sent from their original senders
+/// (signatures are not checked by the simulation), and
+/// are unchecked, so reverted transactions do not stop
+/// the simulation. The order transactions are executed
+/// in is set by the environment [Validator]. Contract
+/// creation transactions are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use verbs_rs::agent::{AgentSet, HistoricalFlow};
+///
+/// let flow = HistoricalFlow::from_json(r#"[
+///     {
+///         "number": "0x65",
+///         "transactions": [
+///             {
+///                 "from": "0x0101010101010101010101010101010101010101",
+///                 "to": "0x0202020202020202020202020202020202020202",
+///                 "input": "0x",
+///                 "value": "0x1",
+///                 "nonce": "0x0"
+///             }
+///         ]
+///     }
+/// ]"#).unwrap();
+///
+/// assert_eq!(flow.remaining(), 1);
+/// ```
+pub struct HistoricalFlow {
+    /// Blocks still to be submitted
+    blocks: VecDeque<HistoricalBlock>,
+}
+
+impl HistoricalFlow {
+    /// Initialise from a sequence of blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - Blocks, in the order they are submitted
+    ///
+    pub fn new(blocks: Vec<HistoricalBlock>) -> Self {
+        HistoricalFlow {
+            blocks: blocks.into(),
+        }
+    }
+
+    /// Parse blocks from JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - JSON array of block objects, in the
+    ///   format returned by `eth_getBlockByNumber` (with
+    ///   full transactions)
+    ///
+    pub fn from_json(json: &str) -> Result<Self, HistoricalFlowError> {
+        Ok(Self::new(serde_json::from_str(json)?))
+    }
+
+    /// Read blocks from JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader of a JSON array of block objects
+    ///
+    pub fn read<R: Read>(reader: R) -> Result<Self, HistoricalFlowError> {
+        Ok(Self::new(serde_json::from_reader(reader)?))
+    }
+
+    /// Load blocks from a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to a JSON array of block objects
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, HistoricalFlowError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Load the blocks following the fork block of a backend
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Fork backend, e.g. the backend of a
+    ///   [crate::ForkDb]
+    /// * `n_blocks` - Number of blocks to load
+    ///
+    pub fn from_backend(
+        backend: &dyn ForkBackend,
+        n_blocks: usize,
+    ) -> Result<Self, HistoricalFlowError> {
+        let start = backend.block().number;
+        let blocks = (1..=n_blocks)
+            .map(|i| {
+                let number = start + U256::from(i);
+                backend
+                    .block_transactions(number)?
+                    .ok_or(HistoricalFlowError::MissingBlock(number))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(blocks))
+    }
+
+    /// Number of blocks still to be submitted
+    pub fn remaining(&self) -> usize {
+        self.blocks.len()
+    }
+}
+
+impl AgentSet for HistoricalFlow {
+    /// Submit the transactions of the next block
+    fn call<D: DB, V: Validator, R: RngCore>(
+        &mut self,
+        _rng: &mut R,
+        _env: &mut Env<D, V>,
+    ) -> Vec<Transaction> {
+        match self.blocks.pop_front() {
+            Some(block) => block
+                .transactions
+                .iter()
+                .filter_map(|tx| tx.to_transaction())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn record<D: DB, V: Validator>(&mut self, _env: &mut Env<D, V>) {}
+
+    fn get_addresses(&self) -> Vec<Address> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::RandomValidator;
+    use crate::{ForkDb, HistoricalTransaction, MockBackend};
+    use alloy_primitives::Bytes;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar;
+    use revm::primitives::AccountInfo;
+    use std::sync::Arc;
+
+    fn transfer(from: u8, to: u8, nonce: u64, value: u64) -> HistoricalTransaction {
+        HistoricalTransaction {
+            hash: Default::default(),
+            from: Address::repeat_byte(from),
+            to: Some(Address::repeat_byte(to)),
+            input: Bytes::new(),
+            value: U256::from(value),
+            nonce: U256::from(nonce),
+            gas_price: None,
+            max_priority_fee_per_gas: None,
+        }
+    }
+
+    #[test]
+    fn test_replay_from_backend() {
+        let mut backend = MockBackend::new(U256::from(100), U256::from(1_700_000_000));
+        backend.insert_account(
+            Address::repeat_byte(1),
+            AccountInfo::from_balance(U256::from(1000)),
+        );
+        backend.insert_account(Address::repeat_byte(2), AccountInfo::default());
+        backend.insert_block(HistoricalBlock {
+            number: U256::from(101),
+            transactions: vec![transfer(1, 2, 5, 100), transfer(1, 2, 6, 200)],
+        });
+        backend.insert_block(HistoricalBlock {
+            number: U256::from(102),
+            transactions: vec![transfer(1, 2, 7, 300)],
+        });
+        let backend = Arc::new(backend);
+
+        assert!(matches!(
+            HistoricalFlow::from_backend(backend.as_ref(), 3),
+            Err(HistoricalFlowError::MissingBlock(_))
+        ));
+
+        let mut flow = HistoricalFlow::from_backend(backend.as_ref(), 2).unwrap();
+        assert_eq!(flow.remaining(), 2);
+
+        let mut env =
+            Env::<ForkDb, RandomValidator>::init_with_backend(backend, RandomValidator {});
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        for step in 0..3 {
+            let transactions = flow.call(&mut rng, &mut env);
+            env.process_transactions(transactions, &mut rng, step);
+        }
+
+        assert_eq!(flow.remaining(), 0);
+        assert_eq!(env.last_events.len(), 3);
+        assert!(env.last_events.iter().all(|e| e.success));
+
+        let balance = |env: &Env<ForkDb, RandomValidator>, i: u8| {
+            env.view()
+                .db()
+                .accounts()
+                .get(&Address::repeat_byte(i))
+                .unwrap()
+                .info
+                .balance
+        };
+        assert_eq!(balance(&env, 1), U256::from(400));
+        assert_eq!(balance(&env, 2), U256::from(600));
+    }
+}
@@ -6,9 +6,34 @@
 //!
 
 pub mod agent_vec;
+pub mod historical_flow;
+pub mod parallel_agent_vec;
 pub mod singleton_agent;
 pub mod traits;
 
 pub use agent_vec::*;
+pub use historical_flow::*;
+pub use parallel_agent_vec::*;
 pub use singleton_agent::*;
 pub use traits::*;
+
+use alloy_primitives::Address;
+use std::collections::HashSet;
+
+/// Panic if an address is shared by several agents
+///
+/// Random streams of agents are derived from
+/// their address (see [crate::rng]), so agents
+/// with the same address would draw identical
+/// random values.
+///
+/// # Arguments
+///
+/// * `addresses` - Addresses of agents in a set
+///
+pub(crate) fn assert_unique_addresses<I: IntoIterator<Item = Address>>(addresses: I) {
+    let mut seen = HashSet::new();
+    for address in addresses {
+        assert!(seen.insert(address), "Duplicate agent address {}", address);
+    }
+}
@@ -0,0 +1,281 @@
+//! Vector of simulation agents updated in parallel
+//!
+//! Data structure that stores a vector of agents
+//! of one type, where the agents are updated in
+//! parallel using a read-only view of the
+//! simulation environment.
+//!
+
+use crate::agent::assert_unique_addresses;
+use crate::agent::traits::{AgentSet, ParallelAgent, RecordedAgent, RecordedAgentSet};
+use crate::contract::Transaction;
+use crate::env::{Env, Validator};
+use crate::rng;
+use crate::DB;
+use alloy_primitives::Address;
+use rand::RngCore;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::mem;
+
+/// Implementation of agent set updating agents in parallel
+///
+/// Stores a vector of agents of a single type, and stores
+/// records of their state. Agents are updated in parallel
+/// on the [rayon] thread-pool, each with their own random
+/// generator (derived from the address of the agent, see
+/// [crate::rng]) and a shared read-only view of the
+/// simulation environment. Transactions are collected in
+/// the order of agents in the set, so updates are
+/// deterministic for a given random seed.
+///
+/// # Examples
+///
+/// ```
+/// use rand::RngCore;
+/// use alloy_primitives::Address;
+/// use verbs_rs::{DB, env::{EnvView, Validator, Env}};
+/// use verbs_rs::agent::{ParallelAgent, RecordedAgent, ParallelAgentVec, AgentSet};
+/// use verbs_rs::contract::Transaction;
+///
+/// struct DummyAgent(Address);
+///
+/// impl ParallelAgent for DummyAgent {
+///     fn update<D: DB, R: RngCore>(
+///         &mut self, rng: &mut R, env: &EnvView<D>
+///     ) -> Vec<Transaction> {
+///         Vec::default()
+///     }
+///
+///     fn get_address(&self) -> Address {
+///         self.0
+///     }
+/// }
+///
+/// impl RecordedAgent<bool> for DummyAgent {
+///     fn record<D: DB, V: Validator>(&mut self, _env: &mut Env<D, V>) -> bool {
+///         true
+///     }
+/// }
+///
+/// let agent_vec = ParallelAgentVec::<bool, DummyAgent>::from(
+///     vec![DummyAgent(Address::ZERO), DummyAgent(Address::repeat_byte(1))]
+/// );
+///
+/// let addresses = agent_vec.get_addresses();
+/// ```
+#[derive(Serialize, Deserialize)]
+pub struct ParallelAgentVec<R, A: ParallelAgent + RecordedAgent<R>> {
+    /// Vector of agents of a single type
+    agents: Vec<A>,
+    /// Records of agent states over the course of the simulation
+    records: Vec<Vec<R>>,
+}
+
+impl<R, A: ParallelAgent + RecordedAgent<R>> Default for ParallelAgentVec<R, A> {
+    fn default() -> Self {
+        ParallelAgentVec {
+            agents: Vec::<A>::new(),
+            records: Vec::<Vec<R>>::new(),
+        }
+    }
+}
+
+impl<R, A: ParallelAgent + RecordedAgent<R>> ParallelAgentVec<R, A> {
+    /// Initialise an empty vector agent-set
+    pub fn new() -> Self {
+        ParallelAgentVec {
+            agents: Vec::<A>::new(),
+            records: Vec::<Vec<R>>::new(),
+        }
+    }
+    /// Initialise an agent-vec from an existing vector of agents
+    ///
+    /// # Arguments
+    ///
+    /// * `agents` - Vector af agents of this type
+    ///
+    /// # Panics
+    ///
+    /// Panics if several agents share the same address
+    ///
+    pub fn from(agents: Vec<A>) -> Self {
+        assert_unique_addresses(agents.iter().map(|x| x.get_address()));
+        ParallelAgentVec {
+            agents,
+            records: Vec::<Vec<R>>::new(),
+        }
+    }
+    /// Insert an agent into the set.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent` - Agents of this type
+    ///
+    /// # Panics
+    ///
+    /// Panics if an agent in the set has the same address
+    ///
+    pub fn add_agent(&mut self, agent: A) {
+        let address = agent.get_address();
+        assert!(
+            self.agents.iter().all(|x| x.get_address() != address),
+            "Duplicate agent address {}",
+            address
+        );
+        self.agents.push(agent);
+    }
+    /// Get the recorded history of agents in this set.
+    pub fn get_records(&self) -> &Vec<Vec<R>> {
+        &self.records
+    }
+}
+
+impl<R, A: ParallelAgent + RecordedAgent<R>> RecordedAgentSet<R> for ParallelAgentVec<R, A> {
+    /// Take the vector of agent records from the set
+    fn take_records(&mut self) -> Vec<Vec<R>> {
+        mem::take(&mut self.records)
+    }
+}
+
+/// Implementations of agent updates and recording.
+impl<R: 'static, A: ParallelAgent + RecordedAgent<R> + 'static> AgentSet
+    for ParallelAgentVec<R, A>
+{
+    /// Call the agents in parallel and collect any returned EVM transactions
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - Random generator, used to seed the
+    ///   generators of the individual agents
+    /// * `env` - Simulation environment
+    ///
+    fn call<D: DB, V: Validator, RG: RngCore>(
+        &mut self,
+        rng: &mut RG,
+        env: &mut Env<D, V>,
+    ) -> Vec<Transaction> {
+        let seed = rng.next_u64();
+        let view = env.view();
+
+        let transactions: Vec<Vec<Transaction>> = self
+            .agents
+            .par_iter_mut()
+            .map(|x| {
+                let mut agent_rng = rng::stream(seed, rng::address_id(x.get_address()));
+                x.update(&mut agent_rng, &view)
+            })
+            .collect();
+
+        transactions.into_iter().flatten().collect()
+    }
+    /// Record the current state of the agents in this set
+    fn record<D: DB, V: Validator>(&mut self, env: &mut Env<D, V>) {
+        let records: Vec<R> = self.agents.iter_mut().map(|x| x.record(env)).collect();
+        self.records.push(records);
+    }
+    /// Get the addresses of the agents in this set.
+    fn get_addresses(&self) -> Vec<Address> {
+        self.agents.iter().map(|x| x.get_address()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::{EnvView, RandomValidator};
+    use crate::LocalDB;
+    use alloy_primitives::{Uint, U256};
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar;
+    use rstest::*;
+
+    struct TestAgent {
+        address: Address,
+        value: u64,
+    }
+
+    impl ParallelAgent for TestAgent {
+        fn update<D: DB, RG: RngCore>(
+            &mut self,
+            rng: &mut RG,
+            _env: &EnvView<D>,
+        ) -> Vec<Transaction> {
+            self.value = rng.next_u64();
+            vec![Transaction {
+                function_selector: [0, 0, 0, 0],
+                callee: self.address,
+                transact_to: Address::ZERO,
+                args: Vec::default(),
+                value: U256::ZERO,
+                checked: false,
+                gas_priority_fee: None,
+                nonce: None,
+            }]
+        }
+
+        fn get_address(&self) -> Address {
+            self.address
+        }
+    }
+
+    impl RecordedAgent<u64> for TestAgent {
+        fn record<D: DB, V: Validator>(&mut self, _env: &mut Env<D, V>) -> u64 {
+            self.value
+        }
+    }
+
+    #[fixture]
+    fn env() -> Env<LocalDB, RandomValidator> {
+        Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {})
+    }
+
+    fn agents(n: u64) -> ParallelAgentVec<u64, TestAgent> {
+        ParallelAgentVec::from(
+            (0..n)
+                .map(|i| TestAgent {
+                    address: Address::from(Uint::from(i)),
+                    value: 0,
+                })
+                .collect(),
+        )
+    }
+
+    #[rstest]
+    fn test_parallel_agent_vec(mut env: Env<LocalDB, RandomValidator>) {
+        let mut rng_a = Xoroshiro128StarStar::seed_from_u64(101);
+        let mut rng_b = Xoroshiro128StarStar::seed_from_u64(101);
+
+        let mut agents_a = agents(100);
+        let mut agents_b = agents(100);
+
+        let calls_a = agents_a.call(&mut rng_a, &mut env);
+        let calls_b = agents_b.call(&mut rng_b, &mut env);
+
+        assert_eq!(calls_a.len(), 100);
+
+        let callees_a: Vec<Address> = calls_a.iter().map(|x| x.callee).collect();
+        let callees_b: Vec<Address> = calls_b.iter().map(|x| x.callee).collect();
+        assert_eq!(callees_a, agents_a.get_addresses());
+        assert_eq!(callees_a, callees_b);
+
+        agents_a.record(&mut env);
+        agents_b.record(&mut env);
+
+        let records_a = agents_a.take_records();
+        let records_b = agents_b.take_records();
+
+        assert_eq!(records_a, records_b);
+        assert_ne!(records_a[0][0], records_a[0][1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate agent address")]
+    fn test_duplicate_addresses() {
+        let mut agents = agents(2);
+        agents.add_agent(TestAgent {
+            address: Address::from(Uint::from(1)),
+            value: 0,
+        });
+    }
+}
@@ -6,6 +6,7 @@ use crate::env::{Env, Validator};
 use crate::DB;
 use alloy_primitives::Address;
 use rand::RngCore;
+use serde::{Deserialize, Serialize};
 use std::mem;
 
 /// Implementation of agent set for a single agent
@@ -48,6 +49,7 @@ use std::mem;
 ///
 /// let addresses = singleton_agent.get_addresses();
 /// ```
+#[derive(Serialize, Deserialize)]
 pub struct SingletonAgent<R, A: Agent + RecordedAgent<R>> {
     /// Single agent in this set
     agent: A,
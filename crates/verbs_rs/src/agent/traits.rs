@@ -9,6 +9,9 @@
 //!   an agent type
 //! * [Agent] is an individual agent that may be member of an
 //!   [AgentSet]
+//! * [ParallelAgent] is an individual agent that only reads
+//!   from the EVM during its update, allowing groups of these
+//!   agents to be updated in parallel
 //!
 //! Implementers have the flexibility to only use part of this
 //! structure though, for instance an implementation of
@@ -21,7 +24,7 @@
 //!
 
 use crate::contract::Transaction;
-use crate::env::{Env, Validator};
+use crate::env::{Env, EnvView, Validator};
 use crate::DB;
 use alloy_primitives::Address;
 use rand::RngCore;
@@ -133,6 +136,54 @@ pub trait Agent {
     fn get_address(&self) -> Address;
 }
 
+/// Trait defining behaviour for an agent that can be updated in parallel
+///
+/// Similar to [Agent], but the agent is only given a
+/// read-only [EnvView] of the simulation environment
+/// during its update. This allows a collection of these
+/// agents (e.g. [super::ParallelAgentVec]) to be updated
+/// across multiple threads.
+///
+/// # Examples
+///
+/// ```
+/// use rand::RngCore;
+/// use alloy_primitives::Address;
+/// use verbs_rs::DB;
+/// use verbs_rs::env::EnvView;
+/// use verbs_rs::agent::ParallelAgent;
+/// use verbs_rs::contract::Transaction;
+///
+/// struct DummyAgent{
+///     state: i32,
+/// }
+///
+/// impl ParallelAgent for DummyAgent {
+///     fn update<D: DB, R: RngCore>(
+///         &mut self, rng: &mut R, env: &EnvView<D>
+///     ) -> Vec<Transaction> {
+///         self.state += 1;
+///         Vec::default()
+///     }
+///
+///     fn get_address(&self) -> Address {
+///         Address::ZERO
+///     }
+/// }
+/// ```
+pub trait ParallelAgent: Send {
+    /// Update the agent and optionally return a [Transaction]
+    ///
+    /// # Arguments
+    ///
+    /// * `rng`: Random generator, unique to this agent
+    /// * `env`: Read-only view of the simulation environment
+    ///
+    fn update<D: DB, R: RngCore>(&mut self, rng: &mut R, env: &EnvView<D>) -> Vec<Transaction>;
+    /// Get the address of the agent.
+    fn get_address(&self) -> Address;
+}
+
 /// Trait used to record the state of the agent over the course of the simulation
 ///
 /// Each step this is called after the state of the simulation
@@ -0,0 +1,161 @@
+//! Batch simulation execution
+//!
+//! Run a simulation across a set of parameter
+//! samples and random seeds, where each
+//! simulation sample is initialised from a copy
+//! of a base environment (e.g. an environment
+//! with a [crate::LocalDB] initialised from
+//! a [crate::RequestCache]).
+//!
+//! Samples are run in parallel on the [rayon]
+//! thread-pool. The number of threads can be
+//! controlled by running inside a custom pool
+//! using [rayon::ThreadPool::install].
+//!
+
+use crate::env::{Env, Validator};
+use crate::DB;
+use rayon::prelude::*;
+
+/// Samples generated from a set of parameters
+pub struct BatchResult<P, R> {
+    /// Parameters used to generate the samples
+    pub params: P,
+    /// Results of each simulation sample, in the
+    /// same order as the seeds
+    pub samples: Vec<R>,
+}
+
+/// Run a batch of simulations across a set of parameters
+///
+/// Runs a simulation for each combination of parameters
+/// and random seeds. Each simulation is passed a clone
+/// of the base environment, so samples do not share
+/// any state.
+///
+/// # Arguments
+///
+/// * `env` - Base simulation environment, cloned
+///   for each sample
+/// * `params` - Simulation parameter samples
+/// * `seeds` - Random seeds, a sample is run for each
+///   seed for each set of parameters
+/// * `sim_func` - Simulation function, called with
+///   the sample environment, parameters and seed
+///
+/// # Examples
+///
+/// ```
+/// use alloy_primitives::U256;
+/// use verbs_rs::batch_runner::batch_run;
+/// use verbs_rs::env::{Env, RandomValidator};
+/// use verbs_rs::LocalDB;
+///
+/// let env = Env::<LocalDB, RandomValidator>::init(
+///     U256::ZERO, U256::ZERO, RandomValidator {}
+/// );
+///
+/// let results = batch_run(&env, &[1u64, 2u64], &[101, 102, 103], |_env, p, seed| {
+///     // Run the simulation and return results
+///     p * seed
+/// });
+///
+/// assert_eq!(results.len(), 2);
+/// assert_eq!(results[1].samples, vec![202, 204, 206]);
+/// ```
+pub fn batch_run<D, V, P, R, F>(
+    env: &Env<D, V>,
+    params: &[P],
+    seeds: &[u64],
+    sim_func: F,
+) -> Vec<BatchResult<P, R>>
+where
+    D: DB + Clone + Send,
+    V: Validator + Clone + Send + Sync,
+    P: Clone + Sync,
+    R: Send,
+    F: Fn(Env<D, V>, &P, u64) -> R + Sync,
+{
+    let samples: Vec<(&P, u64)> = params
+        .iter()
+        .flat_map(|p| seeds.iter().map(move |s| (p, *s)))
+        .collect();
+
+    let results: Vec<R> = samples
+        .into_par_iter()
+        .map(|(p, seed)| sim_func(env.clone(), p, seed))
+        .collect();
+
+    let mut results = results.into_iter();
+
+    params
+        .iter()
+        .map(|p| BatchResult {
+            params: p.clone(),
+            samples: results.by_ref().take(seeds.len()).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::RandomValidator;
+    use crate::LocalDB;
+    use alloy_primitives::{Address, U256};
+    use rstest::*;
+
+    #[fixture]
+    fn env() -> Env<LocalDB, RandomValidator> {
+        Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {})
+    }
+
+    #[rstest]
+    fn test_batch_run(env: Env<LocalDB, RandomValidator>) {
+        let address = Address::repeat_byte(1);
+
+        let results = batch_run(&env, &[10u64, 20u64], &[1, 2, 3], |mut env, p, seed| {
+            env.insert_account(address, U256::from(p + seed));
+            env.evm_state()
+                .context
+                .evm
+                .db
+                .accounts
+                .get(&address)
+                .unwrap()
+                .info
+                .balance
+        });
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].params, 10);
+        assert_eq!(results[1].params, 20);
+        assert_eq!(
+            results[0].samples,
+            vec![U256::from(11), U256::from(12), U256::from(13)]
+        );
+        assert_eq!(
+            results[1].samples,
+            vec![U256::from(21), U256::from(22), U256::from(23)]
+        );
+
+        // Base environment should be unchanged
+        assert!(!env.view().db().accounts.contains_key(&address));
+    }
+
+    #[rstest]
+    fn test_layered_batch_run(env: Env<LocalDB, RandomValidator>) {
+        let address = Address::repeat_byte(1);
+        let env = env.into_layered();
+
+        let results = batch_run(&env, &[10u64], &[1, 2], |mut env, p, seed| {
+            env.insert_account(address, U256::from(p + seed));
+            let db = &env.evm_state().context.evm.db;
+            // Samples share the base DB
+            (db.accounts.len(), db.base().accounts.len())
+        });
+
+        assert_eq!(results[0].samples, vec![(1, 1), (1, 1)]);
+        assert!(!env.view().db().accounts.contains_key(&address));
+    }
+}
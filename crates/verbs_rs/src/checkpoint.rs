@@ -0,0 +1,392 @@
+//! Simulation checkpoints
+//!
+//! Save the full state of a simulation (agents and
+//! environment) to disk, and resume the simulation
+//! from that state in a new process.
+//!
+//! A checkpoint stores the simulation seed and the
+//! step to resume from, the serialized agents, and
+//! the environment (DB, EVM block environment, event
+//! buffers and validator). Since the random values
+//! used each step only depend on the seed and the
+//! step (see [crate::rng]), a resumed simulation
+//! produces identical results to an uninterrupted run.
+//!
+//! Checkpoints require the DB, validator and agents
+//! to implement [serde::Serialize] and
+//! [serde::Deserialize], e.g. a [crate::LocalDB].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use verbs_rs::agent::{AgentSet, AgentVec, SimState};
+//! use verbs_rs::checkpoint::load_checkpoint;
+//! # use verbs_rs::agent::{Agent, RecordedAgent};
+//! # use verbs_rs::contract::Transaction;
+//! # use verbs_rs::env::{Env, Validator, RandomValidator};
+//! # use verbs_rs::{DB, LocalDB};
+//! # use alloy_primitives::Address;
+//! # use rand::RngCore;
+//! # use serde::{Serialize, Deserialize};
+//! #
+//! # #[derive(Serialize, Deserialize)]
+//! # struct DummyAgent{}
+//! #
+//! # impl Agent for DummyAgent {
+//! #     fn update<D: DB, V: Validator, R: RngCore>(
+//! #         &mut self, _rng: &mut R, _env: &mut Env<D, V>
+//! #     ) -> Vec<Transaction> { Vec::default() }
+//! #     fn get_address(&self) -> Address { Address::ZERO }
+//! # }
+//! #
+//! # impl RecordedAgent<bool> for DummyAgent {
+//! #     fn record<D: DB, V: Validator>(&mut self, _env: &mut Env<D, V>) -> bool { true }
+//! # }
+//!
+//! #[derive(SimState, Serialize, Deserialize)]
+//! struct Agents {
+//!     a: AgentVec::<bool, DummyAgent>,
+//! }
+//!
+//! // Load a checkpoint written using SimRunner::checkpoint_every
+//! let checkpoint = load_checkpoint::<Agents, LocalDB, RandomValidator, _>(
+//!     "checkpoint.bin"
+//! ).unwrap();
+//! let (runner, mut agents, mut env) = checkpoint.into_runner();
+//!
+//! let result = runner
+//!     .max_steps(1_000_000)
+//!     .checkpoint_every(10_000, "checkpoint.bin")
+//!     .run(&mut env, &mut agents);
+//! ```
+
+use crate::agent::SimState;
+use crate::env::{Env, Validator};
+use crate::sim_runner::SimRunner;
+use crate::DB;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Magic bytes identifying a checkpoint file
+const CHECKPOINT_MAGIC: [u8; 8] = *b"VERBSCKP";
+/// Version of the checkpoint format
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// Error raised saving or loading a checkpoint
+#[derive(Debug, thiserror::Error)]
+pub enum CheckpointError {
+    #[error("file is not a simulation checkpoint")]
+    InvalidFile,
+    #[error("unsupported checkpoint version {0} (expected {CHECKPOINT_VERSION})")]
+    Version(u32),
+    #[error("failed to encode/decode checkpoint: {0}")]
+    Encoding(#[from] bincode::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Checkpoint file header
+#[derive(Serialize, Deserialize)]
+struct Header {
+    magic: [u8; 8],
+    version: u32,
+}
+
+#[derive(Serialize)]
+struct CheckpointRef<'a, S, D: DB, V: Validator> {
+    seed: u64,
+    step: usize,
+    agents: &'a S,
+    env: &'a Env<D, V>,
+}
+
+/// Saved state of a simulation
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "S: DeserializeOwned, D: DeserializeOwned, V: DeserializeOwned"))]
+pub struct Checkpoint<S, D: DB, V: Validator> {
+    /// Random seed of the simulation
+    pub seed: u64,
+    /// Step the simulation resumes from
+    pub step: usize,
+    /// Simulation agents
+    pub agents: S,
+    /// Simulation environment
+    pub env: Env<D, V>,
+}
+
+impl<S: SimState, D: DB, V: Validator> Checkpoint<S, D, V> {
+    /// Create a runner resuming from this checkpoint
+    ///
+    /// Returns a [SimRunner] initialised with the
+    /// seed and start step of the checkpoint, and
+    /// the checkpointed agents and environment.
+    pub fn into_runner<'a>(self) -> (SimRunner<'a, S, D, V>, S, Env<D, V>) {
+        (
+            SimRunner::new(self.seed).start_step(self.step),
+            self.agents,
+            self.env,
+        )
+    }
+}
+
+/// Save the state of a simulation to a file
+///
+/// The checkpoint is written to a temporary file
+/// that then replaces the destination file, so an
+/// existing checkpoint is not corrupted if the
+/// process is interrupted while writing.
+///
+/// # Arguments
+///
+/// * `path` - Path of the checkpoint file
+/// * `seed` - Random seed of the simulation
+/// * `step` - Step the simulation should resume from
+/// * `agents` - Simulation agents
+/// * `env` - Simulation environment
+///
+pub fn save_checkpoint<S, D, V, P>(
+    path: P,
+    seed: u64,
+    step: usize,
+    agents: &S,
+    env: &Env<D, V>,
+) -> Result<(), CheckpointError>
+where
+    S: Serialize,
+    D: DB + Serialize,
+    V: Validator + Serialize,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    bincode::serialize_into(
+        &mut writer,
+        &Header {
+            magic: CHECKPOINT_MAGIC,
+            version: CHECKPOINT_VERSION,
+        },
+    )?;
+    bincode::serialize_into(
+        &mut writer,
+        &CheckpointRef {
+            seed,
+            step,
+            agents,
+            env,
+        },
+    )?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    drop(writer);
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Load the state of a simulation from a file
+///
+/// # Arguments
+///
+/// * `path` - Path of the checkpoint file
+///
+pub fn load_checkpoint<S, D, V, P>(path: P) -> Result<Checkpoint<S, D, V>, CheckpointError>
+where
+    S: DeserializeOwned,
+    D: DB + DeserializeOwned,
+    V: Validator + DeserializeOwned,
+    P: AsRef<Path>,
+{
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let header: Header =
+        bincode::deserialize_from(&mut reader).map_err(|_| CheckpointError::InvalidFile)?;
+    if header.magic != CHECKPOINT_MAGIC {
+        return Err(CheckpointError::InvalidFile);
+    }
+    if header.version != CHECKPOINT_VERSION {
+        return Err(CheckpointError::Version(header.version));
+    }
+
+    Ok(bincode::deserialize_from(&mut reader)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentSet, AgentVec, RecordedAgent};
+    use crate::contract::Transaction;
+    use crate::env::RandomValidator;
+    use crate::sim_runner::StopReason;
+    use crate::LocalDB;
+    use alloy_primitives::{Address, U256};
+    use rand::{Rng, RngCore};
+    use rstest::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct TestAgent {
+        address: Address,
+        sent: U256,
+    }
+
+    impl Agent for TestAgent {
+        fn update<D: DB, V: Validator, R: RngCore>(
+            &mut self,
+            rng: &mut R,
+            _env: &mut Env<D, V>,
+        ) -> Vec<Transaction> {
+            let value = U256::from(rng.gen_range(1u64..1000));
+            self.sent += value;
+            vec![Transaction {
+                function_selector: [0, 0, 0, 0],
+                callee: self.address,
+                transact_to: Address::repeat_byte(0xff),
+                args: Vec::default(),
+                value,
+                checked: false,
+                gas_priority_fee: None,
+                nonce: None,
+            }]
+        }
+
+        fn get_address(&self) -> Address {
+            self.address
+        }
+    }
+
+    impl RecordedAgent<U256> for TestAgent {
+        fn record<D: DB, V: Validator>(&mut self, _env: &mut Env<D, V>) -> U256 {
+            self.sent
+        }
+    }
+
+    #[derive(SimState, Serialize, Deserialize)]
+    struct TestState {
+        agents: AgentVec<U256, TestAgent>,
+    }
+
+    fn init() -> (TestState, Env<LocalDB, RandomValidator>) {
+        let mut env =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
+        let agents = (1..4u8)
+            .map(|i| {
+                let address = Address::repeat_byte(i);
+                env.insert_account(address, U256::from(1_000_000u64));
+                TestAgent {
+                    address,
+                    sent: U256::ZERO,
+                }
+            })
+            .collect();
+        (
+            TestState {
+                agents: AgentVec::from(agents),
+            },
+            env,
+        )
+    }
+
+    fn account_infos(env: &mut Env<LocalDB, RandomValidator>) -> Vec<(Address, U256, u64)> {
+        let mut infos: Vec<(Address, U256, u64)> = env
+            .evm_state()
+            .context
+            .evm
+            .db
+            .accounts
+            .iter()
+            .map(|(k, v)| (*k, v.info.balance, v.info.nonce))
+            .collect();
+        infos.sort();
+        infos
+    }
+
+    #[rstest]
+    fn test_checkpoint_write_failure() {
+        let path = std::env::temp_dir()
+            .join(format!("verbs_missing_{}", std::process::id()))
+            .join("checkpoint.bin");
+
+        let (mut agents, mut env) = init();
+        let result = SimRunner::new(101)
+            .max_steps(12)
+            .checkpoint_every(5, path)
+            .run(&mut env, &mut agents);
+
+        assert_eq!(result.n_steps, 12);
+        assert_eq!(result.stop_reason, StopReason::MaxSteps);
+        assert_eq!(result.checkpoint_errors.len(), 2);
+        assert_eq!(result.checkpoint_errors[0].0, 5);
+        assert_eq!(result.checkpoint_errors[1].0, 10);
+        assert!(matches!(
+            result.checkpoint_errors[0].1,
+            CheckpointError::Io(_)
+        ));
+    }
+
+    #[rstest]
+    fn test_resume_checkpoint() {
+        let path = std::env::temp_dir().join(format!("verbs_ckpt_{}.bin", std::process::id()));
+
+        // Uninterrupted run
+        let (mut agents_a, mut env_a) = init();
+        let result_a = SimRunner::new(101)
+            .max_steps(20)
+            .run(&mut env_a, &mut agents_a);
+
+        // Run interrupted after 12 steps, last checkpoint at step 10
+        let (mut agents_b, mut env_b) = init();
+        SimRunner::new(101)
+            .max_steps(12)
+            .checkpoint_every(5, path.clone())
+            .run(&mut env_b, &mut agents_b);
+
+        let checkpoint = load_checkpoint::<TestState, LocalDB, RandomValidator, _>(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(checkpoint.seed, 101);
+        assert_eq!(checkpoint.step, 10);
+        assert_eq!(checkpoint.env.event_history.len(), 27);
+        assert_eq!(checkpoint.env.last_events.len(), 3);
+
+        let (runner, mut agents_c, mut env_c) = checkpoint.into_runner();
+        let result_c = runner.max_steps(20).run(&mut env_c, &mut agents_c);
+
+        assert_eq!(result_c.n_steps, 10);
+        assert_eq!(
+            bincode::serialize(&agents_a).unwrap(),
+            bincode::serialize(&agents_c).unwrap()
+        );
+        assert_eq!(account_infos(&mut env_a), account_infos(&mut env_c));
+        assert_eq!(
+            env_a.evm_state().context.evm.env.block,
+            env_c.evm_state().context.evm.env.block
+        );
+
+        let events_a: Vec<(usize, usize, bool)> = result_a.events[30..]
+            .iter()
+            .map(|e| (e.step, e.sequence, e.success))
+            .collect();
+        let events_c: Vec<(usize, usize, bool)> = result_c
+            .events
+            .iter()
+            .map(|e| (e.step, e.sequence, e.success))
+            .collect();
+        assert_eq!(events_a, events_c);
+    }
+
+    #[rstest]
+    fn test_invalid_checkpoint() {
+        let path = std::env::temp_dir().join(format!("verbs_bad_{}.bin", std::process::id()));
+        fs::write(&path, b"not a checkpoint").unwrap();
+
+        let result = load_checkpoint::<TestState, LocalDB, RandomValidator, _>(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(CheckpointError::InvalidFile)));
+    }
+}
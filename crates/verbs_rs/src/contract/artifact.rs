@@ -0,0 +1,552 @@
+//! Contract build artifacts
+//!
+//! Loads the ABI, bytecode and deployed bytecode of
+//! contracts from the JSON artifacts generated by
+//! Foundry (`out/<File>.sol/<Contract>.json`) and
+//! Hardhat (`artifacts/**/<Contract>.json`) builds.
+//!
+//! Bytecode of contracts using external libraries
+//! contains placeholders that are replaced with the
+//! addresses of the deployed libraries when the
+//! bytecode is linked. Libraries are identified
+//! either by name (e.g. `Math`) or by their fully
+//! qualified name (e.g. `src/Math.sol:Math`).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use alloy_primitives::{Address, U256};
+//! use std::collections::HashMap;
+//! use verbs_rs::contract::Artifact;
+//! use verbs_rs::env::{Env, RandomValidator};
+//! use verbs_rs::LocalDB;
+//!
+//! let mut env = Env::<LocalDB, RandomValidator>::init(
+//!     U256::ZERO, U256::ZERO, RandomValidator {}
+//! );
+//!
+//! let artifacts = Artifact::load_dir("out").unwrap();
+//!
+//! let math = artifacts["src/Math.sol:Math"]
+//!     .deploy(&mut env, Address::ZERO, &HashMap::new(), &())
+//!     .unwrap();
+//! let pool = artifacts["src/Pool.sol:Pool"]
+//!     .deploy(
+//!         &mut env,
+//!         Address::ZERO,
+//!         &HashMap::from([("Math".to_string(), math)]),
+//!         &(U256::from(1000), Address::ZERO),
+//!     )
+//!     .unwrap();
+//! ```
+
+use crate::env::{Env, Validator};
+use crate::DB;
+use alloy_primitives::Address;
+use alloy_sol_types::abi::TokenSeq;
+use alloy_sol_types::{SolType, SolValue};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Error raised loading or linking an artifact
+#[derive(Debug, thiserror::Error)]
+pub enum ArtifactError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid bytecode: {0}")]
+    Hex(#[from] hex::FromHexError),
+    #[error("no address given for library {0}")]
+    UnlinkedLibrary(String),
+    #[error("link reference of {0} is outside the bytecode")]
+    InvalidLinkReference(String),
+    #[error("artifact of {0} has no bytecode")]
+    NoBytecode(String),
+    #[error("found multiple artifacts of {0}")]
+    DuplicateArtifact(String),
+}
+
+/// Position of a library address in bytecode
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkReference {
+    /// Source file of the library
+    pub source: String,
+    /// Name of the library
+    pub library: String,
+    /// Offset of the address in bytes
+    pub start: usize,
+    /// Length of the address in bytes
+    pub length: usize,
+}
+
+/// Bytecode that may require linking
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArtifactBytecode {
+    /// Hex encoded bytecode, including placeholders
+    hex: String,
+    /// Positions of library addresses
+    pub link_references: Vec<LinkReference>,
+}
+
+impl ArtifactBytecode {
+    fn new(object: &str, link_references: LinkReferences) -> Self {
+        let link_references = link_references
+            .into_iter()
+            .flat_map(|(source, libraries)| {
+                libraries.into_iter().flat_map(move |(library, offsets)| {
+                    let source = source.clone();
+                    offsets.into_iter().map(move |x| LinkReference {
+                        source: source.clone(),
+                        library: library.clone(),
+                        start: x.start,
+                        length: x.length,
+                    })
+                })
+            })
+            .collect();
+        ArtifactBytecode {
+            hex: object.strip_prefix("0x").unwrap_or(object).to_string(),
+            link_references,
+        }
+    }
+
+    /// Whether the artifact has no bytecode (e.g. an interface)
+    pub fn is_empty(&self) -> bool {
+        self.hex.is_empty()
+    }
+
+    /// Fully qualified names of the linked libraries
+    pub fn libraries(&self) -> Vec<String> {
+        let mut libraries: Vec<String> = self
+            .link_references
+            .iter()
+            .map(|x| format!("{}:{}", x.source, x.library))
+            .collect();
+        libraries.dedup();
+        libraries
+    }
+
+    /// Link the bytecode
+    ///
+    /// Replaces library placeholders with the addresses
+    /// of the libraries, and decodes the bytecode.
+    ///
+    /// # Arguments
+    ///
+    /// * `libraries` - Addresses of deployed libraries, keyed
+    ///   by library name or fully qualified name
+    ///
+    pub fn link(&self, libraries: &HashMap<String, Address>) -> Result<Vec<u8>, ArtifactError> {
+        let mut hex = self.hex.clone();
+        for reference in self.link_references.iter() {
+            let name = format!("{}:{}", reference.source, reference.library);
+            let address = libraries
+                .get(&name)
+                .or_else(|| libraries.get(&reference.library))
+                .ok_or_else(|| ArtifactError::UnlinkedLibrary(name.clone()))?;
+            let range = 2 * reference.start..2 * (reference.start + reference.length);
+            if reference.length != Address::len_bytes() || range.end > hex.len() {
+                return Err(ArtifactError::InvalidLinkReference(name));
+            }
+            hex.replace_range(range, &hex::encode(address));
+        }
+        Ok(hex::decode(hex)?)
+    }
+}
+
+/// Contract ABI and bytecode loaded from a build artifact
+#[derive(Clone, Debug)]
+pub struct Artifact {
+    /// Name of the contract, if included in the artifact
+    pub name: Option<String>,
+    /// Source file of the contract, if included in the artifact
+    pub source: Option<String>,
+    /// JSON ABI of the contract
+    pub abi: Value,
+    /// Deployment bytecode
+    pub bytecode: ArtifactBytecode,
+    /// Runtime bytecode
+    pub deployed_bytecode: ArtifactBytecode,
+}
+
+/// Offset of a library address
+#[derive(Deserialize)]
+struct Offset {
+    start: usize,
+    length: usize,
+}
+
+/// Link references grouped by source file and library
+type LinkReferences = BTreeMap<String, BTreeMap<String, Vec<Offset>>>;
+
+/// Foundry bytecode objects, or Hardhat hex strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BytecodeField {
+    Object {
+        object: String,
+        #[serde(default, rename = "linkReferences")]
+        link_references: LinkReferences,
+    },
+    Hex(String),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawArtifact {
+    #[serde(default)]
+    contract_name: Option<String>,
+    #[serde(default)]
+    source_name: Option<String>,
+    #[serde(default)]
+    metadata: Option<Value>,
+    abi: Value,
+    bytecode: BytecodeField,
+    #[serde(default)]
+    deployed_bytecode: Option<BytecodeField>,
+    #[serde(default)]
+    link_references: LinkReferences,
+    #[serde(default)]
+    deployed_link_references: LinkReferences,
+}
+
+fn bytecode_from_field(field: BytecodeField, link_references: LinkReferences) -> ArtifactBytecode {
+    match field {
+        BytecodeField::Object {
+            object,
+            link_references,
+        } => ArtifactBytecode::new(&object, link_references),
+        BytecodeField::Hex(hex) => ArtifactBytecode::new(&hex, link_references),
+    }
+}
+
+/// Source file and name of the contract compiled
+/// to a Foundry artifact, from its metadata
+fn compilation_target(metadata: &Option<Value>) -> Option<(String, String)> {
+    let targets = metadata
+        .as_ref()?
+        .get("settings")?
+        .get("compilationTarget")?
+        .as_object()?;
+    let (source, name) = targets.iter().next()?;
+    Some((source.clone(), name.as_str()?.to_string()))
+}
+
+impl From<RawArtifact> for Artifact {
+    fn from(value: RawArtifact) -> Self {
+        let target = compilation_target(&value.metadata);
+        Artifact {
+            name: value
+                .contract_name
+                .or_else(|| target.as_ref().map(|x| x.1.clone())),
+            source: value.source_name.or_else(|| target.map(|x| x.0)),
+            abi: value.abi,
+            bytecode: bytecode_from_field(value.bytecode, value.link_references),
+            deployed_bytecode: value
+                .deployed_bytecode
+                .map(|x| bytecode_from_field(x, value.deployed_link_references))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Artifact {
+    /// Parse an artifact from JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - Foundry or Hardhat JSON artifact
+    ///
+    pub fn from_json(json: &str) -> Result<Self, ArtifactError> {
+        Ok(serde_json::from_str::<RawArtifact>(json)?.into())
+    }
+
+    /// Read an artifact
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader of a Foundry or Hardhat JSON artifact
+    ///
+    pub fn read<R: Read>(reader: R) -> Result<Self, ArtifactError> {
+        Ok(serde_json::from_reader::<_, RawArtifact>(reader)?.into())
+    }
+
+    /// Load an artifact file
+    ///
+    /// If the artifact does not include the contract
+    /// name (i.e. Foundry artifacts without metadata),
+    /// the name is taken from the file name, ignoring
+    /// any compiler version (e.g. `Pool.0.8.20.json`).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to a Foundry or Hardhat JSON artifact
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ArtifactError> {
+        let path = path.as_ref();
+        let mut artifact = Self::read(BufReader::new(File::open(path)?))?;
+        if artifact.name.is_none() {
+            artifact.name = path
+                .file_name()
+                .and_then(|x| x.to_string_lossy().split('.').next().map(str::to_string));
+        }
+        Ok(artifact)
+    }
+
+    /// Load all the artifacts in a build directory
+    ///
+    /// Recursively loads the artifacts in a directory
+    /// (e.g. Foundry's `out` or Hardhat's `artifacts`
+    /// directories), keyed by fully qualified name (see
+    /// [Artifact::qualified_name]). Other JSON files
+    /// (e.g. build info and Hardhat debug files) are
+    /// skipped.
+    ///
+    /// Returns an error if multiple artifacts have the
+    /// same key, e.g. a contract compiled with multiple
+    /// compiler versions, in which case artifacts should
+    /// be loaded individually with [Artifact::load].
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Path to the build directory
+    ///
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<HashMap<String, Self>, ArtifactError> {
+        let mut artifacts = HashMap::new();
+        load_dir_into(dir.as_ref(), &mut artifacts)?;
+        Ok(artifacts)
+    }
+
+    /// Fully qualified name of the contract
+    ///
+    /// Name of the contract prefixed by its source
+    /// file (e.g. `src/Pool.sol:Pool`), or only the
+    /// name if the artifact does not include the
+    /// source file.
+    pub fn qualified_name(&self) -> Option<String> {
+        let name = self.name.as_ref()?;
+        match &self.source {
+            Some(source) => Some(format!("{}:{}", source, name)),
+            None => Some(name.clone()),
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("contract")
+    }
+
+    /// Deployment data of the contract
+    ///
+    /// Links the deployment bytecode and appends the
+    /// ABI encoded constructor arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `libraries` - Addresses of deployed libraries, keyed
+    ///   by library name or fully qualified name
+    /// * `args` - Constructor arguments as a tuple, e.g.
+    ///   `(U256::from(1), Address::ZERO)`, or `()` if the
+    ///   constructor has no arguments
+    ///
+    pub fn deploy_data<T: SolValue>(
+        &self,
+        libraries: &HashMap<String, Address>,
+        args: &T,
+    ) -> Result<Vec<u8>, ArtifactError>
+    where
+        for<'a> <T::SolType as SolType>::Token<'a>: TokenSeq<'a>,
+    {
+        if self.bytecode.is_empty() {
+            return Err(ArtifactError::NoBytecode(self.name().to_string()));
+        }
+        let mut data = self.bytecode.link(libraries)?;
+        data.extend(args.abi_encode_params());
+        Ok(data)
+    }
+
+    /// Deploy the contract
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Simulation environment
+    /// * `deployer` - Address of contract deployer
+    /// * `libraries` - Addresses of deployed libraries, keyed
+    ///   by library name or fully qualified name
+    /// * `args` - Constructor arguments as a tuple
+    ///
+    /// # Panics
+    ///
+    /// Panics if the deployment is reverted
+    ///
+    pub fn deploy<D: DB, V: Validator, T: SolValue>(
+        &self,
+        env: &mut Env<D, V>,
+        deployer: Address,
+        libraries: &HashMap<String, Address>,
+        args: &T,
+    ) -> Result<Address, ArtifactError>
+    where
+        for<'a> <T::SolType as SolType>::Token<'a>: TokenSeq<'a>,
+    {
+        let data = self.deploy_data(libraries, args)?;
+        Ok(env.deploy_contract(deployer, self.name(), data))
+    }
+}
+
+fn load_dir_into(
+    dir: &Path,
+    artifacts: &mut HashMap<String, Artifact>,
+) -> Result<(), ArtifactError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            if file_name != "build-info" {
+                load_dir_into(&path, artifacts)?;
+            }
+        } else if file_name.ends_with(".json") && !file_name.ends_with(".dbg.json") {
+            // Skip JSON files that are not artifacts
+            if let Ok(artifact) = Artifact::load(&path) {
+                if let Some(name) = artifact.qualified_name() {
+                    if artifacts.contains_key(&name) {
+                        return Err(ArtifactError::DuplicateArtifact(name));
+                    }
+                    artifacts.insert(name, artifact);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::RandomValidator;
+    use crate::LocalDB;
+    use alloy_primitives::U256;
+    use revm::DatabaseRef;
+
+    const PLACEHOLDER: &str = "__$6d1f7d8b8b1a7c0e0f5e4a1b2c3d4e5f60$__";
+
+    /// Init code storing the linked library address in slot 0
+    fn unlinked_code() -> String {
+        format!("0x73{}60005500", PLACEHOLDER)
+    }
+
+    fn foundry_artifact() -> String {
+        format!(
+            r#"{{
+                "abi": [],
+                "bytecode": {{
+                    "object": "{}",
+                    "linkReferences": {{"src/Math.sol": {{"Math": [{{"start": 1, "length": 20}}]}}}}
+                }},
+                "deployedBytecode": {{"object": "0x", "linkReferences": {{}}}},
+                "metadata": {{"settings": {{"compilationTarget": {{"src/Pool.sol": "Pool"}}}}}}
+            }}"#,
+            unlinked_code()
+        )
+    }
+
+    #[test]
+    fn test_hardhat_artifact() {
+        let artifact = Artifact::from_json(&format!(
+            r#"{{
+                "_format": "hh-sol-artifact-1",
+                "contractName": "Pool",
+                "sourceName": "contracts/Pool.sol",
+                "abi": [{{"type": "constructor", "inputs": []}}],
+                "bytecode": "{}",
+                "deployedBytecode": "0x6000",
+                "linkReferences": {{"contracts/Math.sol": {{"Math": [{{"start": 1, "length": 20}}]}}}},
+                "deployedLinkReferences": {{}}
+            }}"#,
+            unlinked_code()
+        ))
+        .unwrap();
+
+        assert_eq!(
+            artifact.qualified_name().as_deref(),
+            Some("contracts/Pool.sol:Pool")
+        );
+        assert_eq!(
+            artifact.bytecode.libraries(),
+            vec!["contracts/Math.sol:Math"]
+        );
+        assert_eq!(
+            artifact.deployed_bytecode.link(&HashMap::new()).unwrap(),
+            vec![0x60, 0x00]
+        );
+
+        assert!(matches!(
+            artifact.bytecode.link(&HashMap::new()),
+            Err(ArtifactError::UnlinkedLibrary(_))
+        ));
+
+        let libraries = HashMap::from([(
+            "contracts/Math.sol:Math".to_string(),
+            Address::repeat_byte(7),
+        )]);
+        let data = artifact
+            .deploy_data(&libraries, &(U256::from(1), Address::ZERO))
+            .unwrap();
+
+        assert_eq!(data.len(), 25 + 64);
+        assert_eq!(&data[1..21], Address::repeat_byte(7).as_slice());
+        assert_eq!(data[56], 1);
+    }
+
+    #[test]
+    fn test_deploy_foundry_artifact() {
+        let dir = std::env::temp_dir().join(format!("verbs_artifacts_{}", std::process::id()));
+        fs::create_dir_all(dir.join("Pool.sol")).unwrap();
+        fs::create_dir_all(dir.join("build-info")).unwrap();
+        fs::write(dir.join("Pool.sol/Pool.json"), foundry_artifact()).unwrap();
+        fs::write(dir.join("build-info/abc.json"), "{}").unwrap();
+
+        let artifacts = Artifact::load_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(artifacts.len(), 1);
+        let artifact = &artifacts["src/Pool.sol:Pool"];
+        assert!(artifact.deployed_bytecode.is_empty());
+
+        let mut env =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
+        let libraries = HashMap::from([("Math".to_string(), Address::repeat_byte(7))]);
+        let address = artifact
+            .deploy(&mut env, Address::ZERO, &libraries, &())
+            .unwrap();
+
+        let stored = env.view().db().storage_ref(address, U256::ZERO).unwrap();
+        assert_eq!(Address::from_word(stored.into()), Address::repeat_byte(7));
+    }
+
+    #[test]
+    fn test_load_dir_duplicates() {
+        let dir = std::env::temp_dir().join(format!("verbs_duplicates_{}", std::process::id()));
+        fs::create_dir_all(dir.join("Pool.sol")).unwrap();
+        let artifact = r#"{"abi": [], "bytecode": {"object": "0x00"}}"#;
+        fs::write(dir.join("Pool.sol/Pool.0.8.19.json"), artifact).unwrap();
+
+        let artifacts = Artifact::load_dir(&dir).unwrap();
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts["Pool"].name.as_deref(), Some("Pool"));
+
+        // Contract compiled with multiple compiler versions
+        fs::write(dir.join("Pool.sol/Pool.0.8.20.json"), artifact).unwrap();
+        let artifacts = Artifact::load_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(
+            artifacts,
+            Err(ArtifactError::DuplicateArtifact(name)) if name == "Pool"
+        ));
+    }
+}
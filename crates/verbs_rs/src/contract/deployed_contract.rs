@@ -1,11 +1,359 @@
-use alloy_primitives::Address;
-use alloy_sol_types::SolInterface;
+//! Typed handles of deployed contracts
+//!
+//! A [DeployedContract] pairs the address of a
+//! contract with its ABI (the calls enum generated
+//! by the [alloy_sol_types::sol] macro), so agents
+//! can call the contract, build transactions and
+//! decode its events without passing addresses and
+//! zero values around. Calls are checked to belong
+//! to the contract interface at compile time, i.e.
+//! the calls enum must implement `From<C>` for a
+//! call `C`, which the macro generates when the
+//! `#![sol(extra_methods)]` attribute is set.
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::{Address, U256};
+//! use alloy_sol_types::sol;
+//! use verbs_rs::contract::DeployedContract;
+//!
+//! sol! {
+//!     #![sol(extra_methods)]
+//!     contract Token {
+//!         function balanceOf(address owner) external view returns (uint256);
+//!     }
+//! }
+//!
+//! let token = DeployedContract::<Token::TokenCalls>::new("token", Address::repeat_byte(1));
+//!
+//! let tx = token.transaction(
+//!     Address::ZERO,
+//!     Token::balanceOfCall { owner: Address::ZERO },
+//!     true,
+//! );
+//!
+//! assert_eq!(tx.transact_to, Address::repeat_byte(1));
+//! assert_eq!(tx.value, U256::ZERO);
+//! ```
 
+use super::structs::{Event, Transaction};
+use crate::env::{Env, EnvView, RevertError, Validator};
+use crate::DB;
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::{SolCall, SolEvent, SolInterface};
+use revm::primitives::Log;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Typed handle of a deployed contract
 pub struct DeployedContract<A: SolInterface> {
     /// Name of the contract.
     pub name: String,
-    /// ABI contract object.
-    pub functions: A,
     /// Address of the contract.
     pub address: Address,
+    /// ABI of the contract
+    interface: PhantomData<fn() -> A>,
+}
+
+impl<A: SolInterface> Clone for DeployedContract<A> {
+    fn clone(&self) -> Self {
+        Self::new(self.name.clone(), self.address)
+    }
+}
+
+impl<A: SolInterface> fmt::Debug for DeployedContract<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeployedContract")
+            .field("name", &self.name)
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+impl<A: SolInterface> DeployedContract<A> {
+    /// Create a handle of a contract at an address
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the contract
+    /// * `address` - Address of the contract
+    ///
+    pub fn new(name: impl Into<String>, address: Address) -> Self {
+        DeployedContract {
+            name: name.into(),
+            address,
+            interface: PhantomData,
+        }
+    }
+
+    /// Deploy a contract and create a handle to it
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Simulation environment
+    /// * `deployer` - Address of contract deployer
+    /// * `name` - Name of the contract
+    /// * `data` - Deployment bytecode, and abi encoded
+    ///   constructor arguments if required
+    ///
+    /// # Panics
+    ///
+    /// Panics if the deployment fails
+    ///
+    pub fn deploy<D: DB, V: Validator>(
+        env: &mut Env<D, V>,
+        deployer: Address,
+        name: impl Into<String>,
+        data: Vec<u8>,
+    ) -> Self {
+        let name = name.into();
+        let address = env.deploy_contract(deployer, &name, data);
+        Self::new(name, address)
+    }
+
+    /// Call a contract function without committing changes
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Simulation environment
+    /// * `from` - Address of the function caller
+    /// * `args` - Function arguments
+    ///
+    pub fn call<C: SolCall, D: DB, V: Validator>(
+        &self,
+        env: &mut Env<D, V>,
+        from: Address,
+        args: C,
+    ) -> Result<(C::Return, Vec<Log>), RevertError>
+    where
+        A: From<C>,
+    {
+        env.direct_call(from, self.address, args, U256::ZERO)
+    }
+
+    /// Call a contract function using a read-only view
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - View of a simulation environment
+    /// * `from` - Address of the function caller
+    /// * `args` - Function arguments
+    ///
+    pub fn call_view<C: SolCall, D: DB>(
+        &self,
+        env: &EnvView<D>,
+        from: Address,
+        args: C,
+    ) -> Result<(C::Return, Vec<Log>), RevertError>
+    where
+        A: From<C>,
+    {
+        env.direct_call(from, self.address, args, U256::ZERO)
+    }
+
+    /// Execute a contract function, committing changes
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Simulation environment
+    /// * `from` - Address of the function caller
+    /// * `args` - Function arguments
+    ///
+    pub fn execute<C: SolCall, D: DB, V: Validator>(
+        &self,
+        env: &mut Env<D, V>,
+        from: Address,
+        args: C,
+    ) -> Result<(C::Return, Vec<Log>), RevertError>
+    where
+        A: From<C>,
+    {
+        self.execute_with_value(env, from, args, U256::ZERO)
+    }
+
+    /// Execute a payable contract function, committing changes
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Simulation environment
+    /// * `from` - Address of the function caller
+    /// * `args` - Function arguments
+    /// * `value` - Value attached to the transaction
+    ///
+    pub fn execute_with_value<C: SolCall, D: DB, V: Validator>(
+        &self,
+        env: &mut Env<D, V>,
+        from: Address,
+        args: C,
+        value: U256,
+    ) -> Result<(C::Return, Vec<Log>), RevertError>
+    where
+        A: From<C>,
+    {
+        env.direct_execute(from, self.address, args, value)
+    }
+
+    /// Build a transaction calling a contract function
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Address of the function caller
+    /// * `args` - Function arguments
+    /// * `checked` - If `true` the simulation will panic
+    ///   if the transaction is reverted
+    ///
+    pub fn transaction<C: SolCall>(&self, from: Address, args: C, checked: bool) -> Transaction
+    where
+        A: From<C>,
+    {
+        Transaction::basic(from, self.address, args, checked)
+    }
+
+    /// Decode events emitted by this contract
+    ///
+    /// Logs emitted by other contracts, or that are
+    /// not of the given event type, are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `logs` - Logs, e.g. returned by [DeployedContract::execute]
+    ///
+    pub fn decode_logs<E: SolEvent>(&self, logs: &[Log]) -> Vec<E> {
+        logs.iter()
+            .filter(|log| log.address == self.address)
+            .filter_map(|log| E::decode_log_data(&log.data, true).ok())
+            .collect()
+    }
+
+    /// Decode events emitted by this contract during a simulation
+    ///
+    /// Returns the simulation step and sequence of the
+    /// transaction along with each decoded event.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - Simulation events, e.g. [Env::event_history]
+    ///
+    pub fn decode_events<E: SolEvent>(&self, events: &[Event]) -> Vec<(usize, usize, E)> {
+        events
+            .iter()
+            .flat_map(|event| {
+                self.decode_logs::<E>(&event.logs)
+                    .into_iter()
+                    .map(|decoded| (event.step, event.sequence, decoded))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::RandomValidator;
+    use crate::LocalDB;
+    use alloy_primitives::{Bytes, I256};
+    use alloy_sol_types::sol;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar;
+    use revm::db::AccountState;
+    use revm::primitives::{AccountInfo, Bytecode};
+
+    sol! {
+        #![sol(extra_methods)]
+        contract Store {
+            event ValueSet(int256 value);
+            function getValue() external view returns (int256);
+            function setValue(int256 x) external;
+        }
+    }
+
+    /// Runtime code storing the argument of calls with
+    /// arguments in slot 0 (and emitting `ValueSet`),
+    /// or returning the value of slot 0
+    fn store_code() -> Bytecode {
+        let mut code = vec![
+            0x60, 0x24, 0x36, 0x10, 0x60, 0x38, 0x57, // jump to 0x38 if no arguments
+            0x60, 0x04, 0x35, 0x80, 0x60, 0x00, 0x55, // sstore(0, x)
+            0x60, 0x00, 0x52, 0x7f, // mstore(0, x), push topic
+        ];
+        code.extend(Store::ValueSet::SIGNATURE_HASH);
+        code.extend([
+            0x60, 0x20, 0x60, 0x00, 0xa1, 0x00, // log1 and stop
+            0x5b, 0x60, 0x00, 0x54, 0x60, 0x00, 0x52, // mstore(0, sload(0))
+            0x60, 0x20, 0x60, 0x00, 0xf3, // return
+        ]);
+        Bytecode::new_raw(Bytes::from(code))
+    }
+
+    fn store_env() -> (
+        Env<LocalDB, RandomValidator>,
+        DeployedContract<Store::StoreCalls>,
+    ) {
+        let mut env =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
+        let address = Address::repeat_byte(1);
+        let code = store_code();
+
+        let db = &mut env.evm_state().context.evm.db;
+        db.insert_account_info(
+            address,
+            AccountInfo::new(U256::ZERO, 0, code.hash_slow(), code),
+        );
+        db.accounts.get_mut(&address).unwrap().account_state = AccountState::StorageCleared;
+
+        (env, DeployedContract::new("store", address))
+    }
+
+    #[test]
+    fn test_call_and_execute() {
+        let (mut env, store) = store_env();
+
+        let (_, logs) = store
+            .execute(
+                &mut env,
+                Address::ZERO,
+                Store::setValueCall { x: I256::ONE },
+            )
+            .unwrap();
+        let events = store.decode_logs::<Store::ValueSet>(&logs);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].value, I256::ONE);
+
+        // Logs of other contracts are skipped
+        let other = DeployedContract::<Store::StoreCalls>::new("other", Address::ZERO);
+        assert!(other.decode_logs::<Store::ValueSet>(&logs).is_empty());
+
+        let (value, _) = store
+            .call(&mut env, Address::ZERO, Store::getValueCall {})
+            .unwrap();
+        assert_eq!(value._0, I256::ONE);
+
+        let (value, _) = store
+            .call_view(&env.view(), Address::ZERO, Store::getValueCall {})
+            .unwrap();
+        assert_eq!(value._0, I256::ONE);
+    }
+
+    #[test]
+    fn test_transactions_and_events() {
+        let (mut env, store) = store_env();
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        let tx = store.transaction(
+            Address::ZERO,
+            Store::setValueCall {
+                x: I256::try_from(5).unwrap(),
+            },
+            true,
+        );
+        assert_eq!(tx.function_selector, Store::setValueCall::SELECTOR);
+
+        env.process_transactions(vec![tx], &mut rng, 3);
+
+        let events = store.decode_events::<Store::ValueSet>(&env.last_events);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, 3);
+        assert_eq!(events[0].2.value, I256::try_from(5).unwrap());
+    }
 }
@@ -0,0 +1,335 @@
+//! Registry of custom Solidity errors
+//!
+//! Custom errors (e.g. `error InsufficientLiquidity(uint256)`)
+//! are identified in revert data by a 4 byte selector. An
+//! [ErrorRegistry] maps selectors to errors, registered
+//! from errors enums generated by the [alloy_sol_types::sol]
+//! macro, error signatures or JSON ABIs, and is used to give
+//! readable messages to [crate::env::RevertError].
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::U256;
+//! use alloy_sol_types::{sol, SolError};
+//! use verbs_rs::contract::ErrorRegistry;
+//!
+//! sol! {
+//!     #![sol(all_derives)]
+//!     contract Pool {
+//!         error InsufficientLiquidity(uint256 available);
+//!     }
+//! }
+//!
+//! let mut registry = ErrorRegistry::new();
+//! registry.register::<Pool::PoolErrors>();
+//!
+//! let error = Pool::InsufficientLiquidity { available: U256::from(5) };
+//!
+//! assert_eq!(
+//!     registry.decode(&error.abi_encode()).unwrap(),
+//!     format!("{:?}", Pool::PoolErrors::InsufficientLiquidity(error)),
+//! );
+//! ```
+
+use alloy_primitives::{hex, keccak256, Address, I256, U256};
+use alloy_sol_types::SolInterface;
+use ethers_core::abi::{self, param_type::Reader, ParamType, Token};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Decoder of a registered error
+#[derive(Clone)]
+enum ErrorDecoder {
+    /// Error signature, e.g. `InsufficientLiquidity(uint256)`
+    Signature(String),
+    /// Decoding using an errors enum
+    Typed(fn(&[u8]) -> Option<String>),
+}
+
+/// Custom errors indexed by selector
+#[derive(Clone, Default)]
+pub struct ErrorRegistry {
+    errors: HashMap<[u8; 4], ErrorDecoder>,
+}
+
+impl fmt::Debug for ErrorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorRegistry")
+            .field("n_errors", &self.errors.len())
+            .finish()
+    }
+}
+
+/// Decode errors using an errors enum, formatting the decoded value
+fn decode_typed<E: SolInterface + fmt::Debug>(data: &[u8]) -> Option<String> {
+    E::abi_decode(data, true).ok().map(|e| format!("{:?}", e))
+}
+
+/// Format a decoded ABI value
+fn format_token(token: &Token) -> String {
+    let join = |tokens: &[Token]| {
+        tokens
+            .iter()
+            .map(format_token)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match token {
+        Token::Address(a) => Address::from(a.0).to_string(),
+        Token::Bytes(b) | Token::FixedBytes(b) => format!("0x{}", hex::encode(b)),
+        Token::Int(i) => I256::from_raw(U256::from_limbs(i.0)).to_string(),
+        Token::Uint(i) => U256::from_limbs(i.0).to_string(),
+        Token::Bool(b) => b.to_string(),
+        Token::String(s) => format!("{:?}", s),
+        Token::Array(a) | Token::FixedArray(a) => format!("[{}]", join(a)),
+        Token::Tuple(t) => format!("({})", join(t)),
+    }
+}
+
+/// Decode errors using an error signature
+///
+/// Returns `None` if the signature cannot be parsed
+/// or the arguments do not match the signature.
+fn decode_signature(signature: &str, data: &[u8]) -> Option<String> {
+    let (name, params) = signature.split_once('(')?;
+    let params = match params.strip_suffix(')')? {
+        "" => Vec::new(),
+        params => match Reader::read(&format!("({})", params)).ok()? {
+            ParamType::Tuple(params) => params,
+            _ => return None,
+        },
+    };
+    let tokens = abi::decode(&params, &data[4..]).ok()?;
+    let args = tokens.iter().map(format_token).collect::<Vec<_>>();
+    Some(format!("{}({})", name, args.join(", ")))
+}
+
+/// Canonical type of a JSON ABI parameter
+fn param_type(param: &Value) -> Option<String> {
+    let ty = param.get("type")?.as_str()?;
+    match ty.strip_prefix("tuple") {
+        Some(suffix) => {
+            let components = param
+                .get("components")?
+                .as_array()?
+                .iter()
+                .map(param_type)
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("({}){}", components.join(","), suffix))
+        }
+        None => Some(ty.to_string()),
+    }
+}
+
+/// Signature of a JSON ABI error item
+fn error_signature(item: &Value) -> Option<String> {
+    let name = item.get("name")?.as_str()?;
+    let inputs = match item.get("inputs") {
+        Some(inputs) => inputs
+            .as_array()?
+            .iter()
+            .map(param_type)
+            .collect::<Option<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+    Some(format!("{}({})", name, inputs.join(",")))
+}
+
+impl ErrorRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of registered errors
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Whether no errors have been registered
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Register the errors of a contract
+    ///
+    /// Decoded errors are formatted using their
+    /// [fmt::Debug] implementation (generated by the
+    /// [alloy_sol_types::sol] macro with the
+    /// `#![sol(all_derives)]` attribute).
+    ///
+    /// # Arguments
+    ///
+    /// * `E` - Errors enum of a contract, e.g.
+    ///   `Pool::PoolErrors`
+    ///
+    pub fn register<E: SolInterface + fmt::Debug>(&mut self) {
+        for selector in E::selectors() {
+            self.errors
+                .insert(selector, ErrorDecoder::Typed(decode_typed::<E>));
+        }
+    }
+
+    /// Register an error by its signature
+    ///
+    /// Arguments of errors registered by signature are
+    /// decoded using the types in the signature, e.g.
+    /// `InsufficientLiquidity(5)`. If the arguments cannot
+    /// be decoded, messages include the signature and the
+    /// hex encoded error arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `signature` - Error signature, e.g.
+    ///   `InsufficientLiquidity(uint256)`
+    ///
+    pub fn register_signature(&mut self, signature: &str) {
+        let selector = keccak256(signature.as_bytes());
+        self.errors.insert(
+            selector[..4].try_into().unwrap(),
+            ErrorDecoder::Signature(signature.to_string()),
+        );
+    }
+
+    /// Register the errors of a JSON ABI
+    ///
+    /// Registers the signatures of errors in the ABI,
+    /// other ABI items are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `abi` - JSON ABI, e.g. [crate::contract::Artifact::abi]
+    ///
+    pub fn register_abi(&mut self, abi: &Value) {
+        let items = abi.as_array().map(Vec::as_slice).unwrap_or_default();
+        for item in items {
+            if item.get("type").and_then(Value::as_str) == Some("error") {
+                if let Some(signature) = error_signature(item) {
+                    self.register_signature(&signature);
+                }
+            }
+        }
+    }
+
+    /// Decode revert data
+    ///
+    /// Returns `None` if the revert data does not
+    /// match a registered error.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Revert data
+    ///
+    pub fn decode(&self, data: &[u8]) -> Option<String> {
+        let selector: [u8; 4] = data.get(..4)?.try_into().ok()?;
+        match self.errors.get(&selector)? {
+            ErrorDecoder::Typed(decode) => decode(data),
+            ErrorDecoder::Signature(signature) => decode_signature(signature, data)
+                .or_else(|| Some(format!("{}: 0x{}", signature, hex::encode(&data[4..])))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_sol_types::{sol, SolError, SolValue};
+
+    sol! {
+        contract Pool {
+            struct Position { address owner; uint256 amount; }
+            error Unauthorized();
+            error InsufficientLiquidity(uint256 available);
+            error InvalidPositions(Position[] positions);
+        }
+    }
+
+    #[test]
+    fn test_register_signature() {
+        let mut registry = ErrorRegistry::new();
+        registry.register_signature("Failed(int256,bool,string,bytes4)");
+
+        let data = [
+            &keccak256("Failed(int256,bool,string,bytes4)")[..4],
+            &(I256::MINUS_ONE, true, "oops".to_string(), [1u8, 2, 3, 4]).abi_encode_params()[..],
+        ]
+        .concat();
+        assert_eq!(
+            registry.decode(&data).unwrap(),
+            "Failed(-1, true, \"oops\", 0x01020304)"
+        );
+    }
+
+    #[test]
+    fn test_register_abi() {
+        let abi = serde_json::json!([
+            {"type": "function", "name": "swap", "inputs": [], "outputs": []},
+            {"type": "error", "name": "Unauthorized", "inputs": []},
+            {
+                "type": "error",
+                "name": "InsufficientLiquidity",
+                "inputs": [{"name": "available", "type": "uint256"}]
+            },
+            {
+                "type": "error",
+                "name": "InvalidPositions",
+                "inputs": [{
+                    "name": "positions",
+                    "type": "tuple[]",
+                    "components": [
+                        {"name": "owner", "type": "address"},
+                        {"name": "amount", "type": "uint256"}
+                    ]
+                }]
+            }
+        ]);
+
+        let mut registry = ErrorRegistry::new();
+        registry.register_abi(&abi);
+        assert_eq!(registry.len(), 3);
+
+        assert_eq!(
+            registry
+                .decode(&Pool::Unauthorized {}.abi_encode())
+                .unwrap(),
+            "Unauthorized()"
+        );
+
+        let data = Pool::InsufficientLiquidity {
+            available: U256::from(5),
+        }
+        .abi_encode();
+        assert_eq!(registry.decode(&data).unwrap(), "InsufficientLiquidity(5)");
+        assert_eq!(
+            registry.decode(&data[..20]).unwrap(),
+            format!(
+                "InsufficientLiquidity(uint256): 0x{}",
+                hex::encode(&data[4..20])
+            )
+        );
+
+        let owner = Address::repeat_byte(1);
+        let data = Pool::InvalidPositions {
+            positions: vec![
+                Pool::Position {
+                    owner,
+                    amount: U256::from(2),
+                },
+                Pool::Position {
+                    owner: Address::ZERO,
+                    amount: U256::ZERO,
+                },
+            ],
+        }
+        .abi_encode();
+        assert_eq!(
+            registry.decode(&data).unwrap(),
+            format!("InvalidPositions([({}, 2), ({}, 0)])", owner, Address::ZERO)
+        );
+
+        assert!(registry.decode(&[1, 2, 3, 4]).is_none());
+        assert!(registry.decode(&[]).is_none());
+    }
+}
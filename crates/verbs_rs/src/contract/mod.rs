@@ -1,8 +1,14 @@
 //! Contract data structures and utilities
 //!
 
+pub mod artifact;
 pub mod deployed_contract;
+pub mod error_registry;
+pub mod storage_layout;
 pub mod structs;
 
+pub use artifact::{Artifact, ArtifactBytecode, ArtifactError, LinkReference};
 pub use deployed_contract::*;
+pub use error_registry::ErrorRegistry;
+pub use storage_layout::{MappingKey, StorageLayout, StorageLayoutError, StorageValue};
 pub use structs::*;
@@ -0,0 +1,1091 @@
+//! Solidity storage layout decoding
+//!
+//! Read and write named contract state variables
+//! using the storage layout generated by solc
+//! (the `storageLayout` output selection), rather
+//! than custom view functions or hand-computed
+//! storage slots.
+//!
+//! Variables are identified by a path, starting
+//! with the name of the state variable, followed
+//! by struct members (e.g. `reserves.reserve0`)
+//! and array indices (e.g. `values[2]`). Mapping
+//! keys are passed separately as [MappingKey], and
+//! are applied in order each time a mapping is
+//! reached along the path.
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::{Address, U256};
+//! use revm::primitives::AccountInfo;
+//! use verbs_rs::contract::{MappingKey, StorageLayout, StorageValue};
+//! use verbs_rs::{LocalDB, DB};
+//!
+//! let layout = StorageLayout::from_json(r#"{
+//!     "storage": [
+//!         {"label": "balanceOf", "offset": 0, "slot": "0", "type": "t_mapping(t_address,t_uint256)"}
+//!     ],
+//!     "types": {
+//!         "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+//!         "t_mapping(t_address,t_uint256)": {
+//!             "encoding": "mapping", "key": "t_address", "label": "mapping(address => uint256)",
+//!             "numberOfBytes": "32", "value": "t_uint256"
+//!         },
+//!         "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"}
+//!     }
+//! }"#).unwrap();
+//!
+//! let token = Address::repeat_byte(1);
+//! let user = Address::repeat_byte(2);
+//!
+//! let mut db = LocalDB::new();
+//! db.insert_account_info(token, AccountInfo::default());
+//!
+//! let keys = [MappingKey::from(user)];
+//! layout.write(&mut db, token, "balanceOf", &keys, &U256::from(100).into()).unwrap();
+//! let balance = layout.read(&mut db, token, "balanceOf", &keys).unwrap();
+//!
+//! assert_eq!(balance, StorageValue::Word(U256::from(100)));
+//! ```
+
+use crate::{DatabaseError, DB};
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Maximum length of a dynamic array or `bytes` value
+/// read as a whole. Lengths are read from storage, so
+/// larger values are rejected rather than reading an
+/// unbounded number of slots, longer arrays can still
+/// be read element by element (e.g. `values[70000]`).
+pub const MAX_DYNAMIC_LENGTH: usize = 1 << 16;
+
+/// Error raised decoding a storage layout or accessing storage
+#[derive(Debug, thiserror::Error)]
+pub enum StorageLayoutError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+    #[error("no state variable named {0}")]
+    UnknownVariable(String),
+    #[error("type {0} missing from storage layout")]
+    UnknownType(String),
+    #[error("{0} has no member {1}")]
+    UnknownMember(String, String),
+    #[error("invalid storage path {0}")]
+    InvalidPath(String),
+    #[error("index {1} out of bounds for {0}")]
+    OutOfBounds(String, usize),
+    #[error("missing key for {0}")]
+    MissingKey(String),
+    #[error("{0} cannot be read or written as a whole")]
+    Mapping(String),
+    #[error("value does not match type {0}")]
+    ValueMismatch(String),
+    #[error("no storage layout registered for {0}")]
+    NoLayout(Address),
+}
+
+/// Encoding of a type in storage
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// Stored in place, i.e. value types,
+    /// structs and fixed size arrays
+    Inplace,
+    /// Mapping
+    Mapping,
+    /// Dynamic size array
+    DynamicArray,
+    /// `bytes` or `string`
+    Bytes,
+}
+
+/// State variable (or struct member) in a storage layout
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StorageEntry {
+    /// Variable name
+    pub label: String,
+    /// Storage slot (relative to the start of the
+    /// struct for struct members)
+    pub slot: U256,
+    /// Offset of the value in the slot in bytes
+    pub offset: usize,
+    /// Identifier of the type of the variable
+    #[serde(rename = "type")]
+    pub type_id: String,
+}
+
+/// Type in a storage layout
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageType {
+    /// Storage encoding of the type
+    pub encoding: Encoding,
+    /// Solidity type name
+    pub label: String,
+    /// Number of bytes used by the type
+    #[serde(deserialize_with = "deserialize_number")]
+    pub number_of_bytes: usize,
+    /// Key type of mappings
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Value type of mappings
+    #[serde(default)]
+    pub value: Option<String>,
+    /// Element type of arrays
+    #[serde(default)]
+    pub base: Option<String>,
+    /// Members of structs
+    #[serde(default)]
+    pub members: Option<Vec<StorageEntry>>,
+}
+
+/// Solc storage layout of a contract
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StorageLayout {
+    /// State variables of the contract
+    pub storage: Vec<StorageEntry>,
+    /// Types used by the state variables
+    #[serde(default, deserialize_with = "deserialize_types")]
+    pub types: HashMap<String, StorageType>,
+}
+
+/// Location of a value in storage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StorageSlot {
+    /// Storage slot (of the start of the value)
+    pub slot: U256,
+    /// Offset of the value in the slot in bytes
+    pub offset: usize,
+    /// Number of bytes used by the value
+    pub number_of_bytes: usize,
+}
+
+/// Key of a Solidity mapping
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MappingKey {
+    /// Value type key, encoded as a 32 byte word
+    Word(B256),
+    /// `bytes` or `string` key
+    Bytes(Bytes),
+}
+
+impl MappingKey {
+    /// Slot of the value of this key in a mapping at `slot`
+    fn slot(&self, slot: U256) -> U256 {
+        let mut data = match self {
+            MappingKey::Word(w) => w.to_vec(),
+            MappingKey::Bytes(b) => b.to_vec(),
+        };
+        data.extend_from_slice(&slot.to_be_bytes::<32>());
+        keccak256(data).into()
+    }
+}
+
+impl From<Address> for MappingKey {
+    fn from(value: Address) -> Self {
+        MappingKey::Word(value.into_word())
+    }
+}
+
+impl From<U256> for MappingKey {
+    fn from(value: U256) -> Self {
+        MappingKey::Word(value.into())
+    }
+}
+
+impl From<B256> for MappingKey {
+    fn from(value: B256) -> Self {
+        MappingKey::Word(value)
+    }
+}
+
+impl From<bool> for MappingKey {
+    fn from(value: bool) -> Self {
+        MappingKey::Word(U256::from(value as u8).into())
+    }
+}
+
+impl From<&str> for MappingKey {
+    fn from(value: &str) -> Self {
+        MappingKey::Bytes(Bytes::copy_from_slice(value.as_bytes()))
+    }
+}
+
+impl From<Bytes> for MappingKey {
+    fn from(value: Bytes) -> Self {
+        MappingKey::Bytes(value)
+    }
+}
+
+/// Value of a state variable
+///
+/// Value types (integers, addresses, booleans,
+/// fixed size bytes, enums and contracts) are
+/// represented as the unsigned integer they are
+/// stored as, i.e. signed integers are not sign
+/// extended, and `bytesN` values are right-aligned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StorageValue {
+    /// Value type
+    Word(U256),
+    /// `bytes` or `string`
+    Bytes(Bytes),
+    /// Struct member names and values
+    Struct(Vec<(String, StorageValue)>),
+    /// Array elements
+    Array(Vec<StorageValue>),
+}
+
+impl StorageValue {
+    /// Get the value of a value type
+    pub fn as_word(&self) -> Option<U256> {
+        match self {
+            StorageValue::Word(w) => Some(*w),
+            _ => None,
+        }
+    }
+
+    /// Get the value of an address
+    pub fn as_address(&self) -> Option<Address> {
+        self.as_word().map(|w| Address::from_word(B256::from(w)))
+    }
+
+    /// Get the value of `bytes` or `string`
+    pub fn as_bytes(&self) -> Option<&Bytes> {
+        match self {
+            StorageValue::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+impl From<U256> for StorageValue {
+    fn from(value: U256) -> Self {
+        StorageValue::Word(value)
+    }
+}
+
+impl From<Address> for StorageValue {
+    fn from(value: Address) -> Self {
+        StorageValue::Word(value.into_word().into())
+    }
+}
+
+impl From<bool> for StorageValue {
+    fn from(value: bool) -> Self {
+        StorageValue::Word(U256::from(value as u8))
+    }
+}
+
+impl From<&str> for StorageValue {
+    fn from(value: &str) -> Self {
+        StorageValue::Bytes(Bytes::copy_from_slice(value.as_bytes()))
+    }
+}
+
+impl From<Bytes> for StorageValue {
+    fn from(value: Bytes) -> Self {
+        StorageValue::Bytes(value)
+    }
+}
+
+/// Step along a storage path
+enum Segment<'a> {
+    Member(&'a str),
+    Index(usize),
+}
+
+/// Location of a value and its type
+struct Location<'a> {
+    slot: U256,
+    offset: usize,
+    type_id: &'a str,
+}
+
+impl StorageLayout {
+    /// Parse a storage layout from JSON
+    ///
+    /// Accepts either the solc `storageLayout` output,
+    /// or a contract artifact (e.g. generated by Foundry)
+    /// with a `storageLayout` field.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - JSON storage layout
+    ///
+    pub fn from_json(json: &str) -> Result<Self, StorageLayoutError> {
+        Self::from_value(serde_json::from_str(json)?)
+    }
+
+    /// Load a storage layout from a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the storage layout or artifact file
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, StorageLayoutError> {
+        let reader = BufReader::new(File::open(path)?);
+        Self::from_value(serde_json::from_reader(reader)?)
+    }
+
+    fn from_value(mut value: serde_json::Value) -> Result<Self, StorageLayoutError> {
+        if let Some(layout) = value.get_mut("storageLayout") {
+            value = layout.take();
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Get the storage location of a value
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the value, e.g. `reserves.reserve0`
+    /// * `keys` - Keys of mappings along the path
+    ///
+    pub fn locate(
+        &self,
+        path: &str,
+        keys: &[MappingKey],
+    ) -> Result<StorageSlot, StorageLayoutError> {
+        let location = self.location(path, keys)?;
+        Ok(StorageSlot {
+            slot: location.slot,
+            offset: location.offset,
+            number_of_bytes: self.storage_type(location.type_id)?.number_of_bytes,
+        })
+    }
+
+    /// Read a value from contract storage
+    ///
+    /// Dynamic arrays and `bytes` values longer than
+    /// [MAX_DYNAMIC_LENGTH] return a
+    /// [StorageLayoutError::ValueMismatch] error.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - DB to read from
+    /// * `address` - Address of the contract
+    /// * `path` - Path to the value, e.g. `reserves.reserve0`
+    /// * `keys` - Keys of mappings along the path
+    ///
+    pub fn read<D: DB>(
+        &self,
+        db: &mut D,
+        address: Address,
+        path: &str,
+        keys: &[MappingKey],
+    ) -> Result<StorageValue, StorageLayoutError> {
+        let location = self.location(path, keys)?;
+        // Ensure the account is loaded (e.g. from a fork)
+        db.basic(address)?;
+        self.read_location(db, address, &location)
+    }
+
+    /// Write a value to contract storage
+    ///
+    /// Writing a dynamic array or `bytes` value does
+    /// not clear any existing elements beyond the
+    /// length of the new value.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - DB to write to
+    /// * `address` - Address of the contract
+    /// * `path` - Path to the value, e.g. `reserves.reserve0`
+    /// * `keys` - Keys of mappings along the path
+    /// * `value` - New value
+    ///
+    pub fn write<D: DB>(
+        &self,
+        db: &mut D,
+        address: Address,
+        path: &str,
+        keys: &[MappingKey],
+        value: &StorageValue,
+    ) -> Result<(), StorageLayoutError> {
+        let location = self.location(path, keys)?;
+        db.basic(address)?;
+        self.write_location(db, address, &location, value)
+    }
+
+    fn storage_type(&self, type_id: &str) -> Result<&StorageType, StorageLayoutError> {
+        self.types
+            .get(type_id)
+            .ok_or_else(|| StorageLayoutError::UnknownType(type_id.to_string()))
+    }
+
+    fn location<'a>(
+        &'a self,
+        path: &'a str,
+        keys: &[MappingKey],
+    ) -> Result<Location<'a>, StorageLayoutError> {
+        let (name, segments) = parse_path(path)?;
+
+        let entry = self
+            .storage
+            .iter()
+            .find(|e| e.label == name)
+            .ok_or_else(|| StorageLayoutError::UnknownVariable(name.to_string()))?;
+
+        let mut keys = keys.iter();
+        let mut location = self.apply_keys(
+            Location {
+                slot: entry.slot,
+                offset: entry.offset,
+                type_id: &entry.type_id,
+            },
+            &mut keys,
+        )?;
+
+        for segment in segments {
+            let ty = self.storage_type(location.type_id)?;
+            location = match segment {
+                _ if ty.encoding == Encoding::Mapping => {
+                    return Err(StorageLayoutError::MissingKey(ty.label.clone()))
+                }
+                Segment::Member(name) => {
+                    let member = ty
+                        .members
+                        .iter()
+                        .flatten()
+                        .find(|m| m.label == name)
+                        .ok_or_else(|| {
+                            StorageLayoutError::UnknownMember(ty.label.clone(), name.to_string())
+                        })?;
+                    Location {
+                        slot: location.slot + member.slot,
+                        offset: member.offset,
+                        type_id: &member.type_id,
+                    }
+                }
+                Segment::Index(i) => self.element(&location, ty, i)?,
+            };
+            location = self.apply_keys(location, &mut keys)?;
+        }
+
+        match keys.next() {
+            Some(_) => Err(StorageLayoutError::InvalidPath(format!(
+                "{} (too many mapping keys)",
+                path
+            ))),
+            None => Ok(location),
+        }
+    }
+
+    /// Look up values of mappings while keys are available
+    fn apply_keys<'a>(
+        &'a self,
+        mut location: Location<'a>,
+        keys: &mut std::slice::Iter<MappingKey>,
+    ) -> Result<Location<'a>, StorageLayoutError> {
+        loop {
+            let ty = self.storage_type(location.type_id)?;
+            if ty.encoding != Encoding::Mapping {
+                return Ok(location);
+            }
+            let key = match keys.next() {
+                Some(k) => k,
+                None => return Ok(location),
+            };
+            let value = ty
+                .value
+                .as_deref()
+                .ok_or_else(|| StorageLayoutError::UnknownType(ty.label.clone()))?;
+            location = Location {
+                slot: key.slot(location.slot),
+                offset: 0,
+                type_id: value,
+            };
+        }
+    }
+
+    /// Location of an array element
+    fn element<'a>(
+        &'a self,
+        array: &Location<'a>,
+        ty: &'a StorageType,
+        index: usize,
+    ) -> Result<Location<'a>, StorageLayoutError> {
+        let base = match &ty.base {
+            Some(base) => base,
+            None => return Err(StorageLayoutError::InvalidPath(ty.label.clone())),
+        };
+        let start = match ty.encoding {
+            Encoding::DynamicArray => keccak256(array.slot.to_be_bytes::<32>()).into(),
+            Encoding::Inplace => {
+                if let Some(length) = array_length(array.type_id) {
+                    if index >= length {
+                        return Err(StorageLayoutError::OutOfBounds(ty.label.clone(), index));
+                    }
+                }
+                array.slot
+            }
+            _ => return Err(StorageLayoutError::InvalidPath(ty.label.clone())),
+        };
+
+        let size = self.storage_type(base)?.number_of_bytes;
+        let (slot, offset) = match size < 32 {
+            // Elements are packed into slots
+            true => {
+                let per_slot = 32 / size;
+                (index / per_slot, (index % per_slot) * size)
+            }
+            false => (index * size.div_ceil(32), 0),
+        };
+
+        Ok(Location {
+            slot: start + U256::from(slot),
+            offset,
+            type_id: base,
+        })
+    }
+
+    fn read_location<D: DB>(
+        &self,
+        db: &mut D,
+        address: Address,
+        location: &Location,
+    ) -> Result<StorageValue, StorageLayoutError> {
+        let ty = self.storage_type(location.type_id)?;
+
+        match ty.encoding {
+            Encoding::Mapping => Err(StorageLayoutError::Mapping(ty.label.clone())),
+            Encoding::Bytes => read_bytes(db, address, location.slot).map(StorageValue::Bytes),
+            Encoding::DynamicArray => {
+                let length = dynamic_length(db.storage(address, location.slot)?, &ty.label)?;
+                self.read_elements(db, address, location, ty, length)
+            }
+            Encoding::Inplace => match (&ty.members, &ty.base) {
+                (Some(members), _) => {
+                    let mut values = Vec::with_capacity(members.len());
+                    for member in members {
+                        let member_location = Location {
+                            slot: location.slot + member.slot,
+                            offset: member.offset,
+                            type_id: &member.type_id,
+                        };
+                        values.push((
+                            member.label.clone(),
+                            self.read_location(db, address, &member_location)?,
+                        ));
+                    }
+                    Ok(StorageValue::Struct(values))
+                }
+                (None, Some(_)) => {
+                    let length = array_length(location.type_id)
+                        .ok_or_else(|| StorageLayoutError::UnknownType(ty.label.clone()))?;
+                    self.read_elements(db, address, location, ty, length)
+                }
+                (None, None) => {
+                    let word = db.storage(address, location.slot)?;
+                    Ok(StorageValue::Word(
+                        (word >> (8 * location.offset)) & mask(ty.number_of_bytes),
+                    ))
+                }
+            },
+        }
+    }
+
+    fn read_elements<D: DB>(
+        &self,
+        db: &mut D,
+        address: Address,
+        location: &Location,
+        ty: &StorageType,
+        length: usize,
+    ) -> Result<StorageValue, StorageLayoutError> {
+        let mut values = Vec::new();
+        for i in 0..length {
+            let element = self.element(location, ty, i)?;
+            values.push(self.read_location(db, address, &element)?);
+        }
+        Ok(StorageValue::Array(values))
+    }
+
+    fn write_location<D: DB>(
+        &self,
+        db: &mut D,
+        address: Address,
+        location: &Location,
+        value: &StorageValue,
+    ) -> Result<(), StorageLayoutError> {
+        let ty = self.storage_type(location.type_id)?;
+        let mismatch = || StorageLayoutError::ValueMismatch(ty.label.clone());
+
+        match (ty.encoding, value) {
+            (Encoding::Mapping, _) => Err(StorageLayoutError::Mapping(ty.label.clone())),
+            (Encoding::Bytes, StorageValue::Bytes(bytes)) => {
+                write_bytes(db, address, location.slot, bytes)
+            }
+            (Encoding::DynamicArray, StorageValue::Array(values)) => {
+                db.insert_account_storage(address, location.slot, U256::from(values.len()))?;
+                self.write_elements(db, address, location, ty, values)
+            }
+            (Encoding::Inplace, StorageValue::Struct(values)) => {
+                let members = ty.members.as_ref().ok_or_else(mismatch)?;
+                for (name, value) in values {
+                    let member = members.iter().find(|m| &m.label == name).ok_or_else(|| {
+                        StorageLayoutError::UnknownMember(ty.label.clone(), name.clone())
+                    })?;
+                    let member_location = Location {
+                        slot: location.slot + member.slot,
+                        offset: member.offset,
+                        type_id: &member.type_id,
+                    };
+                    self.write_location(db, address, &member_location, value)?;
+                }
+                Ok(())
+            }
+            (Encoding::Inplace, StorageValue::Array(values)) if ty.base.is_some() => {
+                match array_length(location.type_id) {
+                    Some(length) if values.len() == length => {
+                        self.write_elements(db, address, location, ty, values)
+                    }
+                    _ => Err(mismatch()),
+                }
+            }
+            (Encoding::Inplace, StorageValue::Word(word))
+                if ty.members.is_none() && ty.base.is_none() =>
+            {
+                let mask = mask(ty.number_of_bytes);
+                if *word > mask {
+                    return Err(mismatch());
+                }
+                let shift = 8 * location.offset;
+                let current = match ty.number_of_bytes {
+                    32 => U256::ZERO,
+                    _ => db.storage(address, location.slot)? & !(mask << shift),
+                };
+                db.insert_account_storage(address, location.slot, current | (*word << shift))?;
+                Ok(())
+            }
+            _ => Err(mismatch()),
+        }
+    }
+
+    fn write_elements<D: DB>(
+        &self,
+        db: &mut D,
+        address: Address,
+        location: &Location,
+        ty: &StorageType,
+        values: &[StorageValue],
+    ) -> Result<(), StorageLayoutError> {
+        for (i, value) in values.iter().enumerate() {
+            let element = self.element(location, ty, i)?;
+            self.write_location(db, address, &element, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Mask of the lowest `n_bytes` bytes of a word
+fn mask(n_bytes: usize) -> U256 {
+    match n_bytes {
+        32 => U256::MAX,
+        n => (U256::from(1) << (8 * n)) - U256::from(1),
+    }
+}
+
+/// Length of a fixed size array from its type id,
+/// e.g. `t_array(t_uint256)3_storage`
+fn array_length(type_id: &str) -> Option<usize> {
+    type_id.rsplit(')').next()?.split('_').next()?.parse().ok()
+}
+
+/// Length of a dynamic value read from storage,
+/// checked against [MAX_DYNAMIC_LENGTH]
+fn dynamic_length(length: U256, label: &str) -> Result<usize, StorageLayoutError> {
+    match usize::try_from(length) {
+        Ok(length) if length <= MAX_DYNAMIC_LENGTH => Ok(length),
+        _ => Err(StorageLayoutError::ValueMismatch(label.to_string())),
+    }
+}
+
+fn read_bytes<D: DB>(
+    db: &mut D,
+    address: Address,
+    slot: U256,
+) -> Result<Bytes, StorageLayoutError> {
+    let word = db.storage(address, slot)?;
+
+    // Short values are stored in the slot with
+    // length * 2 in the lowest byte
+    if !word.bit(0) {
+        let length = usize::from(word.byte(0)) / 2;
+        if length > 31 {
+            return Err(StorageLayoutError::ValueMismatch("bytes".to_string()));
+        }
+        let data = word.to_be_bytes::<32>();
+        return Ok(Bytes::copy_from_slice(&data[..length]));
+    }
+
+    let length = dynamic_length(word >> 1usize, "bytes")?;
+    let start: U256 = keccak256(slot.to_be_bytes::<32>()).into();
+    let mut data = Vec::new();
+
+    for i in 0..length.div_ceil(32) {
+        let chunk = db.storage(address, start + U256::from(i))?;
+        data.extend_from_slice(&chunk.to_be_bytes::<32>());
+    }
+    data.truncate(length);
+
+    Ok(Bytes::from(data))
+}
+
+fn write_bytes<D: DB>(
+    db: &mut D,
+    address: Address,
+    slot: U256,
+    bytes: &Bytes,
+) -> Result<(), StorageLayoutError> {
+    if bytes.len() < 32 {
+        let mut data = [0u8; 32];
+        data[..bytes.len()].copy_from_slice(bytes);
+        data[31] = 2 * bytes.len() as u8;
+        db.insert_account_storage(address, slot, U256::from_be_bytes(data))?;
+        return Ok(());
+    }
+
+    db.insert_account_storage(address, slot, U256::from(2 * bytes.len() + 1))?;
+    let start: U256 = keccak256(slot.to_be_bytes::<32>()).into();
+
+    for (i, chunk) in bytes.chunks(32).enumerate() {
+        let mut data = [0u8; 32];
+        data[..chunk.len()].copy_from_slice(chunk);
+        db.insert_account_storage(address, start + U256::from(i), U256::from_be_bytes(data))?;
+    }
+
+    Ok(())
+}
+
+fn parse_path(path: &str) -> Result<(&str, Vec<Segment<'_>>), StorageLayoutError> {
+    let invalid = || StorageLayoutError::InvalidPath(path.to_string());
+
+    let end = path.find(['.', '[']).unwrap_or(path.len());
+    let (name, mut rest) = path.split_at(end);
+    if name.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix('.') {
+            let end = r.find(['.', '[']).unwrap_or(r.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            segments.push(Segment::Member(&r[..end]));
+            rest = &r[end..];
+        } else if let Some(r) = rest.strip_prefix('[') {
+            let end = r.find(']').ok_or_else(invalid)?;
+            let index = r[..end].trim().parse().map_err(|_| invalid())?;
+            segments.push(Segment::Index(index));
+            rest = &r[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    Ok((name, segments))
+}
+
+/// Solc writes sizes as decimal strings
+fn deserialize_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        String(String),
+        Number(usize),
+    }
+
+    match Number::deserialize(deserializer)? {
+        Number::String(s) => s.parse().map_err(de::Error::custom),
+        Number::Number(n) => Ok(n),
+    }
+}
+
+/// Solc writes `null` types for contracts without state variables
+fn deserialize_types<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, StorageType>, D::Error> {
+    Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalDB;
+    use revm::db::{in_memory_db::DbAccount, AccountState};
+    use rstest::*;
+
+    // Layout of
+    //
+    // contract Test {
+    //     struct Reserves { uint112 reserve0; uint112 reserve1; uint32 timestamp; }
+    //     uint128 a;
+    //     uint64 b;
+    //     address owner;
+    //     Reserves reserves;
+    //     mapping(address => uint256) balanceOf;
+    //     mapping(address => mapping(address => uint256)) allowance;
+    //     uint256[] values;
+    //     uint16[3] small;
+    //     string name;
+    // }
+    const LAYOUT: &str = r#"{
+        "storage": [
+            {"astId": 1, "contract": "Test.sol:Test", "label": "a", "offset": 0, "slot": "0", "type": "t_uint128"},
+            {"astId": 2, "contract": "Test.sol:Test", "label": "b", "offset": 16, "slot": "0", "type": "t_uint64"},
+            {"astId": 3, "contract": "Test.sol:Test", "label": "owner", "offset": 0, "slot": "1", "type": "t_address"},
+            {"astId": 4, "contract": "Test.sol:Test", "label": "reserves", "offset": 0, "slot": "2", "type": "t_struct(Reserves)10_storage"},
+            {"astId": 5, "contract": "Test.sol:Test", "label": "balanceOf", "offset": 0, "slot": "3", "type": "t_mapping(t_address,t_uint256)"},
+            {"astId": 6, "contract": "Test.sol:Test", "label": "allowance", "offset": 0, "slot": "4", "type": "t_mapping(t_address,t_mapping(t_address,t_uint256))"},
+            {"astId": 7, "contract": "Test.sol:Test", "label": "values", "offset": 0, "slot": "5", "type": "t_array(t_uint256)dyn_storage"},
+            {"astId": 8, "contract": "Test.sol:Test", "label": "small", "offset": 0, "slot": "6", "type": "t_array(t_uint16)3_storage"},
+            {"astId": 9, "contract": "Test.sol:Test", "label": "name", "offset": 0, "slot": "7", "type": "t_string_storage"}
+        ],
+        "types": {
+            "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+            "t_array(t_uint16)3_storage": {"base": "t_uint16", "encoding": "inplace", "label": "uint16[3]", "numberOfBytes": "32"},
+            "t_array(t_uint256)dyn_storage": {"base": "t_uint256", "encoding": "dynamic_array", "label": "uint256[]", "numberOfBytes": "32"},
+            "t_mapping(t_address,t_mapping(t_address,t_uint256))": {"encoding": "mapping", "key": "t_address", "label": "mapping(address => mapping(address => uint256))", "numberOfBytes": "32", "value": "t_mapping(t_address,t_uint256)"},
+            "t_mapping(t_address,t_uint256)": {"encoding": "mapping", "key": "t_address", "label": "mapping(address => uint256)", "numberOfBytes": "32", "value": "t_uint256"},
+            "t_string_storage": {"encoding": "bytes", "label": "string", "numberOfBytes": "32"},
+            "t_struct(Reserves)10_storage": {
+                "encoding": "inplace", "label": "struct Test.Reserves", "numberOfBytes": "32",
+                "members": [
+                    {"astId": 11, "contract": "Test.sol:Test", "label": "reserve0", "offset": 0, "slot": "0", "type": "t_uint112"},
+                    {"astId": 12, "contract": "Test.sol:Test", "label": "reserve1", "offset": 14, "slot": "0", "type": "t_uint112"},
+                    {"astId": 13, "contract": "Test.sol:Test", "label": "timestamp", "offset": 28, "slot": "0", "type": "t_uint32"}
+                ]
+            },
+            "t_uint112": {"encoding": "inplace", "label": "uint112", "numberOfBytes": "14"},
+            "t_uint128": {"encoding": "inplace", "label": "uint128", "numberOfBytes": "16"},
+            "t_uint16": {"encoding": "inplace", "label": "uint16", "numberOfBytes": "2"},
+            "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"},
+            "t_uint32": {"encoding": "inplace", "label": "uint32", "numberOfBytes": "4"},
+            "t_uint64": {"encoding": "inplace", "label": "uint64", "numberOfBytes": "8"}
+        }
+    }"#;
+
+    const CONTRACT: Address = Address::repeat_byte(1);
+
+    #[fixture]
+    fn layout() -> StorageLayout {
+        StorageLayout::from_json(LAYOUT).unwrap()
+    }
+
+    #[fixture]
+    fn db() -> LocalDB {
+        let mut db = LocalDB::new();
+        // Storage of a newly deployed contract
+        db.accounts.insert(
+            CONTRACT,
+            DbAccount {
+                account_state: AccountState::StorageCleared,
+                ..Default::default()
+            },
+        );
+        db
+    }
+
+    fn slot(db: &LocalDB, slot: U256) -> U256 {
+        db.accounts[&CONTRACT].storage[&slot]
+    }
+
+    fn word(x: u64) -> StorageValue {
+        StorageValue::Word(U256::from(x))
+    }
+
+    #[rstest]
+    fn test_packed_values(layout: StorageLayout, mut db: LocalDB) {
+        layout.write(&mut db, CONTRACT, "a", &[], &word(1)).unwrap();
+        layout.write(&mut db, CONTRACT, "b", &[], &word(2)).unwrap();
+
+        assert_eq!(
+            slot(&db, U256::ZERO),
+            U256::from(1) | (U256::from(2) << 128)
+        );
+        assert_eq!(layout.read(&mut db, CONTRACT, "a", &[]).unwrap(), word(1));
+        assert_eq!(layout.read(&mut db, CONTRACT, "b", &[]).unwrap(), word(2));
+
+        // Values must fit the type
+        assert!(matches!(
+            layout.write(&mut db, CONTRACT, "b", &[], &U256::MAX.into()),
+            Err(StorageLayoutError::ValueMismatch(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_struct(layout: StorageLayout, mut db: LocalDB) {
+        let reserves = StorageValue::Struct(vec![
+            ("reserve0".to_string(), word(10)),
+            ("reserve1".to_string(), word(20)),
+            ("timestamp".to_string(), word(30)),
+        ]);
+        layout
+            .write(&mut db, CONTRACT, "reserves", &[], &reserves)
+            .unwrap();
+
+        assert_eq!(
+            slot(&db, U256::from(2)),
+            U256::from(10) | (U256::from(20) << 112) | (U256::from(30) << 224)
+        );
+        assert_eq!(
+            layout.read(&mut db, CONTRACT, "reserves", &[]).unwrap(),
+            reserves
+        );
+        assert_eq!(
+            layout
+                .read(&mut db, CONTRACT, "reserves.reserve1", &[])
+                .unwrap(),
+            word(20)
+        );
+    }
+
+    #[rstest]
+    fn test_mappings(layout: StorageLayout, mut db: LocalDB) {
+        let owner = Address::repeat_byte(2);
+        let spender = Address::repeat_byte(3);
+
+        let location = layout.locate("balanceOf", &[owner.into()]).unwrap();
+        let mut key = owner.into_word().to_vec();
+        key.extend_from_slice(&U256::from(3).to_be_bytes::<32>());
+        assert_eq!(location.slot, U256::from_be_bytes(keccak256(key).0));
+
+        let keys = [owner.into(), spender.into()];
+        layout
+            .write(&mut db, CONTRACT, "allowance", &keys, &word(5))
+            .unwrap();
+        assert_eq!(
+            layout.read(&mut db, CONTRACT, "allowance", &keys).unwrap(),
+            word(5)
+        );
+
+        assert!(matches!(
+            layout.read(&mut db, CONTRACT, "allowance", &keys[..1]),
+            Err(StorageLayoutError::Mapping(_))
+        ));
+        assert!(matches!(
+            layout.read(&mut db, CONTRACT, "balanceOf", &keys),
+            Err(StorageLayoutError::InvalidPath(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_arrays(layout: StorageLayout, mut db: LocalDB) {
+        let values = StorageValue::Array(vec![word(1), word(2), word(3)]);
+        layout
+            .write(&mut db, CONTRACT, "values", &[], &values)
+            .unwrap();
+
+        let start: U256 = keccak256(U256::from(5).to_be_bytes::<32>()).into();
+        assert_eq!(slot(&db, U256::from(5)), U256::from(3));
+        assert_eq!(slot(&db, start + U256::from(2)), U256::from(3));
+        assert_eq!(
+            layout.read(&mut db, CONTRACT, "values", &[]).unwrap(),
+            values
+        );
+        assert_eq!(
+            layout.read(&mut db, CONTRACT, "values[1]", &[]).unwrap(),
+            word(2)
+        );
+
+        let small = StorageValue::Array(vec![word(4), word(5), word(6)]);
+        layout
+            .write(&mut db, CONTRACT, "small", &[], &small)
+            .unwrap();
+        assert_eq!(
+            slot(&db, U256::from(6)),
+            U256::from(4) | (U256::from(5) << 16) | (U256::from(6) << 32)
+        );
+        assert_eq!(layout.read(&mut db, CONTRACT, "small", &[]).unwrap(), small);
+        assert!(matches!(
+            layout.read(&mut db, CONTRACT, "small[3]", &[]),
+            Err(StorageLayoutError::OutOfBounds(_, 3))
+        ));
+    }
+
+    #[rstest]
+    #[case("verbs")]
+    #[case("a string that is longer than thirty one bytes")]
+    fn test_strings(layout: StorageLayout, mut db: LocalDB, #[case] name: &str) {
+        layout
+            .write(&mut db, CONTRACT, "name", &[], &name.into())
+            .unwrap();
+        let value = layout.read(&mut db, CONTRACT, "name", &[]).unwrap();
+        assert_eq!(value.as_bytes().unwrap().as_ref(), name.as_bytes());
+    }
+
+    #[rstest]
+    fn test_invalid_lengths(layout: StorageLayout, mut db: LocalDB) {
+        let storage = &mut db.accounts.get_mut(&CONTRACT).unwrap().storage;
+        // Short string longer than 31 bytes
+        storage.insert(U256::from(7), U256::from(0xfe));
+        // Array length that does not fit a usize
+        storage.insert(U256::from(5), U256::MAX);
+
+        assert!(matches!(
+            layout.read(&mut db, CONTRACT, "name", &[]),
+            Err(StorageLayoutError::ValueMismatch(_))
+        ));
+        assert!(matches!(
+            layout.read(&mut db, CONTRACT, "values", &[]),
+            Err(StorageLayoutError::ValueMismatch(_))
+        ));
+
+        // Long string whose length does not fit a usize
+        db.accounts
+            .get_mut(&CONTRACT)
+            .unwrap()
+            .storage
+            .insert(U256::from(7), U256::MAX);
+        assert!(matches!(
+            layout.read(&mut db, CONTRACT, "name", &[]),
+            Err(StorageLayoutError::ValueMismatch(_))
+        ));
+
+        // Lengths that fit a usize, but are above the limit
+        let storage = &mut db.accounts.get_mut(&CONTRACT).unwrap().storage;
+        storage.insert(U256::from(5), U256::from(1u64 << 40));
+        storage.insert(U256::from(7), U256::from((1u64 << 40) * 2 + 1));
+
+        assert!(matches!(
+            layout.read(&mut db, CONTRACT, "values", &[]),
+            Err(StorageLayoutError::ValueMismatch(_))
+        ));
+        assert!(matches!(
+            layout.read(&mut db, CONTRACT, "name", &[]),
+            Err(StorageLayoutError::ValueMismatch(_))
+        ));
+        // Elements can still be read individually
+        assert!(layout.read(&mut db, CONTRACT, "values[70000]", &[]).is_ok());
+    }
+
+    #[rstest]
+    fn test_invalid_paths(layout: StorageLayout) {
+        assert!(matches!(
+            layout.locate("missing", &[]),
+            Err(StorageLayoutError::UnknownVariable(_))
+        ));
+        assert!(matches!(
+            layout.locate("reserves.missing", &[]),
+            Err(StorageLayoutError::UnknownMember(..))
+        ));
+        assert!(matches!(
+            layout.locate("values[", &[]),
+            Err(StorageLayoutError::InvalidPath(_))
+        ));
+    }
+}
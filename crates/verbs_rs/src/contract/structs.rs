@@ -3,9 +3,10 @@
 use alloy_primitives::{Address, U256};
 use alloy_sol_types::SolCall;
 use revm::primitives::{Log, Output};
+use serde::{Deserialize, Serialize};
 
 /// EVM transaction argument data
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Transaction {
     /// 4 byte function selector
     pub function_selector: [u8; 4],
@@ -22,7 +23,7 @@ pub struct Transaction {
     /// Value attached to the transaction
     pub value: U256,
     /// Flag, if `true` the simulation will halt (panic)
-    /// if this transaction is reverted.
+    /// if this transaction is reverted, halted or invalid.
     pub checked: bool,
 }
 
@@ -79,9 +80,11 @@ pub struct TransactionResult {
 /// transactions. This allow a history of simulation
 /// events to be recreated after a simulation
 ///
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Event {
     /// If the event was successful (i.e. `false`
-    /// indicates a transaction was reverted)
+    /// indicates a transaction was reverted, halted
+    /// or invalid)
     pub success: bool,
     /// 4 byte function selector of the called function
     pub function_selector: [u8; 4],
@@ -91,4 +94,8 @@ pub struct Event {
     pub step: usize,
     /// Sequence the event was created inside a block
     pub sequence: usize,
+    /// Gas used by the transaction
+    pub gas_used: u64,
+    /// Value attached to the transaction
+    pub value: U256,
 }
@@ -0,0 +1,239 @@
+//! Export of simulation state as an anvil state dump
+//!
+//! Writes the accounts (balance, nonce, code and
+//! storage) and block environment of a simulation
+//! in the JSON format used by anvil `--dump-state`,
+//! so the state at any point of a simulation can be
+//! loaded into anvil (using `--load-state`) and
+//! inspected with standard Ethereum tooling.
+//!
+//! Exported states can also be loaded back into
+//! a simulation using [super::GenesisState].
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::{Address, U256};
+//! use verbs_rs::env::{Env, RandomValidator};
+//! use verbs_rs::{AnvilState, GenesisState, LocalDB};
+//!
+//! let mut env = Env::<LocalDB, RandomValidator>::init(
+//!     U256::ZERO, U256::from(10), RandomValidator {}
+//! );
+//! env.insert_account(Address::repeat_byte(1), U256::from(1000));
+//!
+//! let json = AnvilState::from_env(&env).to_json().unwrap();
+//!
+//! let genesis = GenesisState::from_json(&json).unwrap();
+//! assert_eq!(genesis.number, Some(U256::from(10)));
+//! assert_eq!(
+//!     genesis.accounts[&Address::repeat_byte(1)].balance,
+//!     U256::from(1000)
+//! );
+//! ```
+
+use super::traits::DB;
+use crate::env::{Env, Validator};
+use alloy_primitives::{Address, Bytes, U256};
+use revm::primitives::BlockEnv;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Error raised writing an anvil state dump
+#[derive(Debug, thiserror::Error)]
+pub enum AnvilStateError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Account in an anvil state dump
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct AnvilAccount {
+    /// Account nonce
+    pub nonce: u64,
+    /// Account balance
+    pub balance: U256,
+    /// Contract code
+    pub code: Bytes,
+    /// Contract storage
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// Anvil state dump
+#[derive(Clone, Debug, Serialize)]
+pub struct AnvilState {
+    /// Block environment
+    pub block: BlockEnv,
+    /// Accounts
+    pub accounts: BTreeMap<Address, AnvilAccount>,
+}
+
+impl AnvilState {
+    /// Create a state dump of an environment
+    ///
+    /// Includes the full state exported by the
+    /// environment DB (see [DB::export_state]), so
+    /// the dump of a forked simulation only contains
+    /// the accounts and storage slots that have been
+    /// loaded from the fork (or modified by the
+    /// simulation).
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Simulation environment
+    ///
+    pub fn from_env<D: DB, V: Validator>(env: &Env<D, V>) -> Self {
+        let view = env.view();
+        let db = view.db().export_state();
+
+        let accounts = db
+            .accounts
+            .iter()
+            .filter_map(|(address, account)| {
+                let info = account.info()?;
+                let code = info
+                    .code
+                    .as_ref()
+                    .filter(|code| !code.is_empty())
+                    .or_else(|| db.contracts.get(&info.code_hash))
+                    .map(|code| code.original_bytes())
+                    .unwrap_or_default();
+                Some((
+                    *address,
+                    AnvilAccount {
+                        nonce: info.nonce,
+                        balance: info.balance,
+                        code,
+                        storage: account.storage.iter().map(|(k, v)| (*k, *v)).collect(),
+                    },
+                ))
+            })
+            .collect();
+
+        AnvilState {
+            block: view.block().clone(),
+            accounts,
+        }
+    }
+
+    /// Encode the state dump as JSON
+    pub fn to_json(&self) -> Result<String, AnvilStateError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Write the state dump as JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Writer the JSON is written to
+    ///
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), AnvilStateError> {
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Save the state dump to a JSON file
+    ///
+    /// The file can be loaded by anvil using
+    /// `anvil --load-state <path>`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to write
+    ///
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AnvilStateError> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::RandomValidator;
+    use crate::utils::Eth;
+    use crate::{GenesisState, LocalDB};
+    use revm::primitives::{AccountInfo, Bytecode};
+
+    #[test]
+    fn test_layered_env() {
+        let mut env =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
+        env.evm_state()
+            .context
+            .evm
+            .db
+            .insert_account_storage(Address::ZERO, U256::from(1), U256::from(2))
+            .unwrap();
+
+        let mut env = env.into_layered();
+        env.insert_account(Address::repeat_byte(1), U256::from(3));
+
+        let state = AnvilState::from_env(&env);
+        // Accounts and storage of the base DB are included
+        assert_eq!(
+            state.accounts[&Address::ZERO].balance,
+            U256::to_weth(10_000)
+        );
+        assert_eq!(
+            state.accounts[&Address::ZERO].storage[&U256::from(1)],
+            U256::from(2)
+        );
+        assert_eq!(
+            state.accounts[&Address::repeat_byte(1)].balance,
+            U256::from(3)
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut env = Env::<LocalDB, RandomValidator>::init(
+            U256::from(100),
+            U256::from(5),
+            RandomValidator {},
+        );
+
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x00]));
+        env.evm_state().context.evm.db.insert_account_info(
+            Address::repeat_byte(1),
+            AccountInfo::new(U256::from(7), 3, code.hash_slow(), code),
+        );
+        env.evm_state()
+            .context
+            .evm
+            .db
+            .insert_account_storage(Address::repeat_byte(1), U256::from(1), U256::from(2))
+            .unwrap();
+
+        let state = AnvilState::from_env(&env);
+        let json: serde_json::Value = serde_json::from_str(&state.to_json().unwrap()).unwrap();
+
+        assert_eq!(json["block"]["number"], "0x5");
+        assert_eq!(json["block"]["timestamp"], "0x64");
+
+        let account = &json["accounts"]["0x0101010101010101010101010101010101010101"];
+        assert_eq!(account["nonce"], 3);
+        assert_eq!(account["balance"], "0x7");
+        assert_eq!(account["code"], "0x6000");
+        assert_eq!(account["storage"]["0x1"], "0x2");
+
+        let genesis = GenesisState::from_json(&state.to_json().unwrap()).unwrap();
+        let db = LocalDB::from_genesis(&genesis);
+
+        assert_eq!(genesis.timestamp, Some(U256::from(100)));
+        assert_eq!(db.accounts[&Address::repeat_byte(1)].info.nonce, 3);
+        assert_eq!(
+            db.accounts[&Address::repeat_byte(1)].storage[&U256::from(1)],
+            U256::from(2)
+        );
+        assert_eq!(
+            db.accounts[&Address::ZERO].info.balance,
+            U256::to_weth(10_000)
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Record and replay of fork requests
+//!
+//! A cassette is a JSON-lines file, where each line
+//! stores a JSON-RPC request (method and parameters)
+//! made by a [super::ForkDb] along with the response
+//! from the remote endpoint. Cassettes allow forked
+//! simulations and tests to be re-run deterministically
+//! without access to the network.
+//!
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Data source mode of a [super::ForkDb]
+#[derive(Debug, Clone, Default)]
+pub enum ForkMode {
+    /// Make all requests to the remote endpoint
+    #[default]
+    Live,
+    /// Make requests to the remote endpoint and
+    /// write every response to a new cassette file
+    Record(PathBuf),
+    /// Serve requests only from a cassette file,
+    /// returning an error if a request is missing
+    Replay(PathBuf),
+    /// Serve requests from a cassette file, falling
+    /// back to the remote endpoint (and appending
+    /// the response to the cassette) if missing
+    Hybrid(PathBuf),
+}
+
+impl ForkMode {
+    /// Whether the mode runs without network access
+    pub fn is_offline(&self) -> bool {
+        matches!(self, ForkMode::Replay(_))
+    }
+}
+
+/// Error raised opening or writing a cassette
+#[derive(Debug, thiserror::Error)]
+pub enum CassetteError {
+    #[error("invalid cassette entry on line {0}: {1}")]
+    Entry(usize, serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Single recorded request and response
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    method: String,
+    params: Value,
+    result: Value,
+}
+
+/// Request/response store used by a fork client
+#[derive(Debug)]
+pub(crate) struct Cassette {
+    serve: bool,
+    record: bool,
+    responses: Mutex<HashMap<String, Value>>,
+    file: Option<Mutex<File>>,
+}
+
+impl Cassette {
+    /// Open the cassette used by a fork mode
+    ///
+    /// Returns `None` for [ForkMode::Live].
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Mode of the fork DB
+    ///
+    pub fn open(mode: &ForkMode) -> Result<Option<Self>, CassetteError> {
+        let cassette = match mode {
+            ForkMode::Live => return Ok(None),
+            ForkMode::Record(path) => Cassette {
+                serve: false,
+                record: true,
+                responses: Mutex::new(HashMap::new()),
+                file: Some(Mutex::new(File::create(path)?)),
+            },
+            ForkMode::Replay(path) => Cassette {
+                serve: true,
+                record: false,
+                responses: Mutex::new(read_entries(path)?),
+                file: None,
+            },
+            ForkMode::Hybrid(path) => {
+                let responses = match path.exists() {
+                    true => read_entries(path)?,
+                    false => HashMap::new(),
+                };
+                Cassette {
+                    serve: true,
+                    record: true,
+                    responses: Mutex::new(responses),
+                    file: Some(Mutex::new(
+                        OpenOptions::new().create(true).append(true).open(path)?,
+                    )),
+                }
+            }
+        };
+        Ok(Some(cassette))
+    }
+
+    /// Whether requests missing from the cassette
+    /// can be made to the remote endpoint
+    pub fn allows_network(&self) -> bool {
+        self.record
+    }
+
+    /// Get a recorded response
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - JSON-RPC method
+    /// * `params` - Request parameters
+    ///
+    pub fn get(&self, method: &str, params: &Value) -> Option<Value> {
+        match self.serve {
+            true => self
+                .responses
+                .lock()
+                .unwrap()
+                .get(&request_key(method, params))
+                .cloned(),
+            false => None,
+        }
+    }
+
+    /// Record a response
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - JSON-RPC method
+    /// * `params` - Request parameters
+    /// * `result` - Response from the remote endpoint
+    ///
+    pub fn insert(&self, method: &str, params: Value, result: Value) -> std::io::Result<()> {
+        if let Some(file) = &self.file {
+            let entry = Entry {
+                method: method.to_string(),
+                params,
+                result,
+            };
+            let mut line = serde_json::to_string(&entry)?;
+            line.push('\n');
+            let mut file = file.lock().unwrap();
+            file.write_all(line.as_bytes())?;
+            file.flush()?;
+
+            self.responses
+                .lock()
+                .unwrap()
+                .insert(request_key(method, &entry.params), entry.result);
+        }
+        Ok(())
+    }
+}
+
+/// Key identifying a request in a cassette
+pub(crate) fn request_key(method: &str, params: &Value) -> String {
+    format!("{}:{}", method, params)
+}
+
+fn read_entries(path: &Path) -> Result<HashMap<String, Value>, CassetteError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut responses = HashMap::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Entry =
+            serde_json::from_str(&line).map_err(|e| CassetteError::Entry(i + 1, e))?;
+        responses.insert(request_key(&entry.method, &entry.params), entry.result);
+    }
+
+    Ok(responses)
+}
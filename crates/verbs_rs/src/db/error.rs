@@ -1,7 +1,7 @@
 use alloy_primitives::{Address, B256, U256};
 use ethers_core::types::BlockId;
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 #[allow(missing_docs)]
 pub enum DatabaseError {
     #[error("{0}")]
@@ -24,6 +24,10 @@ pub enum DatabaseError {
     BlockNotFound(BlockId),
     #[error("failed to get transaction {0}")]
     GetTransaction(B256),
+    #[error("failed to get full storage for {0}")]
+    GetFullStorage(Address),
+    #[error("request missing from fork cassette: {0}")]
+    CassetteMiss(String),
 }
 
 impl DatabaseError {
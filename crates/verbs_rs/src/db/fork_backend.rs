@@ -0,0 +1,384 @@
+//! Data sources for forked DBs
+//!
+//! A [ForkBackend] provides the account, storage,
+//! code and block-hash values a [super::ForkDb]
+//! loads on demand. Implementations are provided
+//! for a remote JSON-RPC endpoint
+//! ([super::RpcBackend]), a cached file
+//! ([FileBackend]) and an in-process mock that can
+//! be populated by tests ([MockBackend]).
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::Arc;
+//! use alloy_primitives::{Address, U256};
+//! use revm::primitives::AccountInfo;
+//! use revm::Database;
+//! use verbs_rs::{ForkDb, MockBackend};
+//!
+//! let mut backend = MockBackend::new(U256::from(100), U256::from(1_700_000_000));
+//! backend.insert_account(
+//!     Address::repeat_byte(1), AccountInfo::from_balance(U256::from(1000))
+//! );
+//! let backend = Arc::new(backend);
+//!
+//! let mut db = ForkDb::from_backend(backend.clone());
+//! let info = db.basic(Address::repeat_byte(1)).unwrap().unwrap();
+//!
+//! assert_eq!(info.balance, U256::from(1000));
+//! assert_eq!(backend.n_requests(), 1);
+//! ```
+
+use super::error::DatabaseError;
+use super::historical::HistoricalBlock;
+use super::local_db::LocalDB;
+use super::snapshot::{Snapshot, SnapshotError};
+use super::types::RequestCache;
+use alloy_primitives::{Address, B256, U256};
+use ethers_core::types::BlockId;
+use revm::primitives::{AccountInfo, Bytecode, HashMap};
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Block a fork DB starts from
+#[derive(Debug, Clone, Default)]
+pub struct ForkBlock {
+    /// Block number
+    pub number: U256,
+    /// Block timestamp
+    pub timestamp: U256,
+}
+
+/// Source of data for a [super::ForkDb]
+///
+/// A backend serves values from the state of
+/// the chain at a fixed block ([ForkBlock]).
+/// The batch methods can be overridden by
+/// backends that can load several values
+/// more efficiently than one at a time.
+pub trait ForkBackend: Debug + Send + Sync {
+    /// Block the backend serves values from
+    fn block(&self) -> ForkBlock;
+
+    /// Get account info, `None` if the account does not exist
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the account
+    ///
+    fn account(&self, address: Address) -> Result<Option<AccountInfo>, DatabaseError>;
+
+    /// Get a storage value
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract
+    /// * `index` - Storage slot index
+    ///
+    fn storage(&self, address: Address, index: U256) -> Result<U256, DatabaseError>;
+
+    /// Get contract code from its hash
+    ///
+    /// Only called for code not returned as
+    /// part of the account info.
+    ///
+    /// # Arguments
+    ///
+    /// * `code_hash` - Hash of the contract code
+    ///
+    fn code_by_hash(&self, code_hash: B256) -> Result<Bytecode, DatabaseError> {
+        Err(DatabaseError::MissingCode(code_hash))
+    }
+
+    /// Get the hash of a block
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Block number
+    ///
+    fn block_hash(&self, number: U256) -> Result<B256, DatabaseError>;
+
+    /// Get the full storage of a contract
+    ///
+    /// Returns `None` if the backend cannot
+    /// load storage in bulk.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract
+    ///
+    fn full_storage(
+        &self,
+        _address: Address,
+    ) -> Result<Option<HashMap<U256, U256>>, DatabaseError> {
+        Ok(None)
+    }
+
+    /// Get a block and its transactions
+    ///
+    /// Returns `None` if the block does not exist.
+    /// Raises an error by default, for backends
+    /// that cannot load historical blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Block number
+    ///
+    fn block_transactions(&self, number: U256) -> Result<Option<HistoricalBlock>, DatabaseError> {
+        Err(DatabaseError::GetFullBlock(BlockId::from(
+            number.saturating_to::<u64>(),
+        )))
+    }
+
+    /// Get info of multiple accounts
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses` - Addresses of the accounts
+    ///
+    fn accounts(&self, addresses: &[Address]) -> Vec<Result<Option<AccountInfo>, DatabaseError>> {
+        addresses.iter().map(|a| self.account(*a)).collect()
+    }
+
+    /// Get multiple storage values
+    ///
+    /// # Arguments
+    ///
+    /// * `slots` - Contract addresses and storage indices
+    ///
+    fn storage_slots(&self, slots: &[(Address, U256)]) -> Vec<Result<U256, DatabaseError>> {
+        slots.iter().map(|(a, i)| self.storage(*a, *i)).collect()
+    }
+}
+
+/// Backend serving values from a cached DB state
+///
+/// Serves values from a [LocalDB], e.g. loaded
+/// from a [Snapshot] file or a [RequestCache].
+/// Accounts missing from the cache are treated
+/// as not existing, and missing storage values
+/// as zero.
+#[derive(Debug, Clone)]
+pub struct FileBackend {
+    block: ForkBlock,
+    db: LocalDB,
+}
+
+impl FileBackend {
+    /// Create a backend from a snapshot
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - Snapshot of a DB and block
+    ///
+    pub fn from_snapshot(snapshot: Snapshot) -> Self {
+        FileBackend {
+            block: ForkBlock {
+                number: snapshot.block.number,
+                timestamp: snapshot.block.timestamp,
+            },
+            db: snapshot.db,
+        }
+    }
+
+    /// Create a backend from a cache of fork requests
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - Requests made by a [super::ForkDb]
+    ///
+    pub fn from_request_cache(cache: &RequestCache) -> Self {
+        FileBackend {
+            block: ForkBlock {
+                number: cache.start_block_number,
+                timestamp: cache.start_timestamp,
+            },
+            db: LocalDB::from_request_cache(cache),
+        }
+    }
+
+    /// Load a backend from a snapshot file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of a file written by [Snapshot::save]
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        Ok(Self::from_snapshot(Snapshot::load(path)?))
+    }
+}
+
+impl ForkBackend for FileBackend {
+    fn block(&self) -> ForkBlock {
+        self.block.clone()
+    }
+
+    fn account(&self, address: Address) -> Result<Option<AccountInfo>, DatabaseError> {
+        Ok(self.db.accounts.get(&address).and_then(|a| a.info()))
+    }
+
+    fn storage(&self, address: Address, index: U256) -> Result<U256, DatabaseError> {
+        Ok(self
+            .db
+            .accounts
+            .get(&address)
+            .and_then(|a| a.storage.get(&index).copied())
+            .unwrap_or_default())
+    }
+
+    fn code_by_hash(&self, code_hash: B256) -> Result<Bytecode, DatabaseError> {
+        match self.db.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => Err(DatabaseError::MissingCode(code_hash)),
+        }
+    }
+
+    fn block_hash(&self, number: U256) -> Result<B256, DatabaseError> {
+        match self.db.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => Err(DatabaseError::GetBlockHash(number)),
+        }
+    }
+
+    fn full_storage(&self, address: Address) -> Result<Option<HashMap<U256, U256>>, DatabaseError> {
+        Ok(Some(
+            self.db
+                .accounts
+                .get(&address)
+                .map(|a| a.storage.clone())
+                .unwrap_or_default(),
+        ))
+    }
+}
+
+/// In-process backend for tests
+///
+/// Backend populated directly with account,
+/// storage and block-hash values, that counts
+/// the number of requests made to it.
+#[derive(Debug)]
+pub struct MockBackend {
+    inner: FileBackend,
+    blocks: HashMap<U256, HistoricalBlock>,
+    n_requests: AtomicUsize,
+}
+
+impl MockBackend {
+    /// Create an empty mock backend
+    ///
+    /// # Arguments
+    ///
+    /// * `block_number` - Number of the fork block
+    /// * `timestamp` - Timestamp of the fork block
+    ///
+    pub fn new(block_number: U256, timestamp: U256) -> Self {
+        MockBackend {
+            inner: FileBackend {
+                block: ForkBlock {
+                    number: block_number,
+                    timestamp,
+                },
+                db: LocalDB::new(),
+            },
+            blocks: HashMap::new(),
+            n_requests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Insert an account
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the account
+    /// * `info` - Account info (including any code)
+    ///
+    pub fn insert_account(&mut self, address: Address, info: AccountInfo) {
+        self.inner.db.insert_account_info(address, info);
+    }
+
+    /// Insert a storage value of an account
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the account
+    /// * `index` - Storage slot index
+    /// * `value` - Storage value
+    ///
+    pub fn insert_storage(&mut self, address: Address, index: U256, value: U256) {
+        self.inner
+            .db
+            .accounts
+            .entry(address)
+            .or_default()
+            .storage
+            .insert(index, value);
+    }
+
+    /// Insert the hash of a block
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Block number
+    /// * `hash` - Block hash
+    ///
+    pub fn insert_block_hash(&mut self, number: U256, hash: B256) {
+        self.inner.db.block_hashes.insert(number, hash);
+    }
+
+    /// Insert a block and its transactions
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - Block and transactions
+    ///
+    pub fn insert_block(&mut self, block: HistoricalBlock) {
+        self.blocks.insert(block.number, block);
+    }
+
+    /// Number of values requested from the backend
+    pub fn n_requests(&self) -> usize {
+        self.n_requests.load(Ordering::SeqCst)
+    }
+
+    fn count(&self) {
+        self.n_requests.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl ForkBackend for MockBackend {
+    fn block(&self) -> ForkBlock {
+        self.inner.block()
+    }
+
+    fn account(&self, address: Address) -> Result<Option<AccountInfo>, DatabaseError> {
+        self.count();
+        self.inner.account(address)
+    }
+
+    fn storage(&self, address: Address, index: U256) -> Result<U256, DatabaseError> {
+        self.count();
+        self.inner.storage(address, index)
+    }
+
+    fn code_by_hash(&self, code_hash: B256) -> Result<Bytecode, DatabaseError> {
+        self.count();
+        self.inner.code_by_hash(code_hash)
+    }
+
+    fn block_hash(&self, number: U256) -> Result<B256, DatabaseError> {
+        self.count();
+        self.inner.block_hash(number)
+    }
+
+    fn full_storage(&self, address: Address) -> Result<Option<HashMap<U256, U256>>, DatabaseError> {
+        self.count();
+        self.inner.full_storage(address)
+    }
+
+    fn block_transactions(&self, number: U256) -> Result<Option<HistoricalBlock>, DatabaseError> {
+        self.count();
+        Ok(self.blocks.get(&number).cloned())
+    }
+}
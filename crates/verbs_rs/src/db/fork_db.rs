@@ -1,19 +1,19 @@
+use super::cassette::ForkMode;
 use super::error::DatabaseError;
-use super::provider::ProviderBuilder;
-use super::runtime_client::RuntimeClient;
+use super::fork_backend::{ForkBackend, ForkBlock};
+use super::rpc_backend::RpcBackend;
+use super::storage_dump::StorageDump;
 use super::traits::DB;
 use super::types::RequestCache;
-use super::types::{ToAlloy, ToEthers};
-use alloy_primitives::{keccak256, Address, Bytes};
-pub use ethers_core::types::BlockNumber;
-use ethers_core::types::{BigEndianHash, Block, BlockId, NameOrAddress, H256};
-use ethers_providers::{Middleware, Provider, ProviderError};
+use alloy_primitives::Address;
+use itertools::Itertools;
 use revm::db::in_memory_db::DbAccount;
 use revm::db::{AccountState, DatabaseCommit};
 use revm::primitives::{
     hash_map::Entry, Account, AccountInfo, Bytecode, HashMap, Log, B256, KECCAK_EMPTY, U256,
 };
-use revm::Database;
+use revm::{Database, DatabaseRef};
+use std::sync::{Arc, RwLock};
 
 /// Database with ability to load data from a remote fork
 ///
@@ -27,70 +27,336 @@ use revm::Database;
 ///
 /// Since it makes requests from a remote endpoint
 /// this database can be significantly slower than
-/// a purely local DB like [super::LocalDB].
+/// a purely local DB like [super::LocalDB]. Accounts
+/// and storage used by a simulation can be loaded in
+/// concurrent batches before it starts using
+/// [ForkDb::prefetch_accounts] and [ForkDb::prefetch_storage].
 ///
 /// </div>
 ///
+/// Values requested from the fork backend are kept
+/// in a read cache shared by clones of the DB (the
+/// backend state at the fork block never changes),
+/// so read-only calls (e.g. using [crate::env::EnvView])
+/// from multiple threads or multiple clones of an
+/// environment only request each value once.
+///
+/// Requests can be recorded to, and replayed from,
+/// a local cassette file (see [ForkMode]), so forked
+/// simulations can be re-run offline. Values can also
+/// be loaded from other sources implementing
+/// [ForkBackend] (see [ForkDb::from_backend]).
+///
+/// # Examples
+///
+/// ```no_run
+/// use verbs_rs::{ForkDb, ForkMode};
+///
+/// // Record requests made to the remote endpoint
+/// let db = ForkDb::with_mode(
+///     "https://eth-mainnet.g.alchemy.com/v2/KEY",
+///     Some(19_000_000),
+///     ForkMode::Record("cassette.jsonl".into()),
+/// );
+/// // ...
+/// // Replay the recorded requests offline
+/// let db = ForkDb::with_mode(
+///     "", Some(19_000_000), ForkMode::Replay("cassette.jsonl".into())
+/// );
+/// ```
 #[derive(Debug, Clone)]
 pub struct ForkDb {
     pub accounts: HashMap<Address, DbAccount>,
     pub contracts: HashMap<B256, Bytecode>,
     pub logs: Vec<Log>,
     pub block_hashes: HashMap<U256, B256>,
-    provider: Provider<RuntimeClient>,
-    block_id: Option<BlockId>,
-    pub block: Block<H256>,
+    backend: Arc<dyn ForkBackend>,
+    /// Values requested from the backend
+    read_cache: Arc<RwLock<ReadCache>>,
+    pub block: ForkBlock,
     pub requests: RequestCache,
 }
 
+/// Values loaded from a fork backend
+#[derive(Debug, Default)]
+struct ReadCache {
+    accounts: HashMap<Address, Option<AccountInfo>>,
+    storage: HashMap<(Address, U256), U256>,
+    contracts: HashMap<B256, Bytecode>,
+    block_hashes: HashMap<U256, B256>,
+}
+
 impl ForkDb {
     pub fn new(node_url: &str, block_number: Option<u64>) -> Self {
-        let block_number = match block_number {
-            Some(n) => BlockNumber::Number(n.into()),
-            None => BlockNumber::Latest,
-        };
-
-        let provider = ProviderBuilder::new(node_url).build().unwrap();
+        Self::with_mode(node_url, block_number, ForkMode::Live)
+    }
 
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .unwrap();
+    /// Initialise a fork DB with a data source mode
+    ///
+    /// # Arguments
+    ///
+    /// * `node_url` - Url of service to make db requests,
+    ///   unused in [ForkMode::Replay] mode.
+    /// * `block_number` - Block number to fork from, if None
+    ///   latest available block will be used.
+    /// * `mode` - Fork mode, i.e. whether requests are
+    ///   recorded to or replayed from a cassette file.
+    ///
+    pub fn with_mode(node_url: &str, block_number: Option<u64>, mode: ForkMode) -> Self {
+        Self::from_backend(Arc::new(RpcBackend::with_mode(
+            node_url,
+            block_number,
+            mode,
+        )))
+    }
 
-        let block = rt.block_on(provider.get_block(block_number)).unwrap();
-
-        let block = match block {
-            Some(b) => b,
-            None => panic!("Could not retrieve block"),
-        };
+    /// Initialise a fork DB loading values from a backend
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Source of account, storage, code
+    ///   and block-hash values
+    ///
+    pub fn from_backend(backend: Arc<dyn ForkBackend>) -> Self {
+        let block = backend.block();
 
         let mut contracts = HashMap::new();
         contracts.insert(KECCAK_EMPTY, Bytecode::new());
         contracts.insert(B256::ZERO, Bytecode::new());
 
-        // Track the original time and block for when we want
-        //  to run a sim using the same cache
-        let timestamp = U256::try_from(block.timestamp.as_u128()).unwrap();
-        let block_number = match block.number {
-            Some(n) => U256::try_from(n.as_u64()).unwrap(),
-            None => U256::ZERO,
-        };
-
         Self {
             accounts: HashMap::new(),
             contracts,
             logs: Vec::default(),
             block_hashes: HashMap::new(),
-            provider,
-            block_id: Some(block.number.unwrap().into()),
-            block,
+            backend,
+            read_cache: Arc::new(RwLock::new(ReadCache::default())),
+            // Track the original time and block for when we want
+            //  to run a sim using the same cache
             requests: RequestCache {
-                start_timestamp: timestamp,
-                start_block_number: block_number,
+                start_timestamp: block.timestamp,
+                start_block_number: block.number,
                 accounts: Vec::new(),
                 storage: Vec::new(),
             },
+            block,
+        }
+    }
+
+    /// Backend values are loaded from
+    pub fn backend(&self) -> &Arc<dyn ForkBackend> {
+        &self.backend
+    }
+
+    /// Load an account from the read cache or backend
+    fn backend_account(&self, address: Address) -> Result<Option<AccountInfo>, DatabaseError> {
+        if let Some(info) = self.read_cache.read().unwrap().accounts.get(&address) {
+            return Ok(info.clone());
         }
+        let info = self.backend.account(address)?;
+        self.read_cache
+            .write()
+            .unwrap()
+            .accounts
+            .insert(address, info.clone());
+        Ok(info)
+    }
+
+    /// Load a storage value from the read cache or backend
+    fn backend_storage(&self, address: Address, index: U256) -> Result<U256, DatabaseError> {
+        if let Some(value) = self
+            .read_cache
+            .read()
+            .unwrap()
+            .storage
+            .get(&(address, index))
+        {
+            return Ok(*value);
+        }
+        let value = self.backend.storage(address, index)?;
+        self.read_cache
+            .write()
+            .unwrap()
+            .storage
+            .insert((address, index), value);
+        Ok(value)
+    }
+
+    /// Load code from the read cache or backend
+    fn backend_code(&self, code_hash: B256) -> Result<Bytecode, DatabaseError> {
+        if let Some(code) = self.read_cache.read().unwrap().contracts.get(&code_hash) {
+            return Ok(code.clone());
+        }
+        let code = self.backend.code_by_hash(code_hash)?;
+        self.read_cache
+            .write()
+            .unwrap()
+            .contracts
+            .insert(code_hash, code.clone());
+        Ok(code)
+    }
+
+    /// Load a block hash from the read cache or backend
+    fn backend_block_hash(&self, number: U256) -> Result<B256, DatabaseError> {
+        if let Some(hash) = self.read_cache.read().unwrap().block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        let hash = self.backend.block_hash(number)?;
+        self.read_cache
+            .write()
+            .unwrap()
+            .block_hashes
+            .insert(number, hash);
+        Ok(hash)
+    }
+
+    /// Load accounts from the fork backend
+    ///
+    /// Requests the balance, nonce and code of any
+    /// accounts not already in the DB, in batches
+    /// (for backends that support batch requests).
+    /// Prefetching accounts that will be used by a
+    /// simulation before it starts is significantly
+    /// faster than loading each account on demand
+    /// during execution.
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses` - Addresses of accounts to load
+    ///
+    pub fn prefetch_accounts(&mut self, addresses: &[Address]) -> Result<(), DatabaseError> {
+        let missing: Vec<Address> = addresses
+            .iter()
+            .filter(|a| !self.accounts.contains_key(*a))
+            .unique()
+            .cloned()
+            .collect();
+
+        let infos = self.backend.accounts(&missing);
+
+        for (address, info) in missing.into_iter().zip(infos) {
+            let account = match info? {
+                Some(info) => {
+                    self.requests.accounts.push((address, info.clone()));
+                    DbAccount {
+                        info,
+                        ..Default::default()
+                    }
+                }
+                None => DbAccount::new_not_existing(),
+            };
+            self.accounts.insert(address, account);
+        }
+
+        Ok(())
+    }
+
+    /// Load storage values from the fork backend
+    ///
+    /// Requests any storage slots (and their accounts)
+    /// not already in the DB, in batches (for backends
+    /// that support batch requests).
+    ///
+    /// # Arguments
+    ///
+    /// * `slots` - Contract addresses and storage indices
+    ///   of the values to load
+    ///
+    pub fn prefetch_storage(&mut self, slots: &[(Address, U256)]) -> Result<(), DatabaseError> {
+        let addresses: Vec<Address> = slots.iter().map(|(a, _)| *a).collect();
+        self.prefetch_accounts(&addresses)?;
+
+        let missing: Vec<(Address, U256)> = slots
+            .iter()
+            .filter(|(address, index)| match self.accounts.get(address) {
+                Some(account) => {
+                    !account.storage.contains_key(index)
+                        && !matches!(
+                            account.account_state,
+                            AccountState::StorageCleared | AccountState::NotExisting
+                        )
+                }
+                None => false,
+            })
+            .unique()
+            .cloned()
+            .collect();
+
+        let values = self.backend.storage_slots(&missing);
+
+        for ((address, index), value) in missing.into_iter().zip(values) {
+            let value = value?;
+            self.requests.storage.push((address, index, value));
+            self.accounts
+                .get_mut(&address)
+                .unwrap()
+                .storage
+                .insert(index, value);
+        }
+
+        Ok(())
+    }
+
+    /// Load the full storage of contracts from the fork backend
+    ///
+    /// Loads all the storage values of the contracts
+    /// in bulk, where supported by the backend (e.g.
+    /// using `debug_storageRangeAt` requests). Storage
+    /// of these contracts is then treated as complete,
+    /// i.e. no further storage requests are made for
+    /// them. Returns an error if the backend cannot
+    /// load the full storage of a contract.
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses` - Addresses of the contracts
+    ///
+    pub fn load_full_storage(&mut self, addresses: &[Address]) -> Result<(), DatabaseError> {
+        self.prefetch_accounts(addresses)?;
+
+        for address in addresses.iter().unique() {
+            let storage = self
+                .backend
+                .full_storage(*address)?
+                .ok_or(DatabaseError::GetFullStorage(*address))?;
+            self.insert_full_storage(*address, storage);
+        }
+
+        Ok(())
+    }
+
+    /// Import the full storage of contracts from a dump
+    ///
+    /// Storage of contracts in the dump is treated
+    /// as complete, i.e. no further storage requests
+    /// are made for them. Imported values are added
+    /// to the request history.
+    ///
+    /// # Arguments
+    ///
+    /// * `dump` - Full storage of a set of contracts
+    ///
+    pub fn import_storage_dump(&mut self, dump: &StorageDump) -> Result<(), DatabaseError> {
+        let addresses: Vec<Address> = dump.contracts.keys().cloned().collect();
+        self.prefetch_accounts(&addresses)?;
+
+        for (address, storage) in dump.contracts.iter() {
+            self.insert_full_storage(*address, storage.clone());
+        }
+
+        Ok(())
+    }
+
+    fn insert_full_storage(&mut self, address: Address, storage: HashMap<U256, U256>) {
+        self.requests.storage.extend(
+            storage
+                .iter()
+                .map(|(index, value)| (address, *index, *value)),
+        );
+
+        let account = self.accounts.entry(address).or_default();
+        account.storage = storage;
+        account.account_state = AccountState::StorageCleared;
     }
 
     pub fn insert_contract(&mut self, account: &mut AccountInfo) {
@@ -111,7 +377,11 @@ impl ForkDb {
 
     pub fn insert_account_info(&mut self, address: Address, mut info: AccountInfo) {
         self.insert_contract(&mut info);
-        self.accounts.entry(address).or_default().info = info;
+        let account = self.accounts.entry(address).or_default();
+        account.info = info;
+        if matches!(account.account_state, AccountState::NotExisting) {
+            account.account_state = AccountState::StorageCleared;
+        }
     }
 
     pub fn load_account(&mut self, address: Address) -> Result<&mut DbAccount, DatabaseError> {
@@ -149,6 +419,15 @@ impl DB for ForkDb {
         self.insert_account_info(address, account_info)
     }
 
+    fn insert_account_storage(
+        &mut self,
+        address: Address,
+        slot: U256,
+        value: U256,
+    ) -> Result<(), DatabaseError> {
+        self.insert_account_storage(address, slot, value)
+    }
+
     fn accounts(&self) -> &HashMap<Address, DbAccount> {
         &self.accounts
     }
@@ -208,153 +487,267 @@ impl Database for ForkDb {
     type Error = DatabaseError;
 
     fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
-        let basic = self.accounts.entry(address);
-        let basic = match basic {
-            Entry::Occupied(entry) => entry.into_mut(),
-            Entry::Vacant(entry) => {
-                let info = basic_from_fork(&self.provider, address, self.block_id);
-                let account = match info {
-                    Ok(i) => {
-                        self.requests.accounts.push((address, i.clone()));
-                        DbAccount {
-                            info: i,
-                            ..Default::default()
-                        }
-                    }
-                    Err(_) => DbAccount::new_not_existing(),
-                };
-                entry.insert(account)
+        if let Some(account) = self.accounts.get(&address) {
+            return Ok(account.info());
+        }
+        let account = match self.backend_account(address)? {
+            Some(i) => {
+                self.requests.accounts.push((address, i.clone()));
+                DbAccount {
+                    info: i,
+                    ..Default::default()
+                }
             }
+            None => DbAccount::new_not_existing(),
         };
-        Ok(basic.info())
+        let info = account.info();
+        self.accounts.insert(address, account);
+        Ok(info)
     }
 
     fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
-        match self.contracts.entry(code_hash) {
-            Entry::Occupied(entry) => Ok(entry.get().clone()),
-            Entry::Vacant(_) => Err(DatabaseError::MissingCode(code_hash)),
+        if let Some(code) = self.contracts.get(&code_hash) {
+            return Ok(code.clone());
         }
+        let code = self.backend_code(code_hash)?;
+        self.contracts.insert(code_hash, code.clone());
+        Ok(code)
     }
 
     fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
-        match self.accounts.entry(address) {
-            Entry::Occupied(mut acc_entry) => {
-                let acc_entry = acc_entry.get_mut();
-                match acc_entry.storage.entry(index) {
-                    Entry::Occupied(entry) => Ok(*entry.get()),
-                    Entry::Vacant(entry) => {
-                        if matches!(
-                            acc_entry.account_state,
-                            AccountState::StorageCleared | AccountState::NotExisting
-                        ) {
-                            Ok(U256::ZERO)
-                        } else {
-                            let slot =
-                                storage_from_fork(&self.provider, address, index, self.block_id);
-                            match slot {
-                                Ok(s) => {
-                                    self.requests.storage.push((address, index, s));
-                                    entry.insert(s);
-                                    Ok(s)
-                                }
-                                Err(_) => Err(DatabaseError::GetStorage(address, index)),
-                            }
-                        }
-                    }
-                }
-            }
-            Entry::Vacant(_) => Err(DatabaseError::GetAccount(address)),
+        let account = self
+            .accounts
+            .get(&address)
+            .ok_or(DatabaseError::GetAccount(address))?;
+        if let Some(value) = account.storage.get(&index) {
+            return Ok(*value);
         }
+        if matches!(
+            account.account_state,
+            AccountState::StorageCleared | AccountState::NotExisting
+        ) {
+            return Ok(U256::ZERO);
+        }
+        let slot = self.backend_storage(address, index)?;
+        self.requests.storage.push((address, index, slot));
+        self.accounts
+            .get_mut(&address)
+            .unwrap()
+            .storage
+            .insert(index, slot);
+        Ok(slot)
     }
 
     fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
-        match self.block_hashes.entry(number) {
-            Entry::Occupied(entry) => Ok(*entry.get()),
-            Entry::Vacant(entry) => {
-                let hash = block_hash_from_fork(&self.provider, number);
-                match hash {
-                    Ok(h) => {
-                        entry.insert(h);
-                        Ok(h)
-                    }
-                    Err(_) => Err(DatabaseError::GetBlockHash(number)),
-                }
-            }
+        if let Some(hash) = self.block_hashes.get(&number) {
+            return Ok(*hash);
         }
+        let hash = self.backend_block_hash(number)?;
+        self.block_hashes.insert(number, hash);
+        Ok(hash)
     }
 }
 
-fn basic_from_fork(
-    provider: &Provider<RuntimeClient>,
-    address: Address,
-    block_id: Option<BlockId>,
-) -> Result<AccountInfo, ProviderError> {
-    let add = NameOrAddress::Address(address.to_ethers());
+/// Read-only access to the DB
+///
+/// Values missing from the local DB are requested from the
+/// fork backend and kept in the shared read cache, but are
+/// not added to the local DB or recorded in the request
+/// history.
+impl DatabaseRef for ForkDb {
+    type Error = DatabaseError;
 
-    let rt = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()
-        .unwrap();
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(account) => Ok(account.info()),
+            None => self.backend_account(address),
+        }
+    }
 
-    let balance = rt.block_on(provider.get_balance(add.clone(), block_id))?;
-    let nonce = rt.block_on(provider.get_transaction_count(add.clone(), block_id))?;
-    let code = rt.block_on(provider.get_code(add, block_id))?;
-    let code = Bytes::from(code.0);
-
-    let (code, code_hash) = if !code.is_empty() {
-        (code.clone(), keccak256(&code))
-    } else {
-        (Bytes::default(), KECCAK_EMPTY)
-    };
-
-    Ok(AccountInfo {
-        balance: balance.to_alloy(),
-        nonce: nonce.as_u64(),
-        code_hash,
-        code: Some(Bytecode::new_raw(code)),
-    })
-}
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.backend_code(code_hash),
+        }
+    }
 
-fn storage_from_fork(
-    provider: &Provider<RuntimeClient>,
-    address: Address,
-    index: U256,
-    block_id: Option<BlockId>,
-) -> Result<U256, ProviderError> {
-    let idx_req = B256::from(index);
-
-    let rt = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()
-        .unwrap();
-    let storage = rt.block_on(provider.get_storage_at(
-        NameOrAddress::Address(address.to_ethers()),
-        idx_req.to_ethers(),
-        block_id,
-    ))?;
-    Ok(storage.into_uint().to_alloy())
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(account) = self.accounts.get(&address) {
+            if let Some(value) = account.storage.get(&index) {
+                return Ok(*value);
+            }
+            if matches!(
+                account.account_state,
+                AccountState::StorageCleared | AccountState::NotExisting
+            ) {
+                return Ok(U256::ZERO);
+            }
+        }
+        self.backend_storage(address, index)
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        match self.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => self.backend_block_hash(number),
+        }
+    }
 }
 
-fn block_hash_from_fork(
-    provider: &Provider<RuntimeClient>,
-    number: U256,
-) -> Result<B256, ProviderError> {
-    let n: u64 = number.try_into().unwrap();
-    let block_id = BlockId::from(n);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockBackend;
+
+    fn backend() -> MockBackend {
+        let mut backend = MockBackend::new(U256::from(100), U256::from(1_700_000_000));
+        backend.insert_account(
+            Address::repeat_byte(1),
+            AccountInfo::from_balance(U256::from(1000)),
+        );
+        backend.insert_storage(Address::repeat_byte(1), U256::from(1), U256::from(2));
+        backend.insert_block_hash(U256::from(99), B256::repeat_byte(9));
+        backend
+    }
 
-    let rt = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()
+    #[test]
+    fn test_lazy_loading() {
+        let backend = Arc::new(backend());
+        let mut db = ForkDb::from_backend(backend.clone());
+
+        assert_eq!(db.block.number, U256::from(100));
+        assert_eq!(db.requests.start_timestamp, U256::from(1_700_000_000));
+
+        let info = db.basic(Address::repeat_byte(1)).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(1000));
+        let value = db.storage(Address::repeat_byte(1), U256::from(1)).unwrap();
+        assert_eq!(value, U256::from(2));
+        let hash = db.block_hash(U256::from(99)).unwrap();
+        assert_eq!(hash, B256::repeat_byte(9));
+        assert_eq!(backend.n_requests(), 3);
+
+        // Loaded values are served from the local DB
+        db.basic(Address::repeat_byte(1)).unwrap();
+        db.storage(Address::repeat_byte(1), U256::from(1)).unwrap();
+        db.block_hash(U256::from(99)).unwrap();
+        assert_eq!(backend.n_requests(), 3);
+
+        assert_eq!(db.requests.accounts.len(), 1);
+        assert_eq!(
+            db.requests.storage,
+            vec![(Address::repeat_byte(1), U256::from(1), U256::from(2))]
+        );
+    }
+
+    #[test]
+    fn test_missing_account() {
+        let backend = Arc::new(backend());
+        let mut db = ForkDb::from_backend(backend.clone());
+
+        assert!(db.basic(Address::repeat_byte(2)).unwrap().is_none());
+        // Storage of accounts that do not exist is not requested
+        let value = db.storage(Address::repeat_byte(2), U256::from(1)).unwrap();
+        assert_eq!(value, U256::ZERO);
+        assert_eq!(backend.n_requests(), 1);
+        assert!(db.requests.accounts.is_empty());
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let backend = Arc::new(backend());
+        let mut db = ForkDb::from_backend(backend.clone());
+
+        db.prefetch_storage(&[
+            (Address::repeat_byte(1), U256::from(1)),
+            (Address::repeat_byte(1), U256::from(1)),
+            (Address::repeat_byte(1), U256::from(2)),
+            (Address::repeat_byte(2), U256::from(1)),
+        ])
         .unwrap();
 
-    let block = rt.block_on(provider.get_block(block_id));
+        // 2 accounts and 2 storage slots of the existing account
+        assert_eq!(backend.n_requests(), 4);
+        assert_eq!(db.requests.storage.len(), 2);
+
+        db.basic(Address::repeat_byte(1)).unwrap();
+        db.storage(Address::repeat_byte(1), U256::from(2)).unwrap();
+        db.storage(Address::repeat_byte(2), U256::from(1)).unwrap();
+        assert_eq!(backend.n_requests(), 4);
+    }
+
+    #[test]
+    fn test_full_storage() {
+        let backend = Arc::new(backend());
+        let mut db = ForkDb::from_backend(backend.clone());
+
+        db.load_full_storage(&[Address::repeat_byte(1)]).unwrap();
+        // Account and full storage requests
+        assert_eq!(backend.n_requests(), 2);
+        assert_eq!(db.requests.storage.len(), 1);
+
+        // Storage is complete, so no further requests are made
+        let value = db.storage(Address::repeat_byte(1), U256::from(1)).unwrap();
+        assert_eq!(value, U256::from(2));
+        let value = db.storage(Address::repeat_byte(1), U256::from(2)).unwrap();
+        assert_eq!(value, U256::ZERO);
+        assert_eq!(backend.n_requests(), 2);
+    }
+
+    #[test]
+    fn test_read_cache() {
+        let backend = Arc::new(backend());
+        let mut db = ForkDb::from_backend(backend.clone());
+        let clone = db.clone();
+
+        let read = |db: &ForkDb| {
+            let info = db.basic_ref(Address::repeat_byte(1)).unwrap().unwrap();
+            assert_eq!(info.balance, U256::from(1000));
+            let value = db.storage_ref(Address::repeat_byte(1), U256::from(1));
+            assert_eq!(value.unwrap(), U256::from(2));
+        };
+
+        read(&db);
+        assert_eq!(backend.n_requests(), 2);
+        // Values read by other clones and threads are not requested again
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| read(&clone));
+            }
+        });
+        db.basic(Address::repeat_byte(1)).unwrap();
+        db.storage(Address::repeat_byte(1), U256::from(1)).unwrap();
+        assert_eq!(backend.n_requests(), 2);
+        // Values are added to the request history when
+        // loaded into the local DB
+        assert_eq!(db.requests.accounts.len(), 1);
+        assert_eq!(db.requests.storage.len(), 1);
+        assert!(clone.accounts.is_empty());
+    }
 
-    match block {
-        Ok(Some(block)) => Ok(block
-            .hash
-            .expect("empty block hash on mined block, this should never happen")
-            .to_alloy()),
-        Ok(None) => Ok(KECCAK_EMPTY),
-        Err(e) => Err(e),
+    #[test]
+    fn test_import_storage_dump() {
+        let backend = Arc::new(backend());
+        let mut db = ForkDb::from_backend(backend.clone());
+
+        let mut dump = StorageDump::default();
+        dump.contracts.insert(
+            Address::repeat_byte(1),
+            HashMap::from_iter([
+                (U256::from(1), U256::from(3)),
+                (U256::from(2), U256::from(4)),
+            ]),
+        );
+        db.import_storage_dump(&dump).unwrap();
+
+        assert_eq!(backend.n_requests(), 1);
+        assert_eq!(db.requests.storage.len(), 2);
+
+        let info = db.basic(Address::repeat_byte(1)).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(1000));
+        let value = db.storage(Address::repeat_byte(1), U256::from(1)).unwrap();
+        assert_eq!(value, U256::from(3));
+        let value = db.storage(Address::repeat_byte(1), U256::from(5)).unwrap();
+        assert_eq!(value, U256::ZERO);
+        assert_eq!(backend.n_requests(), 1);
     }
 }
@@ -0,0 +1,231 @@
+//! Initial chain state loaded from other tools
+//!
+//! Loads the accounts (balance, nonce, code and
+//! storage) of a geth genesis file `alloc` field,
+//! or of a state dump generated by anvil (using
+//! `--dump-state`) or hardhat, so simulations
+//! can start from states prepared by other tools
+//! (e.g. with pre-deployed protocols).
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::{Address, U256};
+//! use verbs_rs::{GenesisState, LocalDB};
+//!
+//! let genesis = GenesisState::from_json(r#"{
+//!     "timestamp": "0x0",
+//!     "alloc": {
+//!         "0101010101010101010101010101010101010101": {
+//!             "balance": "1000000000000000000",
+//!             "storage": {"0x01": "0x02"}
+//!         }
+//!     }
+//! }"#).unwrap();
+//!
+//! let db = LocalDB::from_genesis(&genesis);
+//! let account = &db.accounts[&Address::repeat_byte(1)];
+//!
+//! assert_eq!(account.info.balance, U256::from(10u128.pow(18)));
+//! assert_eq!(account.storage[&U256::from(1)], U256::from(2));
+//! ```
+
+use alloy_primitives::{Address, Bytes, U256};
+use revm::primitives::HashMap;
+use serde::{de, Deserialize, Deserializer};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Error raised reading a genesis file or state dump
+#[derive(Debug, thiserror::Error)]
+pub enum GenesisError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Account in a genesis file or state dump
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct GenesisAccount {
+    /// Account balance
+    #[serde(default)]
+    pub balance: U256,
+    /// Account nonce
+    #[serde(default, deserialize_with = "deserialize_nonce")]
+    pub nonce: u64,
+    /// Contract code
+    #[serde(default)]
+    pub code: Option<Bytes>,
+    /// Contract storage
+    #[serde(default)]
+    pub storage: HashMap<U256, U256>,
+}
+
+/// Accounts and block of an initial chain state
+#[derive(Clone, Debug, Default)]
+pub struct GenesisState {
+    /// Block number, if included in the file
+    pub number: Option<U256>,
+    /// Block timestamp, if included in the file
+    pub timestamp: Option<U256>,
+    /// Accounts
+    pub accounts: HashMap<Address, GenesisAccount>,
+}
+
+/// Block fields of a genesis file or anvil dump
+#[derive(Deserialize, Default)]
+struct BlockFields {
+    #[serde(default)]
+    number: Option<U256>,
+    #[serde(default)]
+    timestamp: Option<U256>,
+}
+
+/// Geth genesis file or anvil/hardhat state dump
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StateFile {
+    Genesis {
+        alloc: HashMap<Address, GenesisAccount>,
+        #[serde(flatten)]
+        block: BlockFields,
+    },
+    Dump {
+        accounts: HashMap<Address, GenesisAccount>,
+        #[serde(default)]
+        block: Option<BlockFields>,
+        #[serde(default)]
+        best_block_number: Option<U256>,
+    },
+    Accounts(HashMap<Address, GenesisAccount>),
+}
+
+impl GenesisState {
+    /// Parse a genesis file or state dump from JSON
+    ///
+    /// Accepts a geth genesis file (with an `alloc`
+    /// field), an anvil state dump (with an `accounts`
+    /// field), or a JSON object mapping addresses
+    /// to accounts (e.g. a hardhat state dump).
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - JSON genesis file or state dump
+    ///
+    pub fn from_json(json: &str) -> Result<Self, GenesisError> {
+        Ok(serde_json::from_str::<StateFile>(json)?.into())
+    }
+
+    /// Read a genesis file or state dump
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader of a JSON genesis file or state dump
+    ///
+    pub fn read<R: Read>(reader: R) -> Result<Self, GenesisError> {
+        Ok(serde_json::from_reader::<_, StateFile>(reader)?.into())
+    }
+
+    /// Load a genesis file or state dump
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the JSON genesis file or state dump
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GenesisError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+impl From<StateFile> for GenesisState {
+    fn from(value: StateFile) -> Self {
+        match value {
+            StateFile::Genesis { alloc, block } => GenesisState {
+                number: block.number,
+                timestamp: block.timestamp,
+                accounts: alloc,
+            },
+            StateFile::Dump {
+                accounts,
+                block,
+                best_block_number,
+            } => {
+                let block = block.unwrap_or_default();
+                GenesisState {
+                    number: block.number.or(best_block_number),
+                    timestamp: block.timestamp,
+                    accounts,
+                }
+            }
+            StateFile::Accounts(accounts) => GenesisState {
+                accounts,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Nonces are written as either numbers or hex strings
+fn deserialize_nonce<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let nonce = U256::deserialize(deserializer)?;
+    nonce.try_into().map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anvil_dump() {
+        let state = GenesisState::from_json(
+            r#"{
+                "block": {"number": "0x5", "timestamp": "0x65", "gas_limit": "0x1c9c380"},
+                "accounts": {
+                    "0x0101010101010101010101010101010101010101": {
+                        "nonce": 1,
+                        "balance": "0x3e8",
+                        "code": "0x6000",
+                        "storage": {
+                            "0x0000000000000000000000000000000000000000000000000000000000000001": "0x02"
+                        }
+                    },
+                    "0x0202020202020202020202020202020202020202": {
+                        "nonce": "0x2",
+                        "balance": "0x0",
+                        "code": "0x",
+                        "storage": {}
+                    }
+                },
+                "best_block_number": "0x5"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(state.number, Some(U256::from(5)));
+        assert_eq!(state.timestamp, Some(U256::from(101)));
+
+        let account = &state.accounts[&Address::repeat_byte(1)];
+        assert_eq!(account.nonce, 1);
+        assert_eq!(account.balance, U256::from(1000));
+        assert_eq!(account.code.as_ref().unwrap().as_ref(), &[0x60, 0x00]);
+        assert_eq!(account.storage[&U256::from(1)], U256::from(2));
+
+        assert_eq!(state.accounts[&Address::repeat_byte(2)].nonce, 2);
+    }
+
+    #[test]
+    fn test_account_map() {
+        let state = GenesisState::from_json(
+            r#"{"0x0101010101010101010101010101010101010101": {"balance": "0x1"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(state.number, None);
+        assert_eq!(
+            state.accounts[&Address::repeat_byte(1)].balance,
+            U256::from(1)
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Historical blocks and transactions
+//!
+//! Transactions of mined blocks, deserialized from
+//! JSON-RPC block objects (i.e. the result of
+//! `eth_getBlockByNumber` with full transactions).
+//! Used to replay historical order-flow in forked
+//! simulations (see [crate::agent::HistoricalFlow]).
+//!
+
+use crate::contract::Transaction;
+use alloy_primitives::{Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+
+/// Transaction of a mined block
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoricalTransaction {
+    /// Transaction hash
+    #[serde(default)]
+    pub hash: B256,
+    /// Transaction sender
+    pub from: Address,
+    /// Recipient, `None` for contract creation
+    #[serde(default)]
+    pub to: Option<Address>,
+    /// Call data
+    #[serde(default)]
+    pub input: Bytes,
+    /// Value attached to the transaction
+    #[serde(default)]
+    pub value: U256,
+    /// Sender nonce
+    #[serde(default)]
+    pub nonce: U256,
+    /// Gas price (effective gas price of EIP-1559 transactions)
+    #[serde(default)]
+    pub gas_price: Option<U256>,
+    /// Priority fee of EIP-1559 transactions
+    #[serde(default)]
+    pub max_priority_fee_per_gas: Option<U256>,
+}
+
+impl HistoricalTransaction {
+    /// Convert to a simulation [Transaction]
+    ///
+    /// The transaction is sent from the original sender,
+    /// and is unchecked (i.e. reverts do not stop the
+    /// simulation). Returns `None` for contract creation
+    /// transactions, which cannot be submitted as
+    /// simulation transactions.
+    pub fn to_transaction(&self) -> Option<Transaction> {
+        let to = self.to?;
+        let mut function_selector = [0u8; 4];
+        if self.input.len() >= 4 {
+            function_selector.copy_from_slice(&self.input[..4]);
+        }
+        Some(Transaction {
+            function_selector,
+            callee: self.from,
+            transact_to: to,
+            args: self.input.to_vec(),
+            gas_priority_fee: self.max_priority_fee_per_gas.or(self.gas_price),
+            nonce: Some(self.nonce.saturating_to()),
+            value: self.value,
+            checked: false,
+        })
+    }
+}
+
+/// Mined block and its transactions
+///
+/// Block timestamps are not included, the block
+/// environment of a simulation is set by the
+/// simulation itself (see [crate::env::Env::increment_time]).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoricalBlock {
+    /// Block number
+    pub number: U256,
+    /// Transactions in the order they were included
+    #[serde(default)]
+    pub transactions: Vec<HistoricalTransaction>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_rpc_block() {
+        let block: HistoricalBlock = serde_json::from_str(
+            r#"{
+                "number": "0x65",
+                "timestamp": "0x6553f100",
+                "hash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                "transactions": [
+                    {
+                        "hash": "0x0000000000000000000000000000000000000000000000000000000000000002",
+                        "from": "0x0101010101010101010101010101010101010101",
+                        "to": "0x0202020202020202020202020202020202020202",
+                        "input": "0xa9059cbb0000",
+                        "value": "0x0",
+                        "nonce": "0x7",
+                        "gas": "0x5208",
+                        "gasPrice": "0x3b9aca00",
+                        "maxPriorityFeePerGas": "0x5f5e100",
+                        "type": "0x2"
+                    },
+                    {
+                        "from": "0x0101010101010101010101010101010101010101",
+                        "to": null,
+                        "input": "0x6080",
+                        "nonce": "0x8"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(block.number, U256::from(101));
+        assert_eq!(block.transactions.len(), 2);
+
+        let tx = block.transactions[0].to_transaction().unwrap();
+        assert_eq!(tx.callee, Address::repeat_byte(1));
+        assert_eq!(tx.transact_to, Address::repeat_byte(2));
+        assert_eq!(tx.function_selector, [0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(tx.nonce, Some(7));
+        assert_eq!(tx.gas_priority_fee, Some(U256::from(100_000_000)));
+        assert!(!tx.checked);
+
+        assert!(block.transactions[1].to_transaction().is_none());
+    }
+}
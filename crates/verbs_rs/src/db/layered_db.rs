@@ -0,0 +1,510 @@
+use super::error::DatabaseError;
+use super::local_db::LocalDB;
+use super::traits::DB;
+use alloy_primitives::Address;
+use revm::db::in_memory_db::DbAccount;
+use revm::db::{AccountState, DatabaseCommit};
+use revm::primitives::{Account, AccountInfo, Bytecode, HashMap, Log, B256, KECCAK_EMPTY, U256};
+use revm::{Database, DatabaseRef};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Copy-on-write DB layered over a shared base DB
+///
+/// In memory database that stores changes to
+/// the state in a write layer on top of an
+/// immutable base DB, shared using an [Arc].
+/// Values not changed in the write layer are
+/// read from the base DB, so a layered DB can
+/// be cloned, e.g. to run multiple simulation
+/// samples from the same (large) initial state,
+/// without copying the base state.
+///
+/// The data export methods of the [DB] trait
+/// (e.g. [DB::accounts]) only return values
+/// stored in the write layer, the full state is
+/// exported by [DB::export_state] (see
+/// [LayeredDB::merged]).
+///
+/// Values read from the base DB are not copied
+/// into the write layer. Bases that load values
+/// on demand (i.e. a [super::ForkDb]) cache the
+/// values they load, so values are only requested
+/// once for all the layers sharing a base.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use alloy_primitives::{Address, U256};
+/// use revm::primitives::AccountInfo;
+/// use revm::DatabaseRef;
+/// use verbs_rs::{LayeredDB, LocalDB, DB};
+///
+/// let mut base = LocalDB::new();
+/// base.insert_account_info(
+///     Address::repeat_byte(1), AccountInfo::from_balance(U256::from(100))
+/// );
+/// let base = Arc::new(base);
+///
+/// let mut db = LayeredDB::new(base.clone());
+/// db.insert_account_info(
+///     Address::repeat_byte(1), AccountInfo::from_balance(U256::from(50))
+/// );
+///
+/// let balance = |db: &dyn DatabaseRef<Error = _>| {
+///     db.basic_ref(Address::repeat_byte(1)).unwrap().unwrap().balance
+/// };
+/// assert_eq!(balance(&db), U256::from(50));
+/// // The base DB is unchanged
+/// assert_eq!(balance(&*base), U256::from(100));
+/// ```
+#[derive(Debug)]
+pub struct LayeredDB<B: DB + Send = LocalDB> {
+    base: Arc<B>,
+    pub accounts: HashMap<Address, DbAccount>,
+    pub contracts: HashMap<B256, Bytecode>,
+    pub logs: Vec<Log>,
+    pub block_hashes: HashMap<U256, B256>,
+    /// Accounts with storage cleared (or created) in the
+    /// write layer, i.e. storage is not read from the base
+    cleared: HashSet<Address>,
+}
+
+impl<B: DB + Send> Clone for LayeredDB<B> {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            accounts: self.accounts.clone(),
+            contracts: self.contracts.clone(),
+            logs: self.logs.clone(),
+            block_hashes: self.block_hashes.clone(),
+            cleared: self.cleared.clone(),
+        }
+    }
+}
+
+impl<B: DB + Send> LayeredDB<B> {
+    /// Create an empty write layer over a base DB
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - Shared base DB
+    ///
+    pub fn new(base: Arc<B>) -> Self {
+        Self {
+            base,
+            accounts: HashMap::new(),
+            contracts: HashMap::new(),
+            logs: Vec::default(),
+            block_hashes: HashMap::new(),
+            cleared: HashSet::new(),
+        }
+    }
+
+    /// Base DB the write layer is applied to
+    pub fn base(&self) -> &Arc<B> {
+        &self.base
+    }
+
+    /// Merge the write layer and base DB
+    ///
+    /// Creates a [LocalDB] containing the values
+    /// stored in the base DB, updated with the
+    /// values in the write layer.
+    pub fn merged(&self) -> LocalDB {
+        let mut db = self.base.export_state().into_owned();
+
+        for (address, account) in self.accounts.iter() {
+            let merged = db.accounts.entry(*address).or_default();
+            if self.cleared.contains(address) {
+                merged.storage.clear();
+            }
+            merged.info = account.info.clone();
+            merged.account_state = account.account_state.clone();
+            merged.storage.extend(account.storage.iter());
+        }
+        db.contracts.extend(self.contracts.clone());
+        db.logs.extend(self.logs.iter().cloned());
+        db.block_hashes.extend(self.block_hashes.iter());
+
+        db
+    }
+
+    pub fn insert_contract(&mut self, account: &mut AccountInfo) {
+        if let Some(code) = &account.code {
+            if !code.is_empty() {
+                if account.code_hash == KECCAK_EMPTY {
+                    account.code_hash = code.hash_slow();
+                }
+                self.contracts
+                    .entry(account.code_hash)
+                    .or_insert_with(|| code.clone());
+            }
+        }
+        if account.code_hash == B256::ZERO {
+            account.code_hash = KECCAK_EMPTY;
+        }
+    }
+
+    pub fn insert_account_info(&mut self, address: Address, mut info: AccountInfo) {
+        self.insert_contract(&mut info);
+        let account = self.layer_account(address);
+        account.info = info;
+        if matches!(account.account_state, AccountState::NotExisting) {
+            account.account_state = AccountState::StorageCleared;
+        }
+    }
+
+    pub fn insert_account_storage(
+        &mut self,
+        address: Address,
+        slot: U256,
+        value: U256,
+    ) -> Result<(), DatabaseError> {
+        let account = self.layer_account(address);
+        match account.account_state {
+            AccountState::NotExisting => Err(DatabaseError::GetAccount(address)),
+            _ => {
+                account.storage.insert(slot, value);
+                Ok(())
+            }
+        }
+    }
+
+    /// Get an account from the write layer, copying
+    /// the account info (but not storage) from the
+    /// base DB if not already in the layer
+    ///
+    /// Storage of accounts that do not exist in the
+    /// base DB is marked as cleared in the layer,
+    /// otherwise storage missing from the layer is
+    /// read from the base DB (whatever the state of
+    /// the base account).
+    fn layer_account(&mut self, address: Address) -> &mut DbAccount {
+        let base = &self.base;
+        let cleared = &mut self.cleared;
+        self.accounts.entry(address).or_insert_with(|| {
+            let account = match base.accounts().get(&address) {
+                Some(account) => DbAccount {
+                    info: account.info.clone(),
+                    account_state: account.account_state.clone(),
+                    ..Default::default()
+                },
+                None => match base.basic_ref(address) {
+                    Ok(Some(info)) => DbAccount {
+                        info,
+                        ..Default::default()
+                    },
+                    _ => DbAccount::new_not_existing(),
+                },
+            };
+            if matches!(account.account_state, AccountState::NotExisting) {
+                cleared.insert(address);
+            }
+            account
+        })
+    }
+}
+
+impl<B: DB + Send> DB for LayeredDB<B> {
+    fn insert_account_info(&mut self, address: Address, account_info: AccountInfo) {
+        self.insert_account_info(address, account_info)
+    }
+
+    fn insert_account_storage(
+        &mut self,
+        address: Address,
+        slot: U256,
+        value: U256,
+    ) -> Result<(), DatabaseError> {
+        self.insert_account_storage(address, slot, value)
+    }
+
+    fn accounts(&self) -> &HashMap<Address, DbAccount> {
+        &self.accounts
+    }
+
+    fn contracts(&self) -> &HashMap<B256, Bytecode> {
+        &self.contracts
+    }
+
+    fn logs(&self) -> &Vec<Log> {
+        &self.logs
+    }
+
+    fn block_hashes(&self) -> &HashMap<U256, B256> {
+        &self.block_hashes
+    }
+
+    fn export_state(&self) -> Cow<'_, LocalDB> {
+        Cow::Owned(self.merged())
+    }
+}
+
+impl<B: DB + Send> DatabaseCommit for LayeredDB<B> {
+    fn commit(&mut self, changes: HashMap<Address, Account>) {
+        for (address, mut account) in changes {
+            if !account.is_touched() {
+                continue;
+            }
+            if account.is_selfdestructed() {
+                self.cleared.insert(address);
+                let db_account = self.accounts.entry(address).or_default();
+                db_account.storage.clear();
+                db_account.account_state = AccountState::NotExisting;
+                db_account.info = AccountInfo::default();
+                continue;
+            }
+            let is_newly_created = account.is_created();
+            self.insert_contract(&mut account.info);
+            if is_newly_created {
+                self.cleared.insert(address);
+            }
+
+            let db_account = self.layer_account(address);
+            db_account.info = account.info;
+
+            db_account.account_state = if is_newly_created {
+                db_account.storage.clear();
+                AccountState::StorageCleared
+            } else if db_account.account_state.is_storage_cleared() {
+                // Preserve old account state if it already exists
+                AccountState::StorageCleared
+            } else {
+                AccountState::Touched
+            };
+            db_account.storage.extend(
+                account
+                    .storage
+                    .into_iter()
+                    .map(|(key, value)| (key, value.present_value())),
+            );
+        }
+    }
+}
+
+impl<B: DB + Send> Database for LayeredDB<B> {
+    type Error = DatabaseError;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.basic_ref(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.code_by_hash_ref(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.storage_ref(address, index)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.block_hash_ref(number)
+    }
+}
+
+/// Read-only access to the DB
+///
+/// Values missing from the write layer are read
+/// from the base DB. Values read from the base
+/// DB are not copied into the write layer.
+impl<B: DB + Send> DatabaseRef for LayeredDB<B> {
+    type Error = DatabaseError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(account) => Ok(account.info()),
+            None => self.base.basic_ref(address),
+        }
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.base.code_by_hash_ref(code_hash),
+        }
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(account) => match account.storage.get(&index) {
+                Some(value) => Ok(*value),
+                None if self.cleared.contains(&address) => Ok(U256::ZERO),
+                None => self.base.storage_ref(address, index),
+            },
+            None => self.base.storage_ref(address, index),
+        }
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        match self.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => self.base.block_hash_ref(number),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_diff::StateDiff;
+
+    fn base() -> Arc<LocalDB> {
+        let mut base = LocalDB::new();
+        base.insert_account_info(
+            Address::repeat_byte(1),
+            AccountInfo::from_balance(U256::from(1)),
+        );
+        base.insert_account_storage(Address::repeat_byte(1), U256::from(1), U256::from(2))
+            .unwrap();
+        base.insert_account_storage(Address::repeat_byte(1), U256::from(2), U256::from(3))
+            .unwrap();
+        Arc::new(base)
+    }
+
+    #[test]
+    fn test_write_layer() {
+        let base = base();
+        let mut db = LayeredDB::new(base.clone());
+
+        db.insert_account_storage(Address::repeat_byte(1), U256::from(1), U256::from(4))
+            .unwrap();
+
+        let info = db.basic(Address::repeat_byte(1)).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(1));
+        assert_eq!(
+            db.storage(Address::repeat_byte(1), U256::from(1)).unwrap(),
+            U256::from(4)
+        );
+        assert_eq!(
+            db.storage(Address::repeat_byte(1), U256::from(2)).unwrap(),
+            U256::from(3)
+        );
+        // Only the changed slot is copied into the layer
+        assert_eq!(db.accounts[&Address::repeat_byte(1)].storage.len(), 1);
+
+        let branch = db.clone();
+        db.insert_account_storage(Address::repeat_byte(1), U256::from(2), U256::from(5))
+            .unwrap();
+
+        assert_eq!(
+            branch
+                .storage_ref(Address::repeat_byte(1), U256::from(2))
+                .unwrap(),
+            U256::from(3)
+        );
+        assert_eq!(
+            base.storage_ref(Address::repeat_byte(1), U256::from(1))
+                .unwrap(),
+            U256::from(2)
+        );
+    }
+
+    #[test]
+    fn test_deployed_base_contract() {
+        use crate::env::{Env, RandomValidator};
+
+        // Init code storing 3 in slot 2, with runtime code
+        // storing the call data in slot 1
+        let runtime = [0x60, 0x00, 0x35, 0x60, 0x01, 0x55, 0x00];
+        let mut init_code = vec![
+            0x60, 0x03, 0x60, 0x02, 0x55, 0x60, 0x07, 0x60, 0x11, 0x60, 0x00, 0x39, 0x60, 0x07,
+            0x60, 0x00, 0xf3,
+        ];
+        init_code.extend(runtime);
+
+        let mut env =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
+        let contract = env.deploy_contract(Address::ZERO, "test", init_code);
+        let mut env = env.into_layered();
+
+        env.direct_execute_raw(
+            Address::ZERO,
+            contract,
+            U256::from(9).to_be_bytes_vec(),
+            U256::ZERO,
+        )
+        .unwrap();
+
+        let view = env.view();
+        let db = view.db();
+        assert_eq!(
+            db.storage_ref(contract, U256::from(1)).unwrap(),
+            U256::from(9)
+        );
+        // Untouched slots are read from the base DB
+        assert_eq!(
+            db.storage_ref(contract, U256::from(2)).unwrap(),
+            U256::from(3)
+        );
+        assert_eq!(
+            db.merged().storage_ref(contract, U256::from(2)).unwrap(),
+            U256::from(3)
+        );
+    }
+
+    #[test]
+    fn test_fork_base() {
+        use crate::{ForkDb, MockBackend};
+
+        let mut backend = MockBackend::new(U256::from(100), U256::from(1_700_000_000));
+        backend.insert_account(
+            Address::repeat_byte(1),
+            AccountInfo::from_balance(U256::from(1)),
+        );
+        backend.insert_storage(Address::repeat_byte(1), U256::from(1), U256::from(2));
+        let backend = Arc::new(backend);
+        let base = Arc::new(ForkDb::from_backend(backend.clone()));
+
+        // Values loaded from the fork are shared by all the layers
+        for _ in 0..3 {
+            let mut db = LayeredDB::new(base.clone());
+            assert_eq!(
+                db.storage(Address::repeat_byte(1), U256::from(1)).unwrap(),
+                U256::from(2)
+            );
+            db.insert_account_storage(Address::repeat_byte(1), U256::from(1), U256::from(3))
+                .unwrap();
+        }
+        assert_eq!(backend.n_requests(), 2);
+    }
+
+    #[test]
+    fn test_export_state() {
+        let mut db = LayeredDB::new(base());
+        db.insert_account_storage(Address::repeat_byte(1), U256::from(1), U256::from(4))
+            .unwrap();
+
+        let state = db.export_state();
+        let account = &state.accounts[&Address::repeat_byte(1)];
+        assert_eq!(account.info.balance, U256::from(1));
+        assert_eq!(account.storage[&U256::from(1)], U256::from(4));
+        assert_eq!(account.storage[&U256::from(2)], U256::from(3));
+    }
+
+    #[test]
+    fn test_merged() {
+        let base = base();
+        let mut db = LayeredDB::new(base.clone());
+
+        db.insert_account_storage(Address::repeat_byte(1), U256::from(1), U256::from(4))
+            .unwrap();
+        db.insert_account_info(
+            Address::repeat_byte(2),
+            AccountInfo::from_balance(U256::from(5)),
+        );
+
+        let merged = db.merged();
+        let diff = StateDiff::new(&merged, &db).unwrap();
+        assert!(diff.is_empty());
+
+        let diff = StateDiff::new(&*base, &merged).unwrap();
+        assert_eq!(diff.accounts.len(), 2);
+        assert_eq!(
+            diff.accounts[&Address::repeat_byte(1)].storage[&U256::from(1)].after,
+            U256::from(4)
+        );
+    }
+}
@@ -1,15 +1,19 @@
 use super::error::DatabaseError;
+use super::genesis::GenesisState;
 use super::traits::DB;
+use super::types::RequestCache;
 use revm::db::in_memory_db::DbAccount;
 use revm::db::{AccountState, DatabaseCommit};
 use revm::primitives::{
     hash_map::Entry, Account, AccountInfo, Address, Bytecode, HashMap, Log, B256, KECCAK_EMPTY,
     U256,
 };
-use revm::Database;
+use revm::{Database, DatabaseRef};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 /// Local in-memory EVm database
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalDB {
     pub accounts: HashMap<Address, DbAccount>,
     pub contracts: HashMap<B256, Bytecode>,
@@ -36,6 +40,79 @@ impl LocalDB {
         }
     }
 
+    /// Initialise a DB from a cache of fork requests
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - Accounts and storage values requested
+    ///   by a [super::ForkDb]
+    ///
+    pub fn from_request_cache(cache: &RequestCache) -> Self {
+        let mut db = Self::new();
+        db.insert_request_cache(cache);
+        db
+    }
+
+    /// Insert accounts and storage from a cache of fork requests
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - Accounts and storage values requested
+    ///   by a [super::ForkDb]
+    ///
+    pub fn insert_request_cache(&mut self, cache: &RequestCache) {
+        for (address, info) in cache.accounts.iter() {
+            self.insert_account_info(*address, info.clone());
+        }
+        for (address, slot, value) in cache.storage.iter() {
+            self.accounts
+                .entry(*address)
+                .or_default()
+                .storage
+                .insert(*slot, *value);
+        }
+    }
+
+    /// Initialise a DB from a genesis file or state dump
+    ///
+    /// # Arguments
+    ///
+    /// * `genesis` - Accounts loaded from a geth genesis
+    ///   file or an anvil/hardhat state dump
+    ///
+    pub fn from_genesis(genesis: &GenesisState) -> Self {
+        let mut db = Self::new();
+        db.insert_genesis(genesis);
+        db
+    }
+
+    /// Insert accounts from a genesis file or state dump
+    ///
+    /// Inserted accounts replace any existing account
+    /// and storage at the same address. Storage slots
+    /// not included in the genesis read as zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `genesis` - Accounts loaded from a geth genesis
+    ///   file or an anvil/hardhat state dump
+    ///
+    pub fn insert_genesis(&mut self, genesis: &GenesisState) {
+        for (address, account) in genesis.accounts.iter() {
+            let code = match &account.code {
+                Some(code) if !code.is_empty() => Bytecode::new_raw(code.clone()),
+                _ => Bytecode::new(),
+            };
+            self.insert_account_info(
+                *address,
+                AccountInfo::new(account.balance, account.nonce, KECCAK_EMPTY, code),
+            );
+            let db_account = self.accounts.entry(*address).or_default();
+            db_account.account_state = AccountState::StorageCleared;
+            db_account.storage = account.storage.clone();
+        }
+    }
+
     pub fn insert_contract(&mut self, account: &mut AccountInfo) {
         if let Some(code) = &account.code {
             if !code.is_empty() {
@@ -54,7 +131,11 @@ impl LocalDB {
 
     pub fn insert_account_info(&mut self, address: Address, mut info: AccountInfo) {
         self.insert_contract(&mut info);
-        self.accounts.entry(address).or_default().info = info;
+        let account = self.accounts.entry(address).or_default();
+        account.info = info;
+        if matches!(account.account_state, AccountState::NotExisting) {
+            account.account_state = AccountState::StorageCleared;
+        }
     }
 
     pub fn load_account(&mut self, address: Address) -> Result<&mut DbAccount, DatabaseError> {
@@ -92,6 +173,15 @@ impl DB for LocalDB {
         self.insert_account_info(address, account_info)
     }
 
+    fn insert_account_storage(
+        &mut self,
+        address: Address,
+        slot: U256,
+        value: U256,
+    ) -> Result<(), DatabaseError> {
+        self.insert_account_storage(address, slot, value)
+    }
+
     fn accounts(&self) -> &HashMap<Address, DbAccount> {
         &self.accounts
     }
@@ -107,6 +197,10 @@ impl DB for LocalDB {
     fn block_hashes(&self) -> &HashMap<U256, B256> {
         &self.block_hashes
     }
+
+    fn export_state(&self) -> Cow<'_, LocalDB> {
+        Cow::Borrowed(self)
+    }
 }
 
 impl DatabaseCommit for LocalDB {
@@ -197,3 +291,44 @@ impl Database for LocalDB {
         }
     }
 }
+
+impl DatabaseRef for LocalDB {
+    type Error = DatabaseError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        Ok(self.accounts.get(&address).and_then(|a| a.info()))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => Err(DatabaseError::MissingCode(code_hash)),
+        }
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(account) => match account.storage.get(&index) {
+                Some(value) => Ok(*value),
+                None => {
+                    if matches!(
+                        account.account_state,
+                        AccountState::StorageCleared | AccountState::NotExisting
+                    ) {
+                        Ok(U256::ZERO)
+                    } else {
+                        Err(DatabaseError::GetStorage(address, index))
+                    }
+                }
+            },
+            None => Err(DatabaseError::GetStorage(address, index)),
+        }
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        match self.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => Err(DatabaseError::GetBlockHash(number)),
+        }
+    }
+}
@@ -18,15 +18,34 @@
 //! ````
 //!
 
+mod anvil_state;
+mod cassette;
 mod error;
+mod fork_backend;
 mod fork_db;
+mod genesis;
+mod historical;
+mod layered_db;
 mod local_db;
 mod provider;
+mod rpc_backend;
 mod runtime_client;
+mod snapshot;
+mod storage_dump;
 mod traits;
 mod types;
 
+pub use anvil_state::{AnvilAccount, AnvilState, AnvilStateError};
+pub use cassette::{CassetteError, ForkMode};
+pub use error::DatabaseError;
+pub use fork_backend::{FileBackend, ForkBackend, ForkBlock, MockBackend};
 pub use fork_db::ForkDb;
+pub use genesis::{GenesisAccount, GenesisError, GenesisState};
+pub use historical::{HistoricalBlock, HistoricalTransaction};
+pub use layered_db::LayeredDB;
 pub use local_db::LocalDB;
+pub use rpc_backend::RpcBackend;
+pub use snapshot::{Snapshot, SnapshotError, SNAPSHOT_VERSION};
+pub use storage_dump::{StorageDump, StorageDumpError};
 pub use traits::DB;
-pub use types::RequestCache;
+pub use types::{RequestCache, RequestCacheError};
@@ -1,3 +1,4 @@
+use super::cassette::Cassette;
 use super::runtime_client::{RuntimeClient, RuntimeClientBuilder};
 use ethers_core::types::Chain;
 use ethers_providers::{is_local_endpoint, Provider, DEFAULT_LOCAL_POLL_INTERVAL};
@@ -5,6 +6,7 @@ use eyre::{Result, WrapErr};
 use reqwest::Url;
 use std::{
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 use url::ParseError;
@@ -25,6 +27,7 @@ pub struct ProviderBuilder {
     compute_units_per_second: u64,
     jwt: Option<String>,
     headers: Vec<String>,
+    cassette: Option<Arc<Cassette>>,
 }
 
 impl ProviderBuilder {
@@ -61,9 +64,16 @@ impl ProviderBuilder {
             compute_units_per_second: ALCHEMY_FREE_TIER_CUPS,
             jwt: None,
             headers: vec![],
+            cassette: None,
         }
     }
 
+    /// Set cassette used to record/replay requests
+    pub(crate) fn cassette(mut self, cassette: Option<Arc<Cassette>>) -> Self {
+        self.cassette = cassette;
+        self
+    }
+
     pub fn build(self) -> Result<RetryProvider> {
         let ProviderBuilder {
             url,
@@ -75,6 +85,7 @@ impl ProviderBuilder {
             compute_units_per_second,
             jwt,
             headers,
+            cassette,
         } = self;
         let url = url?;
 
@@ -87,7 +98,8 @@ impl ProviderBuilder {
             compute_units_per_second,
         )
         .with_headers(headers)
-        .with_jwt(jwt);
+        .with_jwt(jwt)
+        .with_cassette(cassette);
 
         let mut provider = Provider::new(client_builder.build());
 
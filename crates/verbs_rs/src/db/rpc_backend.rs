@@ -0,0 +1,733 @@
+//! JSON-RPC fork backend
+//!
+//! Backend loading values from a remote endpoint
+//! (e.g. [Alchemy](https://www.alchemy.com/)) using
+//! JSON-RPC requests. Account balance, nonce and code
+//! are requested in a single batch, and requests can
+//! be recorded to, or replayed from, a cassette file
+//! (see [ForkMode]).
+//!
+
+use super::cassette::{Cassette, ForkMode};
+use super::error::DatabaseError;
+use super::fork_backend::{ForkBackend, ForkBlock};
+use super::historical::HistoricalBlock;
+use super::provider::ProviderBuilder;
+use super::runtime_client::{RuntimeClient, RuntimeClientError};
+use super::storage_dump::StorageRange;
+use super::types::{ToAlloy, ToEthers};
+use alloy_primitives::{keccak256, Address, Bytes};
+use ethers_core::types::{
+    BigEndianHash, Block, BlockId, BlockNumber, Bytes as EthersBytes, H256, U256 as EthersU256,
+};
+use ethers_providers::{JsonRpcClient, Middleware, Provider};
+use futures::stream::{self, StreamExt};
+use revm::primitives::{AccountInfo, Bytecode, HashMap, B256, KECCAK_EMPTY, U256};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// Placeholder endpoint used when replaying requests
+const OFFLINE_URL: &str = "http://localhost:8545";
+/// Maximum number of requests in a JSON-RPC batch
+const BATCH_SIZE: usize = 96;
+/// Maximum number of batches requested concurrently
+const MAX_CONCURRENT_BATCHES: usize = 8;
+/// Maximum number of storage values in a range request
+const STORAGE_RANGE_SIZE: usize = 1024;
+
+/// Fork backend making requests to a JSON-RPC endpoint
+///
+/// All requests are made using a single runtime
+/// that lives as long as the backend.
+#[derive(Debug)]
+pub struct RpcBackend {
+    provider: Provider<RuntimeClient>,
+    rt: Runtime,
+    block_id: Option<BlockId>,
+    block: ForkBlock,
+}
+
+impl RpcBackend {
+    /// Initialise a backend forking from a remote endpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `node_url` - Url of service to make db requests
+    /// * `block_number` - Block number to fork from, if None
+    ///   latest available block will be used.
+    ///
+    pub fn new(node_url: &str, block_number: Option<u64>) -> Self {
+        Self::with_mode(node_url, block_number, ForkMode::Live)
+    }
+
+    /// Initialise a backend with a data source mode
+    ///
+    /// # Arguments
+    ///
+    /// * `node_url` - Url of service to make db requests,
+    ///   unused in [ForkMode::Replay] mode.
+    /// * `block_number` - Block number to fork from, if None
+    ///   latest available block will be used.
+    /// * `mode` - Fork mode, i.e. whether requests are
+    ///   recorded to or replayed from a cassette file.
+    ///
+    pub fn with_mode(node_url: &str, block_number: Option<u64>, mode: ForkMode) -> Self {
+        let block_number = match block_number {
+            Some(n) => BlockNumber::Number(n.into()),
+            None => BlockNumber::Latest,
+        };
+
+        let cassette = Cassette::open(&mode)
+            .unwrap_or_else(|e| panic!("Could not open cassette for {:?}: {}", mode, e));
+        let node_url = match mode.is_offline() {
+            true => OFFLINE_URL,
+            false => node_url,
+        };
+
+        let provider = ProviderBuilder::new(node_url)
+            .cassette(cassette.map(Arc::new))
+            .build()
+            .unwrap();
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let block = match rt.block_on(provider.get_block(block_number)) {
+            Ok(Some(b)) => b,
+            Ok(None) => panic!("Could not retrieve block"),
+            Err(e) => panic!("Could not retrieve block: {}", e),
+        };
+
+        let number = match block.number {
+            Some(n) => U256::from(n.as_u64()),
+            None => U256::ZERO,
+        };
+
+        RpcBackend {
+            provider,
+            rt,
+            block_id: Some(block.number.unwrap().into()),
+            block: ForkBlock {
+                number,
+                timestamp: block.timestamp.to_alloy(),
+            },
+        }
+    }
+}
+
+impl ForkBackend for RpcBackend {
+    fn block(&self) -> ForkBlock {
+        self.block.clone()
+    }
+
+    fn account(&self, address: Address) -> Result<Option<AccountInfo>, DatabaseError> {
+        let info = basic_from_fork(&self.rt, self.provider.as_ref(), address, self.block_id);
+        account_result(info)
+    }
+
+    fn storage(&self, address: Address, index: U256) -> Result<U256, DatabaseError> {
+        storage_from_fork(
+            &self.rt,
+            self.provider.as_ref(),
+            address,
+            index,
+            self.block_id,
+        )
+        .map_err(|e| storage_error(e, address, index))
+    }
+
+    fn block_hash(&self, number: U256) -> Result<B256, DatabaseError> {
+        block_hash_from_fork(&self.rt, self.provider.as_ref(), number)
+            .map_err(|e| block_hash_error(e, number))
+    }
+
+    fn full_storage(&self, address: Address) -> Result<Option<HashMap<U256, U256>>, DatabaseError> {
+        full_storage_from_fork(&self.rt, self.provider.as_ref(), address, self.block.number)
+            .map_err(|e| match e {
+                RuntimeClientError::CassetteMiss(request) => DatabaseError::CassetteMiss(request),
+                _ => DatabaseError::GetFullStorage(address),
+            })
+    }
+
+    fn block_transactions(&self, number: U256) -> Result<Option<HistoricalBlock>, DatabaseError> {
+        let n: u64 = number.saturating_to();
+        let params = json!([BlockNumber::from(n), true]);
+        self.rt
+            .block_on(
+                self.provider
+                    .as_ref()
+                    .request("eth_getBlockByNumber", params),
+            )
+            .map_err(|e| match e {
+                RuntimeClientError::CassetteMiss(request) => DatabaseError::CassetteMiss(request),
+                _ => DatabaseError::GetFullBlock(n.into()),
+            })
+    }
+
+    fn accounts(&self, addresses: &[Address]) -> Vec<Result<Option<AccountInfo>, DatabaseError>> {
+        let requests = addresses
+            .iter()
+            .flat_map(|a| account_requests(*a, self.block_id))
+            .collect();
+        let responses = self
+            .rt
+            .block_on(request_batches(self.provider.as_ref(), requests));
+
+        let mut responses = responses.into_iter();
+        addresses
+            .iter()
+            .map(|_| account_result(account_from_responses(responses.by_ref().take(3))))
+            .collect()
+    }
+
+    fn storage_slots(&self, slots: &[(Address, U256)]) -> Vec<Result<U256, DatabaseError>> {
+        let requests = slots
+            .iter()
+            .map(|(address, index)| storage_request(*address, *index, self.block_id))
+            .collect();
+        let responses = self
+            .rt
+            .block_on(request_batches(self.provider.as_ref(), requests));
+
+        slots
+            .iter()
+            .zip(responses)
+            .map(|((address, index), response)| {
+                decode::<H256>(response)
+                    .map(|s| s.into_uint().to_alloy())
+                    .map_err(|e| storage_error(e, *address, *index))
+            })
+            .collect()
+    }
+}
+
+/// Failed account requests are treated as the
+/// account not existing, unless the request was
+/// missing from a replayed cassette
+fn account_result(
+    info: Result<AccountInfo, RuntimeClientError>,
+) -> Result<Option<AccountInfo>, DatabaseError> {
+    match info {
+        Ok(i) => Ok(Some(i)),
+        Err(RuntimeClientError::CassetteMiss(request)) => Err(DatabaseError::CassetteMiss(request)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn storage_error(err: RuntimeClientError, address: Address, index: U256) -> DatabaseError {
+    match err {
+        RuntimeClientError::CassetteMiss(request) => DatabaseError::CassetteMiss(request),
+        _ => DatabaseError::GetStorage(address, index),
+    }
+}
+
+fn block_hash_error(err: RuntimeClientError, number: U256) -> DatabaseError {
+    match err {
+        RuntimeClientError::CassetteMiss(request) => DatabaseError::CassetteMiss(request),
+        _ => DatabaseError::GetBlockHash(number),
+    }
+}
+
+fn decode<T: DeserializeOwned>(
+    response: Result<Value, RuntimeClientError>,
+) -> Result<T, RuntimeClientError> {
+    serde_json::from_value(response?).map_err(|e| RuntimeClientError::ProviderError(e.into()))
+}
+
+fn block_param(block_id: Option<BlockId>) -> Value {
+    json!(block_id.unwrap_or_else(|| BlockNumber::Latest.into()))
+}
+
+/// Requests for the balance, nonce and code of an account
+fn account_requests(address: Address, block_id: Option<BlockId>) -> [(&'static str, Value); 3] {
+    let params = json!([address.to_ethers(), block_param(block_id)]);
+    [
+        ("eth_getBalance", params.clone()),
+        ("eth_getTransactionCount", params.clone()),
+        ("eth_getCode", params),
+    ]
+}
+
+/// Build account info from the responses to [account_requests]
+fn account_from_responses(
+    responses: impl IntoIterator<Item = Result<Value, RuntimeClientError>>,
+) -> Result<AccountInfo, RuntimeClientError> {
+    let mut responses = responses.into_iter();
+    let balance: EthersU256 = decode(responses.next().unwrap())?;
+    let nonce: EthersU256 = decode(responses.next().unwrap())?;
+    let code: EthersBytes = decode(responses.next().unwrap())?;
+    let code = Bytes::from(code.0);
+
+    let (code, code_hash) = if !code.is_empty() {
+        (code.clone(), keccak256(&code))
+    } else {
+        (Bytes::default(), KECCAK_EMPTY)
+    };
+
+    Ok(AccountInfo {
+        balance: balance.to_alloy(),
+        nonce: nonce.as_u64(),
+        code_hash,
+        code: Some(Bytecode::new_raw(code)),
+    })
+}
+
+fn storage_request(
+    address: Address,
+    index: U256,
+    block_id: Option<BlockId>,
+) -> (&'static str, Value) {
+    (
+        "eth_getStorageAt",
+        json!([
+            address.to_ethers(),
+            index.to_ethers(),
+            block_param(block_id)
+        ]),
+    )
+}
+
+fn basic_from_fork(
+    rt: &Runtime,
+    client: &RuntimeClient,
+    address: Address,
+    block_id: Option<BlockId>,
+) -> Result<AccountInfo, RuntimeClientError> {
+    // Balance, nonce and code are requested in a single batch
+    let responses = rt.block_on(client.request_batch(&account_requests(address, block_id)));
+    account_from_responses(responses)
+}
+
+fn storage_from_fork(
+    rt: &Runtime,
+    client: &RuntimeClient,
+    address: Address,
+    index: U256,
+    block_id: Option<BlockId>,
+) -> Result<U256, RuntimeClientError> {
+    let (method, params) = storage_request(address, index, block_id);
+    let storage: H256 = rt.block_on(client.request(method, params))?;
+    Ok(storage.into_uint().to_alloy())
+}
+
+fn block_hash_from_fork(
+    rt: &Runtime,
+    client: &RuntimeClient,
+    number: U256,
+) -> Result<B256, RuntimeClientError> {
+    let n: u64 = number.try_into().unwrap();
+    let params = json!([BlockNumber::from(n), false]);
+
+    let block: Option<Block<H256>> = rt.block_on(client.request("eth_getBlockByNumber", params))?;
+
+    match block {
+        Some(block) => Ok(block
+            .hash
+            .expect("empty block hash on mined block, this should never happen")
+            .to_alloy()),
+        None => Ok(KECCAK_EMPTY),
+    }
+}
+
+/// Request the full storage of a contract using
+/// `debug_storageRangeAt`
+///
+/// Storage is read from the state before the first
+/// transaction of the block after the fork block,
+/// so `None` is returned if that block does not
+/// exist yet (or the node does not return key
+/// preimages).
+fn full_storage_from_fork(
+    rt: &Runtime,
+    client: &RuntimeClient,
+    address: Address,
+    block_number: U256,
+) -> Result<Option<HashMap<U256, U256>>, RuntimeClientError> {
+    let n: u64 = block_number.try_into().unwrap();
+    let params = json!([BlockNumber::from(n + 1), false]);
+    let block: Option<Block<H256>> = rt.block_on(client.request("eth_getBlockByNumber", params))?;
+
+    let block_hash = match block.and_then(|b| b.hash) {
+        Some(hash) => hash,
+        None => return Ok(None),
+    };
+
+    let mut storage = HashMap::new();
+    let mut start_key = B256::ZERO;
+
+    loop {
+        let params = json!([
+            block_hash,
+            0,
+            address.to_ethers(),
+            start_key,
+            STORAGE_RANGE_SIZE
+        ]);
+        let range: StorageRange = rt.block_on(client.request("debug_storageRangeAt", params))?;
+        let next_key = range.next_key;
+
+        if range.insert_into(address, &mut storage).is_err() {
+            return Ok(None);
+        }
+
+        match next_key {
+            Some(key) => start_key = key,
+            None => return Ok(Some(storage)),
+        }
+    }
+}
+
+/// Make requests in concurrent batches
+async fn request_batches(
+    client: &RuntimeClient,
+    requests: Vec<(&'static str, Value)>,
+) -> Vec<Result<Value, RuntimeClientError>> {
+    stream::iter(requests.chunks(BATCH_SIZE))
+        .map(|batch| client.request_batch(batch))
+        .buffered(MAX_CONCURRENT_BATCHES)
+        .flat_map(stream::iter)
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ForkDb;
+    use ethers_core::types::U64;
+    use revm::Database;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    fn test_block() -> Block<H256> {
+        Block::<H256> {
+            number: Some(U64::from(100)),
+            timestamp: 1_700_000_000u64.into(),
+            ..Default::default()
+        }
+    }
+
+    fn respond(request: &Value) -> Value {
+        let result = match request["method"].as_str().unwrap() {
+            "eth_getBlockByNumber" => serde_json::to_value(test_block()).unwrap(),
+            "eth_getBalance" => json!("0x3e8"),
+            "eth_getTransactionCount" => json!("0x1"),
+            "eth_getCode" => json!("0x"),
+            // Return the storage index + 1
+            "eth_getStorageAt" => {
+                let index: EthersU256 =
+                    serde_json::from_value(request["params"][1].clone()).unwrap();
+                json!(H256::from_uint(&(index + 1)))
+            }
+            m => panic!("Unexpected method {}", m),
+        };
+        json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+    }
+
+    fn handle_connection(
+        mut stream: TcpStream,
+        n_requests: Arc<AtomicUsize>,
+        rate_limited: Arc<AtomicUsize>,
+    ) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        loop {
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                let line = line.trim_end().to_ascii_lowercase();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(n) = line.strip_prefix("content-length:") {
+                    content_length = n.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            n_requests.fetch_add(1, Ordering::SeqCst);
+
+            if rate_limited
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                write!(
+                    stream,
+                    "HTTP/1.1 429 Too Many Requests\r\nContent-Length: 0\r\n\r\n"
+                )
+                .unwrap();
+                continue;
+            }
+
+            let response = match serde_json::from_slice(&body).unwrap() {
+                Value::Array(requests) => Value::Array(requests.iter().map(respond).collect()),
+                request => respond(&request),
+            }
+            .to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+        }
+    }
+
+    /// Start a JSON-RPC node counting HTTP requests, that
+    /// rejects the next `rate_limited` requests with a 429
+    fn mock_node(n_requests: Arc<AtomicUsize>, rate_limited: Arc<AtomicUsize>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let n_requests = n_requests.clone();
+                let rate_limited = rate_limited.clone();
+                thread::spawn(move || handle_connection(stream.unwrap(), n_requests, rate_limited));
+            }
+        });
+        url
+    }
+
+    fn write_cassette(path: &std::path::Path, address: Address) {
+        let block = test_block();
+        let address = format!("{:#x}", address);
+        let block_id = json!("0x64");
+
+        let cassette = Cassette::open(&ForkMode::Record(path.to_path_buf()))
+            .unwrap()
+            .unwrap();
+        cassette
+            .insert(
+                "eth_getBlockByNumber",
+                json!([block_id, false]),
+                serde_json::to_value(block).unwrap(),
+            )
+            .unwrap();
+        cassette
+            .insert("eth_getBalance", json!([address, block_id]), json!("0x3e8"))
+            .unwrap();
+        cassette
+            .insert(
+                "eth_getTransactionCount",
+                json!([address, block_id]),
+                json!("0x1"),
+            )
+            .unwrap();
+        cassette
+            .insert("eth_getCode", json!([address, block_id]), json!("0x"))
+            .unwrap();
+        cassette
+            .insert(
+                "eth_getStorageAt",
+                json!([address, "0x1", block_id]),
+                json!(format!("{:#x}", B256::with_last_byte(2))),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_replay_from_cassette() {
+        let path =
+            std::env::temp_dir().join(format!("verbs_cassette_{}.jsonl", std::process::id()));
+        let address = Address::repeat_byte(1);
+        write_cassette(&path, address);
+
+        let mut db = ForkDb::with_mode("", Some(100), ForkMode::Replay(path.clone()));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(db.requests.start_block_number, U256::from(100));
+        assert_eq!(db.requests.start_timestamp, U256::from(1_700_000_000u64));
+
+        let info = db.basic(address).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(1000));
+        assert_eq!(info.nonce, 1);
+        assert_eq!(db.storage(address, U256::from(1)).unwrap(), U256::from(2));
+
+        assert!(matches!(
+            db.basic(Address::repeat_byte(2)),
+            Err(DatabaseError::CassetteMiss(_))
+        ));
+        assert!(matches!(
+            db.storage(address, U256::from(2)),
+            Err(DatabaseError::CassetteMiss(_))
+        ));
+    }
+
+    #[test]
+    fn test_full_storage_from_cassette() {
+        let path = std::env::temp_dir().join(format!(
+            "verbs_storage_cassette_{}.jsonl",
+            std::process::id()
+        ));
+        let address = Address::repeat_byte(1);
+        write_cassette(&path, address);
+
+        let next_block = Block::<H256> {
+            number: Some(U64::from(101)),
+            hash: Some(H256::repeat_byte(3)),
+            ..Default::default()
+        };
+        let entry = |key: u64, value: u64| {
+            json!({
+                "key": B256::from(U256::from(key)),
+                "value": B256::from(U256::from(value)),
+            })
+        };
+        let range_params = |start_key: B256| {
+            json!([
+                H256::repeat_byte(3),
+                0,
+                address.to_ethers(),
+                start_key,
+                STORAGE_RANGE_SIZE
+            ])
+        };
+
+        let cassette = Cassette::open(&ForkMode::Hybrid(path.clone()))
+            .unwrap()
+            .unwrap();
+        cassette
+            .insert(
+                "eth_getBlockByNumber",
+                json!(["0x65", false]),
+                serde_json::to_value(next_block).unwrap(),
+            )
+            .unwrap();
+        // Storage returned over two pages
+        cassette
+            .insert(
+                "debug_storageRangeAt",
+                range_params(B256::ZERO),
+                json!({
+                    "storage": {format!("{:#x}", B256::repeat_byte(4)): entry(1, 2)},
+                    "nextKey": B256::repeat_byte(5),
+                }),
+            )
+            .unwrap();
+        cassette
+            .insert(
+                "debug_storageRangeAt",
+                range_params(B256::repeat_byte(5)),
+                json!({
+                    "storage": {format!("{:#x}", B256::repeat_byte(5)): entry(3, 4)},
+                    "nextKey": null,
+                }),
+            )
+            .unwrap();
+        drop(cassette);
+
+        let mut db = ForkDb::with_mode("", Some(100), ForkMode::Replay(path.clone()));
+        std::fs::remove_file(&path).unwrap();
+
+        db.load_full_storage(&[address]).unwrap();
+
+        assert_eq!(db.requests.storage.len(), 2);
+        assert_eq!(db.storage(address, U256::from(1)).unwrap(), U256::from(2));
+        assert_eq!(db.storage(address, U256::from(3)).unwrap(), U256::from(4));
+        // Storage is complete, so is not requested from the cassette
+        assert_eq!(db.storage(address, U256::from(2)).unwrap(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_block_transactions_from_cassette() {
+        let path =
+            std::env::temp_dir().join(format!("verbs_block_cassette_{}.jsonl", std::process::id()));
+        write_cassette(&path, Address::repeat_byte(1));
+
+        let cassette = Cassette::open(&ForkMode::Hybrid(path.clone()))
+            .unwrap()
+            .unwrap();
+        cassette
+            .insert(
+                "eth_getBlockByNumber",
+                json!(["0x65", true]),
+                json!({
+                    "number": "0x65",
+                    "timestamp": "0x6553f10c",
+                    "transactions": [{
+                        "from": Address::repeat_byte(1),
+                        "to": Address::repeat_byte(2),
+                        "input": "0x",
+                        "value": "0x1",
+                        "nonce": "0x1",
+                    }],
+                }),
+            )
+            .unwrap();
+        drop(cassette);
+
+        let backend = RpcBackend::with_mode("", Some(100), ForkMode::Replay(path.clone()));
+        std::fs::remove_file(&path).unwrap();
+
+        let block = backend
+            .block_transactions(U256::from(101))
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(block.transactions[0].from, Address::repeat_byte(1));
+
+        assert!(matches!(
+            backend.block_transactions(U256::from(102)),
+            Err(DatabaseError::CassetteMiss(_))
+        ));
+    }
+
+    #[test]
+    fn test_batched_requests() {
+        let n_requests = Arc::new(AtomicUsize::new(0));
+        let url = mock_node(n_requests.clone(), Arc::new(AtomicUsize::new(0)));
+
+        let mut db = ForkDb::new(&url, Some(100));
+        assert_eq!(n_requests.load(Ordering::SeqCst), 1);
+
+        // Balance, nonce and code are fetched in one round trip
+        let info = db.basic(Address::repeat_byte(1)).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(1000));
+        assert_eq!(info.nonce, 1);
+        assert_eq!(n_requests.load(Ordering::SeqCst), 2);
+
+        // 99 new accounts, i.e. 297 requests in 4 batches
+        let addresses: Vec<Address> = (0..100u8).map(Address::repeat_byte).collect();
+        db.prefetch_accounts(&addresses).unwrap();
+        assert_eq!(n_requests.load(Ordering::SeqCst), 6);
+        assert_eq!(db.requests.accounts.len(), 100);
+
+        let slots: Vec<(Address, U256)> =
+            addresses.iter().map(|a| (*a, U256::from(a.0[0]))).collect();
+        db.prefetch_storage(&slots).unwrap();
+        assert_eq!(n_requests.load(Ordering::SeqCst), 8);
+        assert_eq!(db.requests.storage.len(), 100);
+
+        for (address, index) in slots.into_iter() {
+            assert_eq!(db.storage(address, index).unwrap(), index + U256::from(1));
+        }
+        assert_eq!(n_requests.load(Ordering::SeqCst), 8);
+    }
+
+    #[test]
+    fn test_rate_limited_batch() {
+        let n_requests = Arc::new(AtomicUsize::new(0));
+        let rate_limited = Arc::new(AtomicUsize::new(0));
+        let url = mock_node(n_requests.clone(), rate_limited.clone());
+
+        let mut db = ForkDb::new(&url, Some(100));
+        assert_eq!(n_requests.load(Ordering::SeqCst), 1);
+
+        // The batch is re-sent after a backoff, rather
+        // than falling back to individual requests
+        rate_limited.store(2, Ordering::SeqCst);
+        let info = db.basic(Address::repeat_byte(1)).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(1000));
+        assert_eq!(info.nonce, 1);
+        assert_eq!(n_requests.load(Ordering::SeqCst), 4);
+    }
+}
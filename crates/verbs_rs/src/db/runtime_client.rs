@@ -1,22 +1,34 @@
+use super::cassette::Cassette;
 use async_trait::async_trait;
 use ethers_providers::{
-    Authorization, ConnectionDetails, Http, HttpRateLimitRetryPolicy, JsonRpcClient, JsonRpcError,
-    JwtAuth, JwtKey, ProviderError, RetryClient, RetryClientBuilder, RpcError, Ws,
+    Authorization, ConnectionDetails, Http, HttpClientError, HttpRateLimitRetryPolicy,
+    JsonRpcClient, JsonRpcError, JwtAuth, JwtKey, ProviderError, RetryClient, RetryClientBuilder,
+    RetryPolicy, RpcError, Ws,
 };
+use futures::future::join_all;
 use reqwest::{
     header::{HeaderName, HeaderValue},
     Url,
 };
-use serde::{de::DeserializeOwned, Serialize};
-use std::{fmt::Debug, str::FromStr, sync::Arc, time::Duration};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    fmt::Debug,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use thiserror::Error;
 use tokio::sync::RwLock;
 
 /// Enum representing a the client types supported by the runtime provider
 #[derive(Debug)]
 enum InnerClient {
-    /// HTTP client
-    Http(RetryClient<Http>),
+    /// HTTP client, and underlying client used for batch requests
+    Http(RetryClient<Http>, reqwest::Client),
     /// WebSocket client
     Ws(Ws),
 }
@@ -43,6 +55,43 @@ pub enum RuntimeClientError {
     /// Invalid file path
     #[error("Invalid IPC file path: {0}")]
     BadPath(String),
+
+    /// Request missing from a replayed cassette
+    #[error("Request missing from cassette: {0}")]
+    CassetteMiss(String),
+
+    /// Failed to record a response to a cassette
+    #[error("Failed to write to cassette: {0}")]
+    CassetteWrite(String),
+}
+
+/// Average cost of a request in compute units (the
+/// cost of `eth_getStorageAt`, as used by [RetryClient])
+const AVG_REQUEST_COST: u64 = 17;
+
+/// Seconds to wait for the compute unit budget to cover
+/// requests that are `ahead_in_queue`, following [RetryClient]
+fn compute_unit_offset_in_secs(
+    compute_units_per_second: u64,
+    queued_requests: u64,
+    ahead_in_queue: u64,
+) -> u64 {
+    let capacity = compute_units_per_second
+        .saturating_div(AVG_REQUEST_COST)
+        .max(1);
+    if queued_requests > capacity {
+        queued_requests.min(ahead_in_queue) / capacity
+    } else {
+        0
+    }
+}
+
+/// Single response in a JSON-RPC batch
+#[derive(Deserialize)]
+struct BatchResponse {
+    id: usize,
+    result: Option<Value>,
+    error: Option<JsonRpcError>,
 }
 
 impl RpcError for RuntimeClientError {
@@ -84,6 +133,9 @@ pub struct RuntimeClient {
     compute_units_per_second: u64,
     jwt: Option<String>,
     headers: Vec<String>,
+    cassette: Option<Arc<Cassette>>,
+    /// Number of queued requests sent in batches
+    requests_enqueued: Arc<AtomicU64>,
 }
 
 /// Builder for RuntimeClient
@@ -97,6 +149,7 @@ pub struct RuntimeClientBuilder {
     compute_units_per_second: u64,
     jwt: Option<String>,
     headers: Vec<String>,
+    cassette: Option<Arc<Cassette>>,
 }
 
 impl ::core::fmt::Display for RuntimeClient {
@@ -155,7 +208,7 @@ impl RuntimeClient {
                 let client = client_builder
                     .build()
                     .map_err(|e| RuntimeClientError::ProviderError(e.into()))?;
-                let provider = Http::new_with_client(self.url.clone(), client);
+                let provider = Http::new_with_client(self.url.clone(), client.clone());
 
                 #[allow(clippy::box_default)]
                 let provider = RetryClientBuilder::default()
@@ -164,7 +217,7 @@ impl RuntimeClient {
                     .timeout_retries(self.timeout_retry)
                     .compute_units_per_second(self.compute_units_per_second)
                     .build(provider, Box::new(HttpRateLimitRetryPolicy));
-                Ok(InnerClient::Http(provider))
+                Ok(InnerClient::Http(provider, client))
             }
             "ws" | "wss" => {
                 let auth: Option<Authorization> = self
@@ -184,6 +237,40 @@ impl RuntimeClient {
             _ => Err(RuntimeClientError::BadScheme(self.url.to_string())),
         }
     }
+
+    /// Connect to the remote endpoint if not already connected
+    async fn ensure_connected(&self) -> Result<(), RuntimeClientError> {
+        if self.client.read().await.is_none() {
+            let mut w = self.client.write().await;
+            if w.is_none() {
+                *w = Some(
+                    self.connect()
+                        .await
+                        .map_err(|e| RuntimeClientError::ProviderError(e.into()))?,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Make a request to the remote endpoint
+    async fn request_remote<T, R>(&self, method: &str, params: T) -> Result<R, RuntimeClientError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        self.ensure_connected().await?;
+
+        let res = match self.client.read().await.as_ref().unwrap() {
+            InnerClient::Http(http, _) => RetryClient::request(http, method, params)
+                .await
+                .map_err(|e| RuntimeClientError::ProviderError(e.into())),
+            InnerClient::Ws(ws) => JsonRpcClient::request(ws, method, params)
+                .await
+                .map_err(|e| RuntimeClientError::ProviderError(e.into())),
+        }?;
+        Ok(res)
+    }
 }
 
 impl RuntimeClientBuilder {
@@ -205,6 +292,7 @@ impl RuntimeClientBuilder {
             compute_units_per_second,
             jwt: None,
             headers: vec![],
+            cassette: None,
         }
     }
 
@@ -221,6 +309,12 @@ impl RuntimeClientBuilder {
         self
     }
 
+    /// Set cassette used to record/replay requests
+    pub(crate) fn with_cassette(mut self, cassette: Option<Arc<Cassette>>) -> Self {
+        self.cassette = cassette;
+        self
+    }
+
     /// Builds RuntimeClient instance
     pub fn build(self) -> RuntimeClient {
         RuntimeClient {
@@ -233,6 +327,8 @@ impl RuntimeClientBuilder {
             compute_units_per_second: self.compute_units_per_second,
             jwt: self.jwt,
             headers: self.headers,
+            cassette: self.cassette,
+            requests_enqueued: Arc::new(AtomicU64::new(0)),
         }
     }
 }
@@ -247,23 +343,267 @@ impl JsonRpcClient for RuntimeClient {
         T: Debug + Serialize + Send + Sync,
         R: DeserializeOwned + Send,
     {
-        if self.client.read().await.is_none() {
-            let mut w = self.client.write().await;
-            *w = Some(
-                self.connect()
-                    .await
-                    .map_err(|e| RuntimeClientError::ProviderError(e.into()))?,
-            );
+        let cassette = match &self.cassette {
+            Some(c) => c,
+            None => return self.request_remote(method, params).await,
+        };
+
+        let params = serde_json::to_value(&params)
+            .map_err(|e| RuntimeClientError::ProviderError(e.into()))?;
+
+        let result = match cassette.get(method, &params) {
+            Some(r) => r,
+            None if cassette.allows_network() => {
+                let r: serde_json::Value = self.request_remote(method, &params).await?;
+                cassette
+                    .insert(method, params, r.clone())
+                    .map_err(|e| RuntimeClientError::CassetteWrite(e.to_string()))?;
+                r
+            }
+            None => {
+                return Err(RuntimeClientError::CassetteMiss(
+                    super::cassette::request_key(method, &params),
+                ))
+            }
+        };
+
+        serde_json::from_value(result).map_err(|e| RuntimeClientError::ProviderError(e.into()))
+    }
+}
+
+impl RuntimeClient {
+    /// Make a batch of requests
+    ///
+    /// Requests are served from the cassette where
+    /// possible, and the remaining requests are sent
+    /// to the remote endpoint in a single JSON-RPC
+    /// batch (HTTP only). Each request in a batch counts
+    /// against the compute units per second budget, and
+    /// rate limited requests are re-sent with a backoff.
+    /// Requests that otherwise fail as part of the batch
+    /// are retried individually.
+    ///
+    /// # Arguments
+    ///
+    /// * `requests` - JSON-RPC methods and parameters
+    ///
+    pub async fn request_batch(
+        &self,
+        requests: &[(&str, Value)],
+    ) -> Vec<Result<Value, RuntimeClientError>> {
+        let mut results: Vec<Option<Result<Value, RuntimeClientError>>> =
+            Vec::with_capacity(requests.len());
+        let mut remote = Vec::new();
+
+        for (i, (method, params)) in requests.iter().enumerate() {
+            match &self.cassette {
+                Some(c) => match c.get(method, params) {
+                    Some(r) => results.push(Some(Ok(r))),
+                    None if c.allows_network() => {
+                        results.push(None);
+                        remote.push(i);
+                    }
+                    None => results.push(Some(Err(RuntimeClientError::CassetteMiss(
+                        super::cassette::request_key(method, params),
+                    )))),
+                },
+                None => {
+                    results.push(None);
+                    remote.push(i);
+                }
+            }
         }
 
-        let res = match self.client.read().await.as_ref().unwrap() {
-            InnerClient::Http(http) => RetryClient::request(http, method, params)
-                .await
-                .map_err(|e| RuntimeClientError::ProviderError(e.into())),
-            InnerClient::Ws(ws) => JsonRpcClient::request(ws, method, params)
-                .await
-                .map_err(|e| RuntimeClientError::ProviderError(e.into())),
-        }?;
-        Ok(res)
+        if !remote.is_empty() {
+            let remote_requests: Vec<(&str, &Value)> = remote
+                .iter()
+                .map(|i| (requests[*i].0, &requests[*i].1))
+                .collect();
+            let remote_results = self.request_batch_remote(&remote_requests).await;
+
+            for (i, result) in remote.into_iter().zip(remote_results) {
+                if let (Some(c), Ok(r)) = (&self.cassette, &result) {
+                    let (method, params) = &requests[i];
+                    if let Err(e) = c.insert(method, params.clone(), r.clone()) {
+                        results[i] = Some(Err(RuntimeClientError::CassetteWrite(e.to_string())));
+                        continue;
+                    }
+                }
+                results[i] = Some(result);
+            }
+        }
+
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Send a batch of requests to the remote endpoint
+    async fn request_batch_remote(
+        &self,
+        requests: &[(&str, &Value)],
+    ) -> Vec<Result<Value, RuntimeClientError>> {
+        if requests.len() > 1 {
+            if let Err(e) = self.ensure_connected().await {
+                let e = e.to_string();
+                return requests
+                    .iter()
+                    .map(|_| {
+                        Err(RuntimeClientError::ProviderError(
+                            ProviderError::CustomError(e.clone()),
+                        ))
+                    })
+                    .collect();
+            }
+
+            let http_client = match self.client.read().await.as_ref().unwrap() {
+                InnerClient::Http(_, client) => Some(client.clone()),
+                InnerClient::Ws(_) => None,
+            };
+
+            if let Some(client) = http_client {
+                let mut responses = self.post_batch_with_retry(&client, requests).await;
+                // Retry failed requests individually
+                let retries = responses
+                    .iter()
+                    .zip(requests)
+                    .filter(|(r, _)| r.is_none())
+                    .map(|(_, (method, params))| self.request_remote::<_, Value>(method, *params));
+                let mut retried = join_all(retries).await.into_iter();
+
+                return responses
+                    .iter_mut()
+                    .map(|r| match r.take() {
+                        Some(v) => Ok(v),
+                        None => retried.next().unwrap(),
+                    })
+                    .collect();
+            }
+        }
+
+        join_all(
+            requests
+                .iter()
+                .map(|(method, params)| self.request_remote::<_, Value>(method, *params)),
+        )
+        .await
+    }
+
+    /// Post a JSON-RPC batch over HTTP, retrying rate limited requests
+    ///
+    /// Rate limited requests (using the same policy as
+    /// [RetryClient]) are re-sent in a batch after a backoff,
+    /// that includes the time for the compute unit budget
+    /// to cover the queued requests, up to the maximum
+    /// number of retries.
+    ///
+    /// Returns the result of each request, or `None`
+    /// if the request failed.
+    async fn post_batch_with_retry(
+        &self,
+        client: &reqwest::Client,
+        requests: &[(&str, &Value)],
+    ) -> Vec<Option<Value>> {
+        let n_requests = requests.len() as u64;
+        let ahead_in_queue = self
+            .requests_enqueued
+            .fetch_add(n_requests, Ordering::SeqCst)
+            + n_requests;
+
+        let mut results = vec![None; requests.len()];
+        let mut pending: Vec<usize> = (0..requests.len()).collect();
+        let mut n_retries = 0;
+
+        while !pending.is_empty() {
+            let batch: Vec<(&str, &Value)> = pending.iter().map(|i| requests[*i]).collect();
+
+            let rate_limited = match self.post_batch(client, &batch).await {
+                Ok(responses) => {
+                    let mut rate_limited = None;
+                    let mut retry = Vec::new();
+                    for (i, response) in pending.into_iter().zip(responses) {
+                        match response {
+                            Some(Ok(v)) => results[i] = Some(v),
+                            Some(Err(e)) => {
+                                let e = HttpClientError::JsonRpcError(e);
+                                if HttpRateLimitRetryPolicy.should_retry(&e) {
+                                    retry.push(i);
+                                    rate_limited = Some(e);
+                                }
+                            }
+                            None => (),
+                        }
+                    }
+                    pending = retry;
+                    rate_limited
+                }
+                Err(e) => HttpRateLimitRetryPolicy.should_retry(&e).then_some(e),
+            };
+
+            match rate_limited {
+                Some(e) if n_retries < self.max_retry => {
+                    n_retries += 1;
+                    let backoff = HttpRateLimitRetryPolicy
+                        .backoff_hint(&e)
+                        .unwrap_or(Duration::from_millis(self.initial_backoff))
+                        + Duration::from_secs(compute_unit_offset_in_secs(
+                            self.compute_units_per_second,
+                            self.requests_enqueued.load(Ordering::SeqCst),
+                            ahead_in_queue,
+                        ));
+                    tokio::time::sleep(backoff).await;
+                }
+                _ => break,
+            }
+        }
+
+        self.requests_enqueued
+            .fetch_sub(n_requests, Ordering::SeqCst);
+        results
+    }
+
+    /// Post a JSON-RPC batch over HTTP
+    ///
+    /// Returns the result or error of each request,
+    /// or `None` if the response is missing.
+    async fn post_batch(
+        &self,
+        client: &reqwest::Client,
+        requests: &[(&str, &Value)],
+    ) -> Result<Vec<Option<Result<Value, JsonRpcError>>>, HttpClientError> {
+        let body: Vec<Value> = requests
+            .iter()
+            .enumerate()
+            .map(|(id, (method, params))| {
+                json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+            })
+            .collect();
+        let body = serde_json::to_vec(&body).map_err(|err| HttpClientError::SerdeJson {
+            err,
+            text: String::new(),
+        })?;
+
+        let response = client
+            .post(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())?;
+        let bytes = response.bytes().await?;
+        let responses: Vec<BatchResponse> =
+            serde_json::from_slice(&bytes).map_err(|err| HttpClientError::SerdeJson {
+                err,
+                text: String::from_utf8_lossy(&bytes).to_string(),
+            })?;
+
+        let mut results = vec![None; requests.len()];
+        for response in responses.into_iter() {
+            if let Some(slot) = results.get_mut(response.id) {
+                *slot = Some(match response.error {
+                    Some(e) => Err(e),
+                    None => Ok(response.result.unwrap_or(Value::Null)),
+                });
+            }
+        }
+        Ok(results)
     }
 }
@@ -0,0 +1,266 @@
+//! Binary snapshots of simulation state
+//!
+//! A snapshot stores the state of a DB (accounts,
+//! contracts, logs and block hashes) along with
+//! the current block environment, in a compact
+//! versioned binary format. Snapshots can be used
+//! to persist and share the initial state of a
+//! simulation, without re-running any setup (e.g.
+//! contract deployment or requests to a fork).
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::{Address, U256};
+//! use verbs_rs::env::{Env, RandomValidator};
+//! use verbs_rs::{LocalDB, Snapshot};
+//!
+//! let mut env = Env::<LocalDB, RandomValidator>::init(
+//!     U256::ZERO, U256::ZERO, RandomValidator {}
+//! );
+//! env.insert_account(Address::repeat_byte(1), U256::from(1000));
+//!
+//! let bytes = Snapshot::from_env(&env).to_bytes().unwrap();
+//!
+//! let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+//! let new_env = Env::<LocalDB, RandomValidator>::from_snapshot(
+//!     snapshot, RandomValidator {}
+//! );
+//! ```
+
+use super::local_db::LocalDB;
+use super::traits::DB;
+use crate::env::{Env, Validator};
+use revm::primitives::BlockEnv;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Magic bytes identifying a snapshot
+const SNAPSHOT_MAGIC: [u8; 8] = *b"VERBSSNP";
+/// Version of the snapshot format
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Error raised reading or writing a snapshot
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("data is not a verbs snapshot")]
+    InvalidFormat,
+    #[error("unsupported snapshot version {0} (expected {SNAPSHOT_VERSION})")]
+    Version(u32),
+    #[error("failed to encode/decode snapshot: {0}")]
+    Encoding(#[from] bincode::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Snapshot header
+#[derive(Serialize, Deserialize)]
+struct Header {
+    magic: [u8; 8],
+    version: u32,
+}
+
+/// Snapshot of a DB and block environment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Block environment at the time of the snapshot
+    pub block: BlockEnv,
+    /// DB state
+    pub db: LocalDB,
+}
+
+impl Snapshot {
+    /// Create a snapshot of the state of an environment
+    ///
+    /// Copies the state of the environment DB into
+    /// a [LocalDB] (see [DB::export_state]), so snapshots
+    /// can be created from any DB (e.g. a [super::ForkDb]
+    /// or the merged layers of a [super::LayeredDB]).
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Simulation environment
+    ///
+    pub fn from_env<D: DB, V: Validator>(env: &Env<D, V>) -> Self {
+        let view = env.view();
+
+        Snapshot {
+            block: view.block().clone(),
+            db: view.db().export_state().into_owned(),
+        }
+    }
+
+    /// Write the snapshot in binary format
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), SnapshotError> {
+        bincode::serialize_into(
+            &mut writer,
+            &Header {
+                magic: SNAPSHOT_MAGIC,
+                version: SNAPSHOT_VERSION,
+            },
+        )?;
+        bincode::serialize_into(&mut writer, self)?;
+        Ok(())
+    }
+
+    /// Read a snapshot in binary format
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, SnapshotError> {
+        let header: Header =
+            bincode::deserialize_from(&mut reader).map_err(|_| SnapshotError::InvalidFormat)?;
+        if header.magic != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::InvalidFormat);
+        }
+        if header.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version(header.version));
+        }
+        Ok(bincode::deserialize_from(&mut reader)?)
+    }
+
+    /// Encode the snapshot as bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Decode a snapshot from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Self::read(bytes)
+    }
+
+    /// Save the snapshot to a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to create
+    ///
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load a snapshot from a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the snapshot file
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::RandomValidator;
+    use alloy_primitives::{Address, U256};
+    use revm::DatabaseRef;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut env = Env::<LocalDB, RandomValidator>::init(
+            U256::from(100),
+            U256::from(10),
+            RandomValidator {},
+        );
+        let address = Address::repeat_byte(1);
+        env.insert_account(address, U256::from(1000));
+        env.evm_state()
+            .context
+            .evm
+            .db
+            .accounts
+            .get_mut(&address)
+            .unwrap()
+            .storage
+            .insert(U256::from(1), U256::from(2));
+
+        let bytes = Snapshot::from_env(&env).to_bytes().unwrap();
+        let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+
+        assert_eq!(snapshot.block.timestamp, U256::from(100));
+        assert_eq!(snapshot.block.number, U256::from(10));
+
+        let mut new_env =
+            Env::<LocalDB, RandomValidator>::from_snapshot(snapshot, RandomValidator {});
+        let account = &new_env.evm_state().context.evm.db.accounts[&address];
+
+        assert_eq!(account.info.balance, U256::from(1000));
+        assert_eq!(account.storage[&U256::from(1)], U256::from(2));
+        assert_eq!(
+            new_env.evm_state().context.evm.env.block.number,
+            U256::from(10)
+        );
+    }
+
+    #[test]
+    fn test_deployed_contract() {
+        // Init code storing 3 in slot 2, with runtime code
+        // storing the call data in slot 1
+        let runtime = [0x60, 0x00, 0x35, 0x60, 0x01, 0x55, 0x00];
+        let mut init_code = vec![
+            0x60, 0x03, 0x60, 0x02, 0x55, 0x60, 0x07, 0x60, 0x11, 0x60, 0x00, 0x39, 0x60, 0x07,
+            0x60, 0x00, 0xf3,
+        ];
+        init_code.extend(runtime);
+
+        let mut env =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
+        let contract = env.deploy_contract(Address::ZERO, "test", init_code);
+        // Snapshot a layered env, base accounts should be included
+        let env = env.into_layered();
+
+        let bytes = Snapshot::from_env(&env).to_bytes().unwrap();
+        let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+
+        assert!(snapshot.db.accounts.contains_key(&contract));
+
+        let mut new_env =
+            Env::<LocalDB, RandomValidator>::from_snapshot(snapshot, RandomValidator {});
+        new_env
+            .direct_execute_raw(
+                Address::ZERO,
+                contract,
+                U256::from(9).to_be_bytes_vec(),
+                U256::ZERO,
+            )
+            .unwrap();
+
+        let db = &new_env.evm_state().context.evm.db;
+        assert_eq!(
+            db.storage_ref(contract, U256::from(1)).unwrap(),
+            U256::from(9)
+        );
+        assert_eq!(
+            db.storage_ref(contract, U256::from(2)).unwrap(),
+            U256::from(3)
+        );
+    }
+
+    #[test]
+    fn test_invalid_snapshot() {
+        assert!(matches!(
+            Snapshot::from_bytes(b"not a snapshot"),
+            Err(SnapshotError::InvalidFormat)
+        ));
+
+        let mut bytes = Vec::new();
+        bincode::serialize_into(
+            &mut bytes,
+            &Header {
+                magic: SNAPSHOT_MAGIC,
+                version: SNAPSHOT_VERSION + 1,
+            },
+        )
+        .unwrap();
+        assert!(matches!(
+            Snapshot::from_bytes(&bytes),
+            Err(SnapshotError::Version(_))
+        ));
+    }
+}
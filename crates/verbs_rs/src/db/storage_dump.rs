@@ -0,0 +1,224 @@
+//! Contract storage dumps
+//!
+//! Full storage of contracts, used to pre-load
+//! state into a [super::ForkDb] rather than
+//! requesting storage values one slot at a time.
+//! A dump is a JSON object mapping contract
+//! addresses to either:
+//!
+//! * A `debug_storageRangeAt` result, i.e. an
+//!   object with a `storage` field mapping hashed
+//!   keys to `key`/`value` pairs (keys must include
+//!   their preimage). Ranges must cover the full
+//!   storage of the contract, i.e. have no `nextKey`.
+//! * An object mapping storage slots to values.
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::{Address, U256};
+//! use verbs_rs::StorageDump;
+//!
+//! let dump = StorageDump::from_json(r#"{
+//!     "0x0101010101010101010101010101010101010101": {
+//!         "storage": {
+//!             "0xb10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6": {
+//!                 "key": "0x0000000000000000000000000000000000000000000000000000000000000001",
+//!                 "value": "0x0000000000000000000000000000000000000000000000000000000000000002"
+//!             }
+//!         },
+//!         "nextKey": null
+//!     },
+//!     "0x0202020202020202020202020202020202020202": {"0x1": "0x3"}
+//! }"#).unwrap();
+//!
+//! let storage = &dump.contracts[&Address::repeat_byte(1)];
+//! assert_eq!(storage[&U256::from(1)], U256::from(2));
+//! ```
+
+use alloy_primitives::{Address, B256, U256};
+use revm::primitives::HashMap;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Error raised reading a storage dump
+#[derive(Debug, thiserror::Error)]
+pub enum StorageDumpError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("storage dump of {0} is missing the preimage of key {1}")]
+    MissingPreimage(Address, B256),
+    #[error("storage dump of {0} is an incomplete storage range")]
+    IncompleteRange(Address),
+}
+
+/// Result of a `debug_storageRangeAt` request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StorageRange {
+    pub storage: HashMap<B256, StorageRangeEntry>,
+    pub next_key: Option<B256>,
+}
+
+/// Storage value in a [StorageRange]
+#[derive(Debug, Deserialize)]
+pub(crate) struct StorageRangeEntry {
+    pub key: Option<U256>,
+    pub value: U256,
+}
+
+impl StorageRange {
+    /// Insert values into a map of storage slots
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract
+    /// * `slots` - Storage slots of the contract
+    ///
+    pub fn insert_into(
+        self,
+        address: Address,
+        slots: &mut HashMap<U256, U256>,
+    ) -> Result<(), StorageDumpError> {
+        for (hashed_key, entry) in self.storage {
+            let key = entry
+                .key
+                .ok_or(StorageDumpError::MissingPreimage(address, hashed_key))?;
+            slots.insert(key, entry.value);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ContractDump {
+    Range(StorageRange),
+    Slots(HashMap<U256, U256>),
+}
+
+/// Full storage of a set of contracts
+#[derive(Debug, Clone, Default)]
+pub struct StorageDump {
+    /// Storage slots of each contract
+    pub contracts: HashMap<Address, HashMap<U256, U256>>,
+}
+
+impl StorageDump {
+    /// Parse a storage dump from JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - JSON storage dump
+    ///
+    pub fn from_json(json: &str) -> Result<Self, StorageDumpError> {
+        Self::from_dump(serde_json::from_str(json)?)
+    }
+
+    /// Read a storage dump
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader of a JSON storage dump
+    ///
+    pub fn read<R: Read>(reader: R) -> Result<Self, StorageDumpError> {
+        Self::from_dump(serde_json::from_reader(reader)?)
+    }
+
+    /// Load a storage dump from a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the storage dump file
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, StorageDumpError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    fn from_dump(dump: HashMap<Address, ContractDump>) -> Result<Self, StorageDumpError> {
+        let mut contracts = HashMap::new();
+        for (address, contract) in dump {
+            let slots = match contract {
+                ContractDump::Range(range) => {
+                    if range.next_key.is_some() {
+                        return Err(StorageDumpError::IncompleteRange(address));
+                    }
+                    let mut slots = HashMap::new();
+                    range.insert_into(address, &mut slots)?;
+                    slots
+                }
+                ContractDump::Slots(slots) => slots,
+            };
+            contracts.insert(address, slots);
+        }
+        Ok(StorageDump { contracts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_preimage() {
+        let dump = StorageDump::from_json(
+            r#"{
+                "0x0101010101010101010101010101010101010101": {
+                    "storage": {
+                        "0xb10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6": {
+                            "key": null,
+                            "value": "0x02"
+                        }
+                    },
+                    "nextKey": null
+                }
+            }"#,
+        );
+
+        assert!(matches!(
+            dump,
+            Err(StorageDumpError::MissingPreimage(address, _)) if address == Address::repeat_byte(1)
+        ));
+    }
+
+    #[test]
+    fn test_incomplete_range() {
+        let dump = StorageDump::from_json(
+            r#"{
+                "0x0101010101010101010101010101010101010101": {
+                    "storage": {
+                        "0xb10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6": {
+                            "key": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                            "value": "0x02"
+                        }
+                    },
+                    "nextKey": "0x0000000000000000000000000000000000000000000000000000000000000002"
+                }
+            }"#,
+        );
+
+        assert!(matches!(
+            dump,
+            Err(StorageDumpError::IncompleteRange(address)) if address == Address::repeat_byte(1)
+        ));
+    }
+
+    #[test]
+    fn test_slot_values() {
+        let dump = StorageDump::from_json(
+            r#"{
+                "0x0101010101010101010101010101010101010101": {"0x1": "0x2", "0xff": "0x0"}
+            }"#,
+        )
+        .unwrap();
+
+        let storage = &dump.contracts[&Address::repeat_byte(1)];
+        assert_eq!(storage.len(), 2);
+        assert_eq!(storage[&U256::from(1)], U256::from(2));
+        assert_eq!(storage[&U256::from(255)], U256::ZERO);
+    }
+}
@@ -1,20 +1,49 @@
 use super::error::DatabaseError;
+use super::local_db::LocalDB;
 use alloy_primitives::Address;
 use revm::{
-    db::{Database, DbAccount},
+    db::{Database, DatabaseRef, DbAccount},
     primitives::{AccountInfo, Bytecode, HashMap, Log, B256, U256},
     DatabaseCommit,
 };
+use std::borrow::Cow;
 
 /// Combined `Revm` database trait with data export methods
 ///
 /// Extends the [Database] and [DatabaseCommit] traits with
 /// methods to export the state of the DB. These methods
 /// allow the Db state to be exported from the Python API.
-pub trait DB: Database<Error = DatabaseError> + DatabaseCommit {
+///
+/// Implementers should also implement [DatabaseRef], which
+/// is used to make read-only calls against the DB (from
+/// multiple threads), without updating its state.
+pub trait DB:
+    Database<Error = DatabaseError> + DatabaseRef<Error = DatabaseError> + DatabaseCommit + Sync
+{
     fn insert_account_info(&mut self, address: Address, account_info: AccountInfo);
+    fn insert_account_storage(
+        &mut self,
+        address: Address,
+        slot: U256,
+        value: U256,
+    ) -> Result<(), DatabaseError>;
     fn accounts(&self) -> &HashMap<Address, DbAccount>;
     fn contracts(&self) -> &HashMap<B256, Bytecode>;
     fn logs(&self) -> &Vec<Log>;
     fn block_hashes(&self) -> &HashMap<U256, B256>;
+
+    /// Full state of the DB, used to export the DB
+    ///
+    /// By default copies the values returned by the
+    /// export methods above. DBs that store part of
+    /// their state elsewhere (e.g. the base DB of a
+    /// [super::LayeredDB]) should return the full state.
+    fn export_state(&self) -> Cow<'_, LocalDB> {
+        Cow::Owned(LocalDB {
+            accounts: self.accounts().clone(),
+            contracts: self.contracts().clone(),
+            logs: self.logs().clone(),
+            block_hashes: self.block_hashes().clone(),
+        })
+    }
 }
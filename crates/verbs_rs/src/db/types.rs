@@ -1,6 +1,10 @@
 use alloy_primitives::{Address, B256, U256 as AlloyU256};
 use ethers_core::types::{H160, H256, U256};
 use revm::primitives::AccountInfo;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
 
 pub trait ToAlloy {
     type To;
@@ -40,6 +44,15 @@ impl ToEthers for Address {
     }
 }
 
+impl ToEthers for AlloyU256 {
+    type To = U256;
+
+    #[inline(always)]
+    fn to_ethers(self) -> Self::To {
+        U256(self.into_limbs())
+    }
+}
+
 impl ToEthers for B256 {
     type To = H256;
 
@@ -57,12 +70,130 @@ impl ToEthers for B256 {
 /// simulation. This cache can be inserted into
 /// [super::LocalDB] in subsequent simulations
 /// to avoid the overhead of making remote
-/// requests.
+/// requests (see [super::LocalDB::from_request_cache]).
+///
+/// Caches can be saved to and loaded from JSON
+/// files, so a simulation can fork once and then
+/// run offline.
+///
+/// # Examples
 ///
-#[derive(Debug, Clone)]
+/// ```no_run
+/// use verbs_rs::env::{Env, RandomValidator};
+/// use verbs_rs::{ForkDb, LocalDB, RequestCache};
+///
+/// // Run against a fork and save the data it requested
+/// let env = Env::<ForkDb, RandomValidator>::init(
+///     "https://eth-mainnet.g.alchemy.com/v2/KEY", None, RandomValidator {}
+/// );
+/// // ...
+/// env.get_request_history().save("cache.json").unwrap();
+///
+/// // Later, initialise an offline simulation from the cache
+/// let cache = RequestCache::load("cache.json").unwrap();
+/// let env = Env::<LocalDB, RandomValidator>::from_request_cache(
+///     &cache, RandomValidator {}
+/// );
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestCache {
     pub start_timestamp: AlloyU256,
     pub start_block_number: AlloyU256,
     pub accounts: Vec<(Address, AccountInfo)>,
     pub storage: Vec<(Address, AlloyU256, AlloyU256)>,
 }
+
+/// Error raised reading or writing a request cache
+#[derive(Debug, thiserror::Error)]
+pub enum RequestCacheError {
+    #[error("failed to encode/decode request cache: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl RequestCache {
+    /// Write the cache as JSON
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), RequestCacheError> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Read a cache from JSON
+    pub fn read<R: Read>(reader: R) -> Result<Self, RequestCacheError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the cache to a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to create
+    ///
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RequestCacheError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load a cache from a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to read
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RequestCacheError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalDB;
+    use alloy_primitives::Bytes;
+    use revm::primitives::Bytecode;
+
+    #[test]
+    fn test_request_cache_round_trip() {
+        let contract = Address::repeat_byte(2);
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x00, 0x60, 0x00, 0xf3]));
+        let cache = RequestCache {
+            start_timestamp: AlloyU256::from(100),
+            start_block_number: AlloyU256::from(10),
+            accounts: vec![
+                (
+                    Address::repeat_byte(1),
+                    AccountInfo::from_balance(AlloyU256::from(1000)),
+                ),
+                (
+                    contract,
+                    AccountInfo::new(AlloyU256::ZERO, 1, code.hash_slow(), code.clone()),
+                ),
+            ],
+            storage: vec![(contract, AlloyU256::from(1), AlloyU256::from(2))],
+        };
+
+        let mut buffer = Vec::new();
+        cache.write(&mut buffer).unwrap();
+        let cache = RequestCache::read(buffer.as_slice()).unwrap();
+
+        assert_eq!(cache.start_block_number, AlloyU256::from(10));
+
+        let db = LocalDB::from_request_cache(&cache);
+
+        assert_eq!(
+            db.accounts[&Address::repeat_byte(1)].info.balance,
+            AlloyU256::from(1000)
+        );
+        assert_eq!(
+            db.accounts[&contract].storage[&AlloyU256::from(1)],
+            AlloyU256::from(2)
+        );
+        assert_eq!(
+            db.contracts[&code.hash_slow()].original_bytes(),
+            code.original_bytes()
+        );
+    }
+}
@@ -7,26 +7,46 @@
 //! process queues of transactions.
 //!
 
+mod serialize;
+mod tx_log;
 mod utils;
 mod validator;
+mod view;
 
-use crate::contract::{Event, Transaction};
+use crate::contract::{
+    ErrorRegistry, Event, MappingKey, StorageLayout, StorageLayoutError, StorageValue, Transaction,
+};
+use crate::state_diff::StateDiff;
 use crate::utils::Eth;
-use crate::{ForkDb, LocalDB, RequestCache, DB};
+use crate::{
+    DatabaseError, ForkBackend, ForkDb, ForkMode, GenesisState, LayeredDB, LocalDB, RequestCache,
+    Snapshot, StorageDump, DB,
+};
 use alloy_primitives::{Address, FixedBytes, B256, U256};
-use alloy_sol_types::SolCall;
+use alloy_sol_types::{SolCall, SolInterface};
 use log::debug;
 use rand::Rng;
-use revm::primitives::{AccountInfo, Bytecode, ExecutionResult, Log, ResultAndState, TxEnv};
-use revm::{ContextWithHandlerCfg, Evm, Handler};
-pub use utils::{decode_event, process_events, RevertError};
+use revm::primitives::{
+    AccountInfo, Bytecode, EVMError, EnvWithHandlerCfg, ExecutionResult, Log, ResultAndState, TxEnv,
+};
+use revm::{inspector_handle_register, Context, ContextWithHandlerCfg, Evm, Handler, Inspector};
+use std::collections::HashMap;
+use std::sync::Arc;
+pub use tx_log::{BlockRecord, TransactionLog, TransactionLogError};
+pub use utils::{decode_event, decode_revert_data, process_events, RevertError};
 pub use validator::{GasPriorityValidator, RandomValidator, Validator};
+pub use view::EnvView;
 
 /// Simulation environment
 ///
 /// Environment wrapping an in-memory EVM and
 /// functionality to update the state of the
 /// environment.
+///
+/// An environment can be cloned (if its DB and
+/// validator can be cloned), for example to run
+/// multiple simulations from the same initial state.
+#[derive(Clone)]
 pub struct Env<D: DB, V: Validator> {
     /// Local EVM state
     pub evm_state: Option<ContextWithHandlerCfg<(), D>>,
@@ -37,21 +57,35 @@ pub struct Env<D: DB, V: Validator> {
     pub event_history: Vec<Event>,
     /// Validator responsible for transaction ordering
     pub validator: V,
+    /// Log of processed transactions, if recording
+    pub transaction_log: Option<TransactionLog>,
+    /// Storage layouts of contracts, used to access
+    /// named state variables (not serialized with
+    /// the environment)
+    pub storage_layouts: HashMap<Address, StorageLayout>,
+    /// Custom errors used to decode reverts (not
+    /// serialized with the environment)
+    pub error_registry: ErrorRegistry,
 }
 
 /// EVM update methods
 trait CallEVM {
     /// Execute a transaction, and update the EVM state
     fn execute(&mut self, tx: TxEnv) -> ExecutionResult;
+    /// Execute a transaction, returning any EVM error
+    fn try_execute(&mut self, tx: TxEnv) -> Result<ExecutionResult, EVMError<DatabaseError>>;
     /// Execute a transaction without updating the EVM
     fn call(&mut self, tx: TxEnv) -> ResultAndState;
 }
 
-impl<'a, D: DB> CallEVM for Evm<'a, (), D> {
-    fn execute(&mut self, tx: TxEnv) -> ExecutionResult {
+impl<'a, EXT, D: DB> CallEVM for Evm<'a, EXT, D> {
+    fn try_execute(&mut self, tx: TxEnv) -> Result<ExecutionResult, EVMError<DatabaseError>> {
         self.context.evm.env.tx = tx;
+        self.transact_commit()
+    }
 
-        match self.transact_commit() {
+    fn execute(&mut self, tx: TxEnv) -> ExecutionResult {
+        match self.try_execute(tx) {
             Ok(val) => val,
             Err(e) => match e {
                 revm::primitives::EVMError::Transaction(t) => {
@@ -110,12 +144,50 @@ impl<V: Validator> Env<ForkDb, V> {
     ///    latest available block will be used.
     ///
     pub fn init(node_url: &str, block_number: Option<u64>, validator: V) -> Self {
-        let db = ForkDb::new(node_url, block_number);
-        let timestamp = db.block.timestamp.as_u128();
-        let block_number = match db.block.number {
-            Some(n) => U256::try_from(n.as_u64()).unwrap(),
-            None => U256::ZERO,
-        };
+        Self::init_with_mode(node_url, block_number, ForkMode::Live, validator)
+    }
+
+    /// Initialise an environment with a forked DB in a given mode
+    ///
+    /// Initialise a simulation environment with a
+    /// forked database that records requests to, or
+    /// replays requests from, a local cassette file
+    /// (see [ForkMode]).
+    ///
+    /// # Arguments
+    ///
+    /// * `node_url` - Url of service to make db requests,
+    ///   unused in [ForkMode::Replay] mode.
+    /// * `block_number` - Block number to fork from, if None
+    ///   latest available block will be used.
+    /// * `mode` - Fork data source mode
+    ///
+    pub fn init_with_mode(
+        node_url: &str,
+        block_number: Option<u64>,
+        mode: ForkMode,
+        validator: V,
+    ) -> Self {
+        Self::from_fork_db(ForkDb::with_mode(node_url, block_number, mode), validator)
+    }
+
+    /// Initialise an environment with a fork backend
+    ///
+    /// Initialise a simulation environment with a
+    /// forked database that loads values from
+    /// a [ForkBackend], e.g. a cached file.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Source of fork values
+    ///
+    pub fn init_with_backend(backend: Arc<dyn ForkBackend>, validator: V) -> Self {
+        Self::from_fork_db(ForkDb::from_backend(backend), validator)
+    }
+
+    fn from_fork_db(db: ForkDb, validator: V) -> Self {
+        let timestamp = db.block.timestamp;
+        let block_number = db.block.number;
 
         let evm = Evm::builder()
             .with_db(db)
@@ -125,7 +197,7 @@ impl<V: Validator> Env<ForkDb, V> {
             })
             .modify_block_env(|block| {
                 block.gas_limit = U256::MAX;
-                block.timestamp = U256::try_from(timestamp).unwrap();
+                block.timestamp = timestamp;
                 block.number = block_number;
             })
             .build();
@@ -137,6 +209,9 @@ impl<V: Validator> Env<ForkDb, V> {
             last_events: Vec::new(),
             event_history: Vec::new(),
             validator,
+            transaction_log: None,
+            storage_layouts: HashMap::new(),
+            error_registry: ErrorRegistry::new(),
         }
     }
 
@@ -152,6 +227,60 @@ impl<V: Validator> Env<ForkDb, V> {
             None => panic!("No EVM state set"),
         }
     }
+
+    /// Load accounts from the remote fork
+    ///
+    /// Loads accounts in concurrent batches of
+    /// requests (see [ForkDb::prefetch_accounts]),
+    /// to avoid the overhead of loading accounts
+    /// one at a time during a simulation.
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses` - Addresses of accounts to load
+    ///
+    pub fn prefetch_accounts(&mut self, addresses: &[Address]) -> Result<(), DatabaseError> {
+        self.evm_state().context.evm.db.prefetch_accounts(addresses)
+    }
+
+    /// Load storage values from the remote fork
+    ///
+    /// Loads storage values in concurrent batches of
+    /// requests (see [ForkDb::prefetch_storage]).
+    ///
+    /// # Arguments
+    ///
+    /// * `slots` - Contract addresses and storage indices
+    ///   of the values to load
+    ///
+    pub fn prefetch_storage(&mut self, slots: &[(Address, U256)]) -> Result<(), DatabaseError> {
+        self.evm_state().context.evm.db.prefetch_storage(slots)
+    }
+
+    /// Load the full storage of contracts from the remote fork
+    ///
+    /// Loads storage in bulk where supported by the
+    /// fork backend (see [ForkDb::load_full_storage]).
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses` - Addresses of the contracts
+    ///
+    pub fn load_full_storage(&mut self, addresses: &[Address]) -> Result<(), DatabaseError> {
+        self.evm_state().context.evm.db.load_full_storage(addresses)
+    }
+
+    /// Import the full storage of contracts from a dump
+    ///
+    /// See [ForkDb::import_storage_dump].
+    ///
+    /// # Arguments
+    ///
+    /// * `dump` - Full storage of a set of contracts
+    ///
+    pub fn import_storage_dump(&mut self, dump: &StorageDump) -> Result<(), DatabaseError> {
+        self.evm_state().context.evm.db.import_storage_dump(dump)
+    }
 }
 
 impl<V: Validator> Env<LocalDB, V> {
@@ -188,12 +317,89 @@ impl<V: Validator> Env<LocalDB, V> {
             last_events: Vec::new(),
             event_history: Vec::new(),
             validator,
+            transaction_log: None,
+            storage_layouts: HashMap::new(),
+            error_registry: ErrorRegistry::new(),
         };
 
         env.insert_account(Address::ZERO, start_balance);
 
         env
     }
+
+    /// Initialise a simulation from a snapshot
+    ///
+    /// Initialises a simulation environment with the
+    /// DB state and block environment of a [Snapshot].
+    ///
+    /// # Arguments
+    ///
+    /// - `snapshot` - Snapshot of a DB and block
+    /// - `validator` - Simulation validator
+    ///
+    pub fn from_snapshot(snapshot: Snapshot, validator: V) -> Self {
+        let Snapshot { block, db } = snapshot;
+
+        let evm = Evm::builder()
+            .with_db(db)
+            .modify_cfg_env(|cfg| {
+                cfg.limit_contract_code_size = Some(0x1000000);
+                cfg.disable_eip3607 = true;
+            })
+            .with_block_env(block)
+            .build();
+
+        Self {
+            evm_state: Some(evm.into_context_with_handler_cfg()),
+            last_events: Vec::new(),
+            event_history: Vec::new(),
+            validator,
+            transaction_log: None,
+            storage_layouts: HashMap::new(),
+            error_registry: ErrorRegistry::new(),
+        }
+    }
+
+    /// Initialise a simulation from a cache of fork requests
+    ///
+    /// Initialises a simulation environment with an
+    /// in-memory DB populated with the accounts and
+    /// storage requested by a forked simulation, at
+    /// the block the fork started from.
+    ///
+    /// # Arguments
+    ///
+    /// - `cache` - Requests made by a [ForkDb]
+    /// - `validator` - Simulation validator
+    ///
+    pub fn from_request_cache(cache: &RequestCache, validator: V) -> Self {
+        let mut env = Self::init(cache.start_timestamp, cache.start_block_number, validator);
+        env.evm_state().context.evm.db.insert_request_cache(cache);
+        env
+    }
+
+    /// Initialise a simulation from a genesis file or state dump
+    ///
+    /// Initialises a simulation environment with an
+    /// in-memory DB populated with the accounts of a
+    /// geth genesis file or anvil/hardhat state dump.
+    /// The block number and timestamp default to zero
+    /// if they are not included in the file.
+    ///
+    /// # Arguments
+    ///
+    /// - `genesis` - Accounts and block of the initial state
+    /// - `validator` - Simulation validator
+    ///
+    pub fn from_genesis(genesis: &GenesisState, validator: V) -> Self {
+        let mut env = Self::init(
+            genesis.timestamp.unwrap_or_default(),
+            genesis.number.unwrap_or_default(),
+            validator,
+        );
+        env.evm_state().context.evm.db.insert_genesis(genesis);
+        env
+    }
 }
 
 impl<D: DB, V: Validator> Env<D, V> {
@@ -220,6 +426,218 @@ impl<D: DB, V: Validator> Env<D, V> {
         }
     }
 
+    /// Convert into an environment with a layered DB
+    ///
+    /// Moves the DB into a shared base of a
+    /// [LayeredDB], keeping the block and EVM
+    /// configuration of the environment. The
+    /// returned environment can then be cheaply
+    /// cloned to run simulations from the same
+    /// initial state (e.g. using
+    /// [crate::batch_runner::batch_run]). Values a
+    /// [ForkDb] base loads from its fork are cached
+    /// in the shared base, so are only requested once.
+    pub fn into_layered(mut self) -> Env<LayeredDB<D>, V>
+    where
+        D: Send,
+    {
+        let ContextWithHandlerCfg { context, cfg } = match self.evm_state.take() {
+            Some(s) => s,
+            None => panic!("No EVM state set (this should not happen!)"),
+        };
+        let evm_context = context.evm.inner;
+
+        let evm = Evm::builder()
+            .with_db(LayeredDB::new(Arc::new(evm_context.db)))
+            .with_env_with_handler_cfg(EnvWithHandlerCfg::new(evm_context.env, cfg))
+            .build();
+
+        Env {
+            evm_state: Some(evm.into_context_with_handler_cfg()),
+            last_events: self.last_events,
+            event_history: self.event_history,
+            validator: self.validator,
+            transaction_log: self.transaction_log,
+            storage_layouts: self.storage_layouts,
+            error_registry: self.error_registry,
+        }
+    }
+
+    /// Register the storage layout of a contract
+    ///
+    /// Allows state variables of the contract to
+    /// be read and written by name (e.g. using
+    /// [Env::read_var]).
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract
+    /// * `layout` - Solc storage layout of the contract
+    ///
+    pub fn register_storage_layout(&mut self, address: Address, layout: StorageLayout) {
+        self.storage_layouts.insert(address, layout);
+    }
+
+    /// Register the custom errors of a contract
+    ///
+    /// Registered errors are used to decode the
+    /// messages of reverted calls, see [ErrorRegistry].
+    ///
+    /// # Arguments
+    ///
+    /// * `E` - Errors enum generated by the
+    ///   [alloy_sol_types::sol] macro
+    ///
+    pub fn register_errors<E: SolInterface + std::fmt::Debug>(&mut self) {
+        self.error_registry.register::<E>();
+    }
+
+    fn layout_and_db(
+        &mut self,
+        address: Address,
+    ) -> Result<(&StorageLayout, &mut D), StorageLayoutError> {
+        let layout = self
+            .storage_layouts
+            .get(&address)
+            .ok_or(StorageLayoutError::NoLayout(address))?;
+        match &mut self.evm_state {
+            Some(e) => Ok((layout, &mut e.context.evm.db)),
+            None => panic!("No EVM state set (this should not happen!)"),
+        }
+    }
+
+    /// Read a contract state variable
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract, with a
+    ///   registered storage layout
+    /// * `path` - Path to the value, e.g. `reserves`
+    ///   or `reserves.reserve0`
+    ///
+    pub fn read_var(
+        &mut self,
+        address: Address,
+        path: &str,
+    ) -> Result<StorageValue, StorageLayoutError> {
+        self.read_storage(address, path, &[])
+    }
+
+    /// Read a value from a contract mapping
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract, with a
+    ///   registered storage layout
+    /// * `path` - Path to the mapping, e.g. `balanceOf`
+    /// * `key` - Mapping key
+    ///
+    pub fn read_mapping<K: Into<MappingKey>>(
+        &mut self,
+        address: Address,
+        path: &str,
+        key: K,
+    ) -> Result<StorageValue, StorageLayoutError> {
+        self.read_storage(address, path, &[key.into()])
+    }
+
+    /// Read a value from contract storage
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract, with a
+    ///   registered storage layout
+    /// * `path` - Path to the value
+    /// * `keys` - Keys of (possibly nested) mappings
+    ///   along the path
+    ///
+    pub fn read_storage(
+        &mut self,
+        address: Address,
+        path: &str,
+        keys: &[MappingKey],
+    ) -> Result<StorageValue, StorageLayoutError> {
+        let (layout, db) = self.layout_and_db(address)?;
+        layout.read(db, address, path, keys)
+    }
+
+    /// Write a contract state variable
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract, with a
+    ///   registered storage layout
+    /// * `path` - Path to the value
+    /// * `value` - New value
+    ///
+    pub fn write_var(
+        &mut self,
+        address: Address,
+        path: &str,
+        value: &StorageValue,
+    ) -> Result<(), StorageLayoutError> {
+        self.write_storage(address, path, &[], value)
+    }
+
+    /// Write a value in a contract mapping
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract, with a
+    ///   registered storage layout
+    /// * `path` - Path to the mapping
+    /// * `key` - Mapping key
+    /// * `value` - New value
+    ///
+    pub fn write_mapping<K: Into<MappingKey>>(
+        &mut self,
+        address: Address,
+        path: &str,
+        key: K,
+        value: &StorageValue,
+    ) -> Result<(), StorageLayoutError> {
+        self.write_storage(address, path, &[key.into()], value)
+    }
+
+    /// Write a value to contract storage
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address of the contract, with a
+    ///   registered storage layout
+    /// * `path` - Path to the value
+    /// * `keys` - Keys of (possibly nested) mappings
+    ///   along the path
+    /// * `value` - New value
+    ///
+    pub fn write_storage(
+        &mut self,
+        address: Address,
+        path: &str,
+        keys: &[MappingKey],
+        value: &StorageValue,
+    ) -> Result<(), StorageLayoutError> {
+        let (layout, db) = self.layout_and_db(address)?;
+        layout.write(db, address, path, keys, value)
+    }
+
+    /// Get a read-only view of the environment
+    ///
+    /// The view can be used to call contracts without
+    /// updating the state of the EVM, and can be shared
+    /// between threads.
+    pub fn view(&self) -> EnvView<'_, D> {
+        match &self.evm_state {
+            Some(e) => EnvView::new(
+                &e.context.evm.db,
+                &e.context.evm.env,
+                e.cfg,
+                &self.error_registry,
+            ),
+            None => panic!("No EVM state set (this should not happen!)"),
+        }
+    }
+
     /// Increment block number, time and prevarando
     pub fn increment_time<R: Rng>(&mut self, rng: &mut R, interval: u64) {
         let state = self.evm_state();
@@ -285,6 +703,116 @@ impl<D: DB, V: Validator> Env<D, V> {
         deploy_address
     }
 
+    /// Place runtime code at an address
+    ///
+    /// Sets the code of an account directly, without
+    /// running a constructor (similar to `anvil_setCode`),
+    /// for example to place canonical contracts at their
+    /// known addresses. The balance and nonce of an existing
+    /// account are kept.
+    ///
+    /// # Arguments
+    ///
+    /// - `address` - Address to place the code at
+    /// - `code` - Contract runtime bytecode
+    ///
+    pub fn insert_code(&mut self, address: Address, code: Vec<u8>) {
+        let db = &mut self.evm_state().context.evm.db;
+        let mut info = revm::Database::basic(db, address)
+            .unwrap_or_else(|e| panic!("Failed to load account {}: {}", address, e))
+            .unwrap_or_default();
+        let code = Bytecode::new_raw(code.into());
+        info.code_hash = code.hash_slow();
+        info.code = Some(code);
+        db.insert_account_info(address, info);
+    }
+
+    /// Deploy a contract to a given address
+    ///
+    /// Runs the contract constructor as if the contract
+    /// were deployed at `address`, i.e. storage written
+    /// by the constructor, and immutable values referencing
+    /// the contract address, are set at the target address.
+    /// Note that the code size of the target address is
+    /// not zero during construction.
+    ///
+    /// # Arguments
+    ///
+    /// - `deployer` - Address of contract deployer
+    /// - `contract_name` - Name of the contract, only used for
+    ///   error messaging
+    /// - `data` - Deployment bytecode, and abi encoded arguments
+    ///   if required
+    /// - `address` - Address to deploy the contract to
+    ///
+    pub fn deploy_contract_at(
+        &mut self,
+        deployer: Address,
+        contract_name: &str,
+        data: Vec<u8>,
+        address: Address,
+    ) -> Address {
+        self.insert_code(address, data);
+        let tx = utils::init_call_transaction(deployer, address, Vec::new(), U256::ZERO);
+        let mut evm = self.evm();
+        let result = evm.execute(tx);
+        self.evm_state = Some(evm.into_context_with_handler_cfg());
+        let code = match utils::deployment_output(contract_name, result) {
+            revm::primitives::Output::Call(code) => code,
+            _ => panic!("Deployment of {} failed", contract_name),
+        };
+        self.insert_code(address, code.to_vec());
+        debug!("Deployed {} to {}", contract_name, address);
+        address
+    }
+
+    /// Deploy a contract using CREATE2
+    ///
+    /// Deploys the contract from the deterministic
+    /// deployment proxy ([crate::utils::CREATE2_DEPLOYER]),
+    /// placing the proxy at its canonical address if it
+    /// has not been deployed. The address of the contract
+    /// can be computed in advance with
+    /// [crate::utils::create2_address].
+    ///
+    /// # Arguments
+    ///
+    /// - `deployer` - Address sending the deployment transaction
+    /// - `contract_name` - Name of the contract, only used for
+    ///   error messaging
+    /// - `salt` - CREATE2 salt
+    /// - `data` - Deployment bytecode, and abi encoded arguments
+    ///   if required
+    ///
+    pub fn deploy_contract_create2(
+        &mut self,
+        deployer: Address,
+        contract_name: &str,
+        salt: B256,
+        data: Vec<u8>,
+    ) -> Address {
+        let proxy = crate::utils::CREATE2_DEPLOYER;
+        let proxy_deployed = revm::Database::basic(&mut self.evm_state().context.evm.db, proxy)
+            .unwrap_or_else(|e| panic!("Failed to load account {}: {}", proxy, e))
+            .is_some_and(|info| !info.is_empty_code_hash());
+        if !proxy_deployed {
+            self.insert_code(proxy, crate::utils::CREATE2_DEPLOYER_CODE.to_vec());
+        }
+
+        let address = crate::utils::create2_address(proxy, salt, &data);
+        let call_data = [salt.as_slice(), data.as_slice()].concat();
+        let tx = utils::init_call_transaction(deployer, proxy, call_data, U256::ZERO);
+        let mut evm = self.evm();
+        let result = evm.execute(tx);
+        self.evm_state = Some(evm.into_context_with_handler_cfg());
+        match utils::deployment_output(contract_name, result) {
+            revm::primitives::Output::Call(output) if output.as_ref() == address.as_slice() => {}
+            _ => panic!("Deployment of {} failed", contract_name),
+        }
+        debug!("Deployed {} to {}", contract_name, address);
+        address
+    }
+
     /// Execute a contract function with ABI encoded arguments
     ///
     /// # Arguments
@@ -307,6 +835,7 @@ impl<D: DB, V: Validator> Env<D, V> {
         let execution_result = evm.execute(tx);
         self.evm_state = Some(evm.into_context_with_handler_cfg());
         utils::result_to_raw_output(callee, execution_result)
+            .map_err(|e| e.decode_with(&self.error_registry))
     }
 
     /// Execute a contract function for a specific ABI
@@ -330,14 +859,15 @@ impl<D: DB, V: Validator> Env<D, V> {
         let tx = utils::init_call_transaction(callee, contract, call_args, value);
         let mut evm = self.evm();
         let execution_result = evm.execute(tx);
-        let (output, events) = utils::result_to_output(function_name, callee, execution_result)?;
+        self.evm_state = Some(evm.into_context_with_handler_cfg());
+        let (output, events) = utils::result_to_output(function_name, callee, execution_result)
+            .map_err(|e| e.decode_with(&self.error_registry))?;
         let output_data = output.into_data();
         let decoded = T::abi_decode_returns(&output_data, true);
         let decoded = match decoded {
             Ok(x) => x,
             Err(e) => panic!("Decoding error from {} {:?}", function_name, e),
         };
-        self.evm_state = Some(evm.into_context_with_handler_cfg());
         Ok((decoded, events))
     }
 
@@ -361,6 +891,7 @@ impl<D: DB, V: Validator> Env<D, V> {
         let result = evm.call(tx);
         self.evm_state = Some(evm.into_context_with_handler_cfg());
         utils::result_to_raw_output(callee, result.result)
+            .map_err(|e| e.decode_with(&self.error_registry))
     }
 
     /// Call a contract function without committing changes
@@ -384,15 +915,16 @@ impl<D: DB, V: Validator> Env<D, V> {
         let tx = utils::init_call_transaction(callee, contract, call_args, value);
         let mut evm = self.evm();
         let execution_result = evm.call(tx);
+        self.evm_state = Some(evm.into_context_with_handler_cfg());
         let (output, events) =
-            utils::result_to_output(function_name, callee, execution_result.result)?;
+            utils::result_to_output(function_name, callee, execution_result.result)
+                .map_err(|e| e.decode_with(&self.error_registry))?;
         let output_data = output.into_data();
         let decoded = T::abi_decode_returns(&output_data, true);
         let decoded = match decoded {
             Ok(x) => x,
             Err(_) => panic!("Decoding error from {}", function_name),
         };
-        self.evm_state = Some(evm.into_context_with_handler_cfg());
         Ok((decoded, events))
     }
 
@@ -408,8 +940,8 @@ impl<D: DB, V: Validator> Env<D, V> {
     /// - `step` - Simulation step number
     /// - `sequence` - Ordering of this transaction in the queue
     ///
-    fn call_from_transaction(
-        evm: &mut Evm<'_, (), D>,
+    fn call_from_transaction<EXT>(
+        evm: &mut Evm<'_, EXT, D>,
         last_events: &mut Vec<Event>,
         transaction: Transaction,
         step: usize,
@@ -421,18 +953,36 @@ impl<D: DB, V: Validator> Env<D, V> {
         );
         let function_selector = transaction.function_selector;
         let check_call = transaction.checked;
+        let value = transaction.value;
         let tx = utils::init_call_transaction(
             transaction.callee,
             transaction.transact_to,
             transaction.args,
             transaction.value,
         );
-        let execution_result = evm.execute(tx);
+        let execution_result = match evm.try_execute(tx) {
+            Ok(execution_result) => execution_result,
+            Err(EVMError::Transaction(e)) if !check_call => {
+                debug!("Invalid transaction {:?}: {:?}", function_selector, e);
+                last_events.push(Event {
+                    success: false,
+                    function_selector,
+                    logs: Vec::default(),
+                    step,
+                    sequence,
+                    gas_used: 0,
+                    value,
+                });
+                return;
+            }
+            Err(e) => panic!("Call failed: {:?}", e),
+        };
         let result = utils::result_to_output_with_events(
             step,
             sequence,
             function_selector,
             transaction.callee,
+            value,
             execution_result,
             check_call,
         );
@@ -453,6 +1003,14 @@ impl<D: DB, V: Validator> Env<D, V> {
     ) {
         let transactions = self.validator.order_transactions(rng, transactions);
 
+        if let (Some(log), Some(state)) = (&mut self.transaction_log, &self.evm_state) {
+            log.push(
+                step,
+                state.context.evm.env.block.clone(),
+                transactions.clone(),
+            );
+        }
+
         let mut evm = self.evm();
         let mut events = Vec::<Event>::new();
 
@@ -463,6 +1021,31 @@ impl<D: DB, V: Validator> Env<D, V> {
         self.last_events.extend(events);
     }
 
+    /// Process a queue of [Transaction] and return the state changes
+    ///
+    /// Processes transactions as [Env::process_transactions],
+    /// returning the changes to the DB made by the block.
+    /// Note that this copies the DB before processing
+    /// the transactions.
+    ///
+    /// # Arguments
+    /// - `transactions` - Vector of transactions
+    /// - `step` - Step number of the simulation
+    ///
+    pub fn process_transactions_with_diff<R: Rng>(
+        &mut self,
+        transactions: Vec<Transaction>,
+        rng: &mut R,
+        step: usize,
+    ) -> Result<StateDiff, DatabaseError>
+    where
+        D: Clone,
+    {
+        let before = self.evm_state().context.evm.db.clone();
+        self.process_transactions(transactions, rng, step);
+        StateDiff::new(&before, &self.evm_state().context.evm.db)
+    }
+
     /// Store events from the last block
     ///
     /// Move events generated in the last block
@@ -471,6 +1054,114 @@ impl<D: DB, V: Validator> Env<D, V> {
     pub fn clear_events(&mut self) {
         self.event_history.append(&mut self.last_events);
     }
+
+    /// Start recording processed transactions
+    ///
+    /// Transactions processed by [Env::process_transactions]
+    /// will be added to the [Env::transaction_log], along
+    /// with the block environment they were executed in.
+    /// Any existing log is discarded.
+    pub fn record_transactions(&mut self) {
+        self.transaction_log = Some(TransactionLog::new());
+    }
+
+    /// Stop recording transactions and return the log
+    pub fn take_transaction_log(&mut self) -> Option<TransactionLog> {
+        self.transaction_log.take()
+    }
+
+    /// Re-execute a log of transactions
+    ///
+    /// Replays the blocks of a [TransactionLog] in order,
+    /// setting the block environment and executing the
+    /// transactions in their recorded order (i.e. without
+    /// the validator re-ordering them). Events are
+    /// generated as during a simulation, so starting
+    /// from the same initial state the replay will
+    /// reproduce the state and events of the original
+    /// simulation.
+    ///
+    /// # Arguments
+    ///
+    /// * `log` - Log of transactions to replay
+    ///
+    pub fn replay(&mut self, log: &TransactionLog) {
+        self.clear_events();
+
+        let mut evm = self.evm();
+        let mut events = Vec::<Event>::new();
+
+        for block in log.blocks.iter() {
+            Self::replay_block(&mut evm, &mut events, block);
+        }
+
+        self.evm_state = Some(evm.into_context_with_handler_cfg());
+        self.event_history.extend(events);
+    }
+
+    /// Re-execute a log of transactions with an inspector
+    ///
+    /// Replays a log of transactions as [Env::replay], but
+    /// with a revm [Inspector] attached to the EVM, e.g.
+    /// to trace the execution of the transactions.
+    ///
+    /// # Arguments
+    ///
+    /// * `log` - Log of transactions to replay
+    /// * `inspector` - EVM inspector
+    ///
+    /// Returns the inspector after the replay is complete.
+    ///
+    pub fn replay_with_inspector<I: Inspector<D>>(
+        &mut self,
+        log: &TransactionLog,
+        inspector: I,
+    ) -> I {
+        self.clear_events();
+
+        let ContextWithHandlerCfg { context, cfg } = match self.evm_state.take() {
+            Some(s) => s,
+            None => panic!("No EVM state set (this should not happen!)"),
+        };
+
+        let mut evm = Evm::builder()
+            .with_context_with_handler_cfg(ContextWithHandlerCfg {
+                context: Context {
+                    evm: context.evm,
+                    external: inspector,
+                },
+                cfg,
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+
+        let mut events = Vec::<Event>::new();
+
+        for block in log.blocks.iter() {
+            Self::replay_block(&mut evm, &mut events, block);
+        }
+
+        let ContextWithHandlerCfg { context, cfg } = evm.into_context_with_handler_cfg();
+        self.evm_state = Some(ContextWithHandlerCfg {
+            context: Context {
+                evm: context.evm,
+                external: (),
+            },
+            cfg,
+        });
+        self.event_history.extend(events);
+
+        context.external
+    }
+
+    /// Execute the transactions of a recorded block
+    fn replay_block<EXT>(evm: &mut Evm<'_, EXT, D>, events: &mut Vec<Event>, block: &BlockRecord) {
+        evm.context.evm.env.block = block.block.clone();
+
+        for (i, call) in block.transactions.iter().enumerate() {
+            Self::call_from_transaction(evm, events, call.clone(), block.step, i);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -527,11 +1218,8 @@ mod tests {
         ]"#
     );
 
-    #[fixture]
-    fn deployment() -> (Env<LocalDB, RandomValidator>, Address, Address) {
-        let mut network =
-            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
-
+    /// Deployment data of the test contract, with value 101
+    fn test_contract_data() -> Vec<u8> {
         let constructor_args = <i128>::abi_encode(&101);
         let bytecode_hex = "608060405234801561001057600080fd5b50\
         6040516102063803806102068339818101604052810190610032919061007a\
@@ -552,12 +1240,20 @@ mod tests {
         9f1c4e30ebbb603943f8e1e44a3b4c0c10c3ea53799a236d64736f6c634300\
         080a0033";
 
+        let mut bytecode: Vec<u8> = utils::data_bytes_from_hex(bytecode_hex);
+        bytecode.extend(constructor_args);
+        bytecode
+    }
+
+    #[fixture]
+    fn deployment() -> (Env<LocalDB, RandomValidator>, Address, Address) {
+        let mut network =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
+
         let user_address = Address::from(Uint::from(999));
         network.insert_account(user_address, Eth::to_weth(100));
 
-        let mut bytecode: Vec<u8> = utils::data_bytes_from_hex(bytecode_hex);
-        bytecode.extend(constructor_args);
-        let contract_address = network.deploy_contract(user_address, "test", bytecode);
+        let contract_address = network.deploy_contract(user_address, "test", test_contract_data());
 
         (network, contract_address, user_address)
     }
@@ -598,6 +1294,189 @@ mod tests {
         assert_eq!(v._0.as_i64(), 1i64);
     }
 
+    #[rstest]
+    fn deploy_at_address(deployment: (Env<LocalDB, RandomValidator>, Address, Address)) {
+        let (mut network, contract_address, user_address) = deployment;
+        let target = Address::repeat_byte(0xaa);
+
+        let address =
+            network.deploy_contract_at(user_address, "test", test_contract_data(), target);
+        assert_eq!(address, target);
+
+        let (v, _) = network
+            .direct_call(
+                user_address,
+                target,
+                TestContract::getValueCall {},
+                U256::ZERO,
+            )
+            .unwrap();
+        assert_eq!(v._0.as_i64(), 101i64);
+
+        // Runtime code placed at an address shares the
+        // code of the deployed contract
+        let code = network
+            .view()
+            .db()
+            .accounts()
+            .get(&contract_address)
+            .unwrap()
+            .info
+            .code
+            .clone()
+            .unwrap();
+        let copy = Address::repeat_byte(0xbb);
+        network.insert_code(copy, code.original_bytes().to_vec());
+        let view = network.view();
+        let db = view.db();
+        assert_eq!(
+            db.accounts().get(&copy).unwrap().info.code_hash,
+            db.accounts().get(&target).unwrap().info.code_hash
+        );
+    }
+
+    #[rstest]
+    fn deploy_create2(deployment: (Env<LocalDB, RandomValidator>, Address, Address)) {
+        let (mut network, _, user_address) = deployment;
+        let salt = B256::repeat_byte(1);
+        let expected = utils::create2_address(utils::CREATE2_DEPLOYER, salt, &test_contract_data());
+
+        let address =
+            network.deploy_contract_create2(user_address, "test", salt, test_contract_data());
+        assert_eq!(address, expected);
+
+        let (v, _) = network
+            .direct_call(
+                user_address,
+                address,
+                TestContract::getValueCall {},
+                U256::ZERO,
+            )
+            .unwrap();
+        assert_eq!(v._0.as_i64(), 101i64);
+
+        // A different salt deploys to a new address
+        let other = network.deploy_contract_create2(
+            user_address,
+            "test",
+            B256::repeat_byte(2),
+            test_contract_data(),
+        );
+        assert_ne!(other, address);
+    }
+
+    #[test]
+    fn revert_errors() {
+        sol! {
+            #![sol(all_derives)]
+            contract Pool {
+                error InsufficientLiquidity(uint256 available);
+                function swap(uint256 amount) external;
+            }
+        }
+
+        let mut network =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
+        // Runtime code reverting with its call data
+        let contract = Address::repeat_byte(1);
+        network.insert_code(
+            contract,
+            vec![0x36, 0x60, 0x00, 0x60, 0x00, 0x37, 0x36, 0x60, 0x00, 0xfd],
+        );
+
+        let panic =
+            alloy_sol_types::SolError::abi_encode(&alloy_sol_types::Panic::from(U256::from(0x11)));
+        let err = network
+            .direct_call_raw(Address::ZERO, contract, panic, U256::ZERO)
+            .unwrap_err();
+        assert_eq!(
+            err.output.unwrap(),
+            "panic: arithmetic underflow or overflow (0x11)"
+        );
+
+        let error = Pool::InsufficientLiquidity {
+            available: U256::from(5),
+        };
+        let data = alloy_sol_types::SolError::abi_encode(&error);
+
+        let err = network
+            .direct_call_raw(Address::ZERO, contract, data.clone(), U256::ZERO)
+            .unwrap_err();
+        assert_eq!(err.data.to_vec(), data);
+        assert_eq!(
+            err.output.as_deref(),
+            Some(format!("custom error 0x{}", hex::encode(&data[..4])).as_str())
+        );
+        assert!(matches!(
+            err.decode::<Pool::PoolErrors>(),
+            Some(Pool::PoolErrors::InsufficientLiquidity(e)) if e.available == U256::from(5)
+        ));
+
+        network.register_errors::<Pool::PoolErrors>();
+        let err = network
+            .view()
+            .direct_call_raw(Address::ZERO, contract, data.clone(), U256::ZERO)
+            .unwrap_err();
+        assert_eq!(
+            err.output.unwrap(),
+            format!("{:?}", Pool::PoolErrors::InsufficientLiquidity(error))
+        );
+
+        // The environment can still be used after a reverted execution
+        let err = network
+            .direct_execute(
+                Address::ZERO,
+                contract,
+                Pool::swapCall { amount: U256::ZERO },
+                U256::ZERO,
+            )
+            .err()
+            .unwrap();
+        assert_eq!(err.selector(), Some(Pool::swapCall::SELECTOR));
+        assert!(network
+            .direct_execute_raw(Address::ZERO, contract, data, U256::ZERO)
+            .is_err());
+    }
+
+    #[rstest]
+    fn view_call(deployment: (Env<LocalDB, RandomValidator>, Address, Address)) {
+        let (network, contract_address, user_address) = deployment;
+
+        let view = network.view();
+
+        let (v, _) = view
+            .direct_call(
+                user_address,
+                contract_address,
+                TestContract::getValueCall {},
+                U256::ZERO,
+            )
+            .unwrap();
+
+        assert_eq!(v._0.as_i64(), 101i64);
+
+        // Calls against the view do not update the DB
+        let _ = view
+            .direct_call(
+                user_address,
+                contract_address,
+                TestContract::setValueCall { x: Signed::ONE },
+                U256::ZERO,
+            )
+            .unwrap();
+
+        let (v, _) = view
+            .direct_call(
+                user_address,
+                contract_address,
+                TestContract::getValueCall {},
+                U256::ZERO,
+            )
+            .unwrap();
+
+        assert_eq!(v._0.as_i64(), 101i64);
+    }
+
     #[rstest]
     fn processing_calls(deployment: (Env<LocalDB, RandomValidator>, Address, Address)) {
         let (mut network, contract_address, user_address) = deployment;
@@ -636,4 +1515,185 @@ mod tests {
 
         assert_eq!(v._0.as_i64(), 303i64);
     }
+
+    #[rstest]
+    fn unchecked_revert(deployment: (Env<LocalDB, RandomValidator>, Address, Address)) {
+        let (mut network, contract_address, user_address) = deployment;
+
+        // Value sent to a non-payable function is reverted
+        let mut call = Transaction::basic(
+            user_address,
+            contract_address,
+            TestContract::setValueCall { x: Signed::ONE },
+            false,
+        );
+        call.value = U256::from(1);
+
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        network.process_transactions(vec![call], &mut rng, 1);
+
+        assert_eq!(network.last_events.len(), 1);
+        assert!(!network.last_events[0].success);
+        assert!(network.last_events[0].gas_used > 0);
+
+        let (v, _) = network
+            .direct_call(
+                user_address,
+                contract_address,
+                TestContract::getValueCall {},
+                U256::ZERO,
+            )
+            .unwrap();
+        assert_eq!(v._0.as_i64(), 101i64);
+    }
+
+    #[rstest]
+    fn failed_unchecked_calls(deployment: (Env<LocalDB, RandomValidator>, Address, Address)) {
+        let (mut network, _, user_address) = deployment;
+
+        // Contract consisting of the INVALID opcode
+        let invalid = Address::repeat_byte(0xaa);
+        network.insert_code(invalid, vec![0xfe]);
+
+        let halted =
+            Transaction::basic(user_address, invalid, TestContract::getValueCall {}, false);
+        let mut unfunded = Transaction::basic(
+            user_address,
+            Address::repeat_byte(0xbb),
+            TestContract::getValueCall {},
+            false,
+        );
+        unfunded.value = Eth::to_weth(1_000);
+
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        network.process_transactions(vec![halted, unfunded], &mut rng, 1);
+
+        assert_eq!(network.last_events.len(), 2);
+        assert!(network.last_events.iter().all(|e| !e.success));
+        assert!(network.last_events.iter().all(|e| e.logs.is_empty()));
+        assert!(network
+            .last_events
+            .iter()
+            .any(|e| e.gas_used == 0 && e.value == Eth::to_weth(1_000)));
+        assert!(network
+            .last_events
+            .iter()
+            .any(|e| e.gas_used > 0 && e.value == U256::ZERO));
+    }
+
+    #[rstest]
+    fn block_state_diff(deployment: (Env<LocalDB, RandomValidator>, Address, Address)) {
+        let (mut network, contract_address, user_address) = deployment;
+
+        let calls = vec![Transaction::basic(
+            user_address,
+            contract_address,
+            TestContract::setValueCall {
+                x: Signed::try_from_be_slice(&303u128.to_be_bytes()).unwrap(),
+            },
+            true,
+        )];
+
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        let diff = network
+            .process_transactions_with_diff(calls, &mut rng, 1)
+            .unwrap();
+
+        let contract = &diff.accounts[&contract_address];
+        assert_eq!(contract.balance, None);
+        assert_eq!(contract.storage.len(), 1);
+        assert_eq!(contract.storage[&U256::ZERO].before, U256::from(101));
+        assert_eq!(contract.storage[&U256::ZERO].after, U256::from(303));
+    }
+
+    #[rstest]
+    fn named_state_variables(deployment: (Env<LocalDB, RandomValidator>, Address, Address)) {
+        let (mut network, contract_address, user_address) = deployment;
+
+        let layout = StorageLayout::from_json(
+            r#"{
+                "storage": [
+                    {"label": "value", "offset": 0, "slot": "0", "type": "t_int256"}
+                ],
+                "types": {
+                    "t_int256": {"encoding": "inplace", "label": "int256", "numberOfBytes": "32"}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            network.read_var(contract_address, "value"),
+            Err(StorageLayoutError::NoLayout(_))
+        ));
+
+        network.register_storage_layout(contract_address, layout);
+
+        let value = network.read_var(contract_address, "value").unwrap();
+        assert_eq!(value, StorageValue::Word(U256::from(101)));
+
+        network
+            .write_var(contract_address, "value", &U256::from(202).into())
+            .unwrap();
+
+        let (v, _) = network
+            .direct_call(
+                user_address,
+                contract_address,
+                TestContract::getValueCall {},
+                U256::ZERO,
+            )
+            .unwrap();
+
+        assert_eq!(v._0.as_i64(), 202i64);
+    }
+
+    #[rstest]
+    fn genesis_init(deployment: (Env<LocalDB, RandomValidator>, Address, Address)) {
+        let (mut network, contract_address, user_address) = deployment;
+
+        let db = &network.evm_state().context.evm.db;
+        let code = db.accounts[&contract_address]
+            .info
+            .code
+            .clone()
+            .unwrap()
+            .original_bytes();
+
+        let genesis = GenesisState::from_json(&format!(
+            r#"{{
+                "timestamp": "0x64",
+                "number": "0xa",
+                "alloc": {{
+                    "{user_address}": {{"balance": "1000000000000000000"}},
+                    "{contract_address}": {{
+                        "code": "{code}",
+                        "storage": {{"0x00": "0x65"}}
+                    }}
+                }}
+            }}"#
+        ))
+        .unwrap();
+
+        let mut network =
+            Env::<LocalDB, RandomValidator>::from_genesis(&genesis, RandomValidator {});
+
+        let block = &network.evm_state().context.evm.env.block;
+        assert_eq!(block.timestamp, U256::from(100));
+        assert_eq!(block.number, U256::from(10));
+
+        let (v, _) = network
+            .direct_call(
+                user_address,
+                contract_address,
+                TestContract::getValueCall {},
+                U256::ZERO,
+            )
+            .unwrap();
+
+        assert_eq!(v._0.as_i64(), 101i64);
+    }
 }
@@ -0,0 +1,82 @@
+//! Serialization of simulation environments
+//!
+//! Environments are serialized as their DB, EVM
+//! environment (config, block and transaction),
+//! EVM spec-id, event buffers, validator and any
+//! transaction log. The EVM is rebuilt from these
+//! values when the environment is deserialized.
+//!
+
+use super::{Env, TransactionLog, Validator};
+use crate::contract::{ErrorRegistry, Event};
+use crate::DB;
+use revm::primitives::{Env as EvmEnv, SpecId};
+use revm::Evm;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+
+#[derive(Serialize)]
+struct EnvRef<'a, D, V> {
+    db: &'a D,
+    evm_env: &'a EvmEnv,
+    spec_id: SpecId,
+    last_events: &'a Vec<Event>,
+    event_history: &'a Vec<Event>,
+    validator: &'a V,
+    transaction_log: &'a Option<TransactionLog>,
+}
+
+#[derive(Deserialize)]
+struct EnvData<D, V> {
+    db: D,
+    evm_env: EvmEnv,
+    spec_id: SpecId,
+    last_events: Vec<Event>,
+    event_history: Vec<Event>,
+    validator: V,
+    transaction_log: Option<TransactionLog>,
+}
+
+impl<D: DB + Serialize, V: Validator + Serialize> Serialize for Env<D, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let state = match &self.evm_state {
+            Some(e) => e,
+            None => panic!("No EVM state set (this should not happen!)"),
+        };
+        EnvRef {
+            db: &state.context.evm.db,
+            evm_env: &state.context.evm.env,
+            spec_id: state.cfg.spec_id,
+            last_events: &self.last_events,
+            event_history: &self.event_history,
+            validator: &self.validator,
+            transaction_log: &self.transaction_log,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, D: DB + DeserializeOwned, V: Validator + DeserializeOwned> Deserialize<'de>
+    for Env<D, V>
+{
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let data = EnvData::<D, V>::deserialize(deserializer)?;
+
+        let evm = Evm::builder()
+            .with_db(data.db)
+            .with_env(Box::new(data.evm_env))
+            .with_spec_id(data.spec_id)
+            .build();
+
+        Ok(Env {
+            evm_state: Some(evm.into_context_with_handler_cfg()),
+            last_events: data.last_events,
+            event_history: data.event_history,
+            validator: data.validator,
+            transaction_log: data.transaction_log,
+            storage_layouts: HashMap::new(),
+            error_registry: ErrorRegistry::new(),
+        })
+    }
+}
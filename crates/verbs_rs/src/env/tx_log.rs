@@ -0,0 +1,211 @@
+//! Transaction logs
+//!
+//! Record of the transactions processed during a
+//! simulation, that can be replayed against the
+//! starting state of the simulation without the
+//! agents that generated the transactions.
+//!
+
+use crate::contract::Transaction;
+use revm::primitives::BlockEnv;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Error raised reading or writing a transaction log
+#[derive(Debug, thiserror::Error)]
+pub enum TransactionLogError {
+    #[error("failed to encode/decode transaction log: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Transactions processed in a single block
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlockRecord {
+    /// Simulation step of the block
+    pub step: usize,
+    /// Block environment the transactions were executed in
+    pub block: BlockEnv,
+    /// Transactions in the order they were executed
+    pub transactions: Vec<Transaction>,
+}
+
+/// Ordered log of transactions processed by an environment
+///
+/// Records are added by [super::Env::process_transactions]
+/// once recording has been enabled with
+/// [super::Env::record_transactions]. The log can be
+/// replayed using [super::Env::replay] (or
+/// [super::Env::replay_with_inspector] to trace execution).
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TransactionLog {
+    /// Records of each processed block
+    pub blocks: Vec<BlockRecord>,
+}
+
+impl TransactionLog {
+    /// Initialise an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a block of transactions to the log
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - Simulation step of the block
+    /// * `block` - Block environment of the block
+    /// * `transactions` - Ordered transactions of the block
+    ///
+    pub fn push(&mut self, step: usize, block: BlockEnv, transactions: Vec<Transaction>) {
+        self.blocks.push(BlockRecord {
+            step,
+            block,
+            transactions,
+        })
+    }
+
+    /// Total number of transactions in the log
+    pub fn n_transactions(&self) -> usize {
+        self.blocks.iter().map(|b| b.transactions.len()).sum()
+    }
+
+    /// Write the log as JSON
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), TransactionLogError> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Read a log from JSON
+    pub fn read<R: Read>(reader: R) -> Result<Self, TransactionLogError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the log to a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to create
+    ///
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), TransactionLogError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load a log from a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to read
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TransactionLogError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::{Env, RandomValidator};
+    use crate::LocalDB;
+    use alloy_primitives::{Address, U256};
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoroshiro128StarStar;
+    use revm::interpreter::{CallInputs, CallOutcome};
+    use revm::{EvmContext, Inspector};
+    use rstest::*;
+
+    #[derive(Default)]
+    struct CallCounter {
+        n_calls: usize,
+    }
+
+    impl Inspector<LocalDB> for CallCounter {
+        fn call(
+            &mut self,
+            _context: &mut EvmContext<LocalDB>,
+            _inputs: &mut CallInputs,
+        ) -> Option<CallOutcome> {
+            self.n_calls += 1;
+            None
+        }
+    }
+
+    fn transfer(from: Address, value: u64) -> Transaction {
+        Transaction {
+            function_selector: [0, 0, 0, 0],
+            callee: from,
+            transact_to: Address::repeat_byte(0xff),
+            args: Vec::default(),
+            value: U256::from(value),
+            checked: false,
+            gas_priority_fee: None,
+            nonce: None,
+        }
+    }
+
+    fn balance(env: &mut Env<LocalDB, RandomValidator>, address: Address) -> U256 {
+        env.evm_state().context.evm.db.accounts[&address]
+            .info
+            .balance
+    }
+
+    #[fixture]
+    fn env() -> Env<LocalDB, RandomValidator> {
+        let mut env =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {});
+        env.insert_account(Address::repeat_byte(1), U256::from(10_000));
+        env.insert_account(Address::repeat_byte(2), U256::from(10_000));
+        env
+    }
+
+    #[rstest]
+    fn test_record_and_replay(mut env: Env<LocalDB, RandomValidator>) {
+        let mut replay_env = env.clone();
+        let mut inspected_env = env.clone();
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(101);
+
+        env.record_transactions();
+
+        for step in 0..3 {
+            env.clear_events();
+            env.increment_time(&mut rng, 15);
+            let transactions = vec![
+                transfer(Address::repeat_byte(1), 100 + step as u64),
+                transfer(Address::repeat_byte(2), 200 + step as u64),
+            ];
+            env.process_transactions(transactions, &mut rng, step);
+        }
+        env.clear_events();
+
+        let log = env.take_transaction_log().unwrap();
+        assert!(env.transaction_log.is_none());
+        assert_eq!(log.blocks.len(), 3);
+        assert_eq!(log.n_transactions(), 6);
+
+        // Round-trip through JSON
+        let mut buffer = Vec::new();
+        log.write(&mut buffer).unwrap();
+        let log = TransactionLog::read(buffer.as_slice()).unwrap();
+
+        replay_env.replay(&log);
+
+        let sink = Address::repeat_byte(0xff);
+        assert_eq!(balance(&mut replay_env, sink), U256::from(906));
+        assert_eq!(balance(&mut replay_env, sink), balance(&mut env, sink));
+        assert_eq!(
+            replay_env.evm_state().context.evm.env.block,
+            env.evm_state().context.evm.env.block
+        );
+        assert_eq!(replay_env.event_history.len(), 6);
+
+        let counter = inspected_env.replay_with_inspector(&log, CallCounter::default());
+        assert_eq!(counter.n_calls, 6);
+        assert_eq!(balance(&mut inspected_env, sink), U256::from(906));
+    }
+}
@@ -1,9 +1,12 @@
 //! EVM and data processing utilities
 //!
 
+use crate::contract::ErrorRegistry;
 use crate::contract::Event;
 use alloy_primitives::{Address, Bytes, Log, U256};
-use alloy_sol_types::{decode_revert_reason, SolCall, SolEvent};
+use alloy_sol_types::{
+    decode_revert_reason, GenericContractError, SolCall, SolEvent, SolInterface,
+};
 use revm::primitives::{ExecutionResult, Output, TransactTo, TxEnv};
 use std::{collections::HashMap, fmt};
 
@@ -16,6 +19,54 @@ pub struct RevertError {
     sender: Address,
     /// Decoded revert error message
     pub output: Option<String>,
+    /// Raw revert data
+    pub data: Bytes,
+}
+
+impl RevertError {
+    pub(crate) fn new(function_name: &'static str, sender: Address, data: Bytes) -> Self {
+        RevertError {
+            function_name,
+            sender,
+            output: decode_revert_data(&data),
+            data,
+        }
+    }
+
+    /// Selector of the revert error, if included in the revert data
+    pub fn selector(&self) -> Option<[u8; 4]> {
+        self.data.get(..4)?.try_into().ok()
+    }
+
+    /// Decode the revert data as a custom error
+    ///
+    /// Returns `None` if the revert data is not an
+    /// error of the given errors enum.
+    ///
+    /// # Arguments
+    ///
+    /// * `E` - Errors enum generated by the [alloy_sol_types::sol]
+    ///   macro, e.g. `Pool::PoolErrors`
+    ///
+    pub fn decode<E: SolInterface>(&self) -> Option<E> {
+        E::abi_decode(&self.data, true).ok()
+    }
+
+    /// Decode the error message using registered custom errors
+    ///
+    /// The message is left unchanged if the revert data
+    /// does not match a registered error.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` - Registry of custom errors
+    ///
+    pub fn decode_with(mut self, registry: &ErrorRegistry) -> Self {
+        if let Some(output) = registry.decode(&self.data) {
+            self.output = Some(output);
+        }
+        self
+    }
 }
 
 impl fmt::Display for RevertError {
@@ -33,6 +84,27 @@ impl fmt::Display for RevertError {
     }
 }
 
+/// Decode revert data into an error message
+///
+/// Decodes `Error(string)` reverts and `Panic(uint256)`
+/// codes (with readable names). Custom errors are
+/// identified by their selector, and can be decoded
+/// using [RevertError::decode] or an [ErrorRegistry].
+///
+/// # Arguments
+///
+/// - `data` - Revert data
+///
+pub fn decode_revert_data(data: &[u8]) -> Option<String> {
+    if let Ok(error) = GenericContractError::abi_decode(data, true) {
+        return Some(error.to_string());
+    }
+    if data.len() >= 4 && data.len() % 32 == 4 {
+        return Some(format!("custom error 0x{}", hex::encode(&data[..4])));
+    }
+    decode_revert_reason(data)
+}
+
 /// Process an [ExecutionResult] from a contract deployment
 ///
 /// Process the result of a call to deploy a contract and
@@ -54,7 +126,7 @@ pub fn deployment_output(contract_name: &str, execution_result: ExecutionResult)
             panic!(
                 "Failed to deploy {} due to revert: {:?}",
                 contract_name,
-                decode_revert_reason(&output.0)
+                decode_revert_data(&output)
             )
         }
         ExecutionResult::Halt { reason, .. } => {
@@ -152,11 +224,9 @@ pub fn result_to_raw_output(
 ) -> Result<ExecutionResult, RevertError> {
     match execution_result {
         ExecutionResult::Success { .. } => Ok(execution_result),
-        ExecutionResult::Revert { output, .. } => Err(RevertError {
-            function_name: "Direct execute raw",
-            sender,
-            output: decode_revert_reason(&output),
-        }),
+        ExecutionResult::Revert { output, .. } => {
+            Err(RevertError::new("Direct execute raw", sender, output))
+        }
         ExecutionResult::Halt { reason, .. } => panic!("Failed due to halt: {:?}", reason),
     }
 }
@@ -175,60 +245,80 @@ pub fn result_to_raw_output(
 /// - `function_selector` - 4 byte function selector of the
 ///   contract function that was called
 /// - `sender` - Address of the transaction sender
+/// - `value` - Value attached to the transaction
 /// - `execution_result` - [ExecutionResult] returned from
 ///   the transaction
-/// - `checked` - Flag if `true` a reverted transaction will
-///   cause a panic and stop the simulation. Should be set
-///   to `false` if it's possible for a transaction to revert
-///   but the simulation should continue.
+/// - `checked` - Flag if `true` a reverted or halted
+///   transaction will cause a panic and stop the simulation.
+///   Should be set to `false` if it's possible for a
+///   transaction to fail but the simulation should continue.
 ///
 /// # Panics
 ///
-/// Panics if the transaction halts and if `checked` is true
-/// and the transaction is reverted.
+/// Panics if `checked` is true and the transaction is
+/// reverted or halted.
 ///
 pub fn result_to_output_with_events(
     step: usize,
     sequence: usize,
     function_selector: [u8; 4],
     sender: Address,
+    value: U256,
     execution_result: ExecutionResult,
     checked: bool,
 ) -> Event {
     match execution_result {
-        ExecutionResult::Success { output, logs, .. } => match output {
+        ExecutionResult::Success {
+            output,
+            logs,
+            gas_used,
+            ..
+        } => match output {
             Output::Call(_) => Event {
                 success: true,
                 function_selector,
                 logs,
                 step,
                 sequence,
+                gas_used,
+                value,
             },
             Output::Create(..) => {
                 panic!("Unexpected call to create contract during simulation.")
             }
         },
-        ExecutionResult::Revert { output, .. } => match checked {
+        ExecutionResult::Revert { output, gas_used } => match checked {
             true => panic!(
                 "Failed to call {:?} from {} due to revert: {:?}",
                 function_selector,
                 sender,
-                decode_revert_reason(&output.0)
+                decode_revert_data(&output)
             ),
             false => Event {
-                success: true,
+                success: false,
                 function_selector,
                 logs: Vec::default(),
                 step,
                 sequence,
+                gas_used,
+                value,
             },
         },
-        ExecutionResult::Halt { reason, .. } => {
-            panic!(
+        ExecutionResult::Halt { reason, gas_used } => match checked {
+            true => panic!(
                 "Failed to call {:?} from {} due to halt: {:?}",
                 function_selector, sender, reason
-            )
-        }
+            ),
+            false => Event {
+                success: false,
+                function_selector,
+                logs: Vec::default(),
+                step,
+                sequence,
+                gas_used,
+                value,
+            },
+        },
     }
 }
 
@@ -252,11 +342,9 @@ pub fn result_to_output(
 ) -> Result<(Output, Vec<Log>), RevertError> {
     match execution_result {
         ExecutionResult::Success { output, logs, .. } => Ok((output, logs)),
-        ExecutionResult::Revert { output, .. } => Err(RevertError {
-            function_name,
-            sender,
-            output: decode_revert_reason(&output),
-        }),
+        ExecutionResult::Revert { output, .. } => {
+            Err(RevertError::new(function_name, sender, output))
+        }
         ExecutionResult::Halt { reason, .. } => {
             panic!(
                 "Failed to call {} from {} due to halt: {:?}",
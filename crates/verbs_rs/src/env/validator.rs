@@ -2,6 +2,7 @@
 //!
 use alloy_primitives::{Address, U256};
 use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
 
 use crate::contract::Transaction;
 use std::collections::HashMap;
@@ -30,6 +31,7 @@ pub trait Validator {
 }
 
 /// Validator that randomly shuffles transactions
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RandomValidator {}
 
 impl Validator for RandomValidator {
@@ -59,6 +61,7 @@ impl Validator for RandomValidator {
 /// - Sort the groups by the gas-priority of the first transaction in the group
 /// - Flatten the groups into a single vector for processing
 ///
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GasPriorityValidator {}
 
 impl Validator for GasPriorityValidator {
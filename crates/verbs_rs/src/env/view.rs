@@ -0,0 +1,128 @@
+//! Read-only view of a simulation environment
+//!
+
+use super::utils::{self, RevertError};
+use crate::contract::ErrorRegistry;
+use crate::DB;
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::SolCall;
+use revm::db::WrapDatabaseRef;
+use revm::primitives::{
+    BlockEnv, Env as EvmEnv, ExecutionResult, HandlerCfg, Log, ResultAndState, TxEnv,
+};
+use revm::Evm;
+
+/// Read-only view of a simulation environment
+///
+/// A view holds a shared reference to the state of
+/// an [super::Env], and can be used to call contracts
+/// without committing any changes. Each call executes
+/// against a fresh EVM wrapping the DB, so a view can
+/// be shared between threads (for example to update
+/// agents in parallel).
+///
+/// Views are created using [super::Env::view].
+pub struct EnvView<'a, D: DB> {
+    /// Reference to the environment DB
+    db: &'a D,
+    /// EVM config and block environment
+    env: &'a EvmEnv,
+    /// EVM handler config
+    cfg: HandlerCfg,
+    /// Custom errors used to decode reverts
+    error_registry: &'a ErrorRegistry,
+}
+
+impl<'a, D: DB> EnvView<'a, D> {
+    pub(crate) fn new(
+        db: &'a D,
+        env: &'a EvmEnv,
+        cfg: HandlerCfg,
+        error_registry: &'a ErrorRegistry,
+    ) -> Self {
+        Self {
+            db,
+            env,
+            cfg,
+            error_registry,
+        }
+    }
+
+    /// Reference to the underlying DB
+    pub fn db(&self) -> &D {
+        self.db
+    }
+
+    /// Current block environment
+    pub fn block(&self) -> &BlockEnv {
+        &self.env.block
+    }
+
+    /// Execute a transaction without committing changes
+    fn call(&self, tx: TxEnv) -> ResultAndState {
+        let mut evm: Evm<'_, (), WrapDatabaseRef<&D>> = Evm::builder()
+            .with_ref_db(self.db)
+            .with_env(Box::new(self.env.clone()))
+            .with_handler_cfg(self.cfg)
+            .build();
+        evm.context.evm.env.tx = tx;
+
+        match evm.transact() {
+            Ok(val) => val,
+            Err(e) => panic!("Call failed: {:?}", e),
+        }
+    }
+
+    /// Call a contract function with ABI encoded arguments
+    ///
+    /// # Arguments
+    ///
+    /// - `callee` - Address of the function caller
+    /// - `contract` - Address of the contract
+    /// - `encoded_args` - ABI encoded function selector and arguments
+    /// - `value` - Value attached to the transaction
+    pub fn direct_call_raw(
+        &self,
+        callee: Address,
+        contract: Address,
+        encoded_args: Vec<u8>,
+        value: U256,
+    ) -> Result<ExecutionResult, RevertError> {
+        let tx = utils::init_call_transaction(callee, contract, encoded_args, value);
+        let result = self.call(tx);
+        utils::result_to_raw_output(callee, result.result)
+            .map_err(|e| e.decode_with(self.error_registry))
+    }
+
+    /// Call a contract function for a specific ABI
+    ///
+    /// # Arguments
+    ///
+    /// - `callee` - Address of the function caller
+    /// - `contract` - Address of the contract
+    /// - `call_args` - Function arguments wrapped in a
+    ///   [SolCall] object
+    /// - `value` - Value attached to the transaction
+    pub fn direct_call<T: SolCall>(
+        &self,
+        callee: Address,
+        contract: Address,
+        call_args: T,
+        value: U256,
+    ) -> Result<(<T as SolCall>::Return, Vec<Log>), RevertError> {
+        let function_name = T::SIGNATURE;
+        let call_args = call_args.abi_encode();
+        let tx = utils::init_call_transaction(callee, contract, call_args, value);
+        let execution_result = self.call(tx);
+        let (output, events) =
+            utils::result_to_output(function_name, callee, execution_result.result)
+                .map_err(|e| e.decode_with(self.error_registry))?;
+        let output_data = output.into_data();
+        let decoded = T::abi_decode_returns(&output_data, true);
+        let decoded = match decoded {
+            Ok(x) => x,
+            Err(_) => panic!("Decoding error from {}", function_name),
+        };
+        Ok((decoded, events))
+    }
+}
@@ -0,0 +1,787 @@
+//! Export of recorded agent data
+//!
+//! Agent sets collect records as a time-series of
+//! vectors of records (i.e. `records[step][agent]`).
+//! These functions flatten these records into tables
+//! with one row per step and agent, that can then be
+//! written to CSV or (with the `arrow` and `parquet`
+//! features enabled) Arrow IPC and Parquet files.
+//!
+//! Records should implement [serde::Serialize], where
+//! the fields of a struct are exported as columns (in
+//! the order the fields are serialized). Records that
+//! do not serialize as a map (e.g. a numeric value) are
+//! exported to a single `value` column. `step` and
+//! `agent` (address) columns are added to each row
+//! automatically.
+//!
+//! Values serialized as 32 bytes (i.e. [U256], but also
+//! [alloy_primitives::B256]) are exported as decimal
+//! strings, and other byte values (e.g. addresses) as
+//! `0x` prefixed hex strings.
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::Address;
+//! use serde::Serialize;
+//! use verbs_rs::export;
+//!
+//! #[derive(Serialize)]
+//! struct Record {
+//!     balance: u64,
+//!     active: bool,
+//! }
+//!
+//! let addresses = vec![Address::ZERO, Address::repeat_byte(1)];
+//! let records = vec![
+//!     vec![Record { balance: 10, active: true }, Record { balance: 20, active: false }],
+//!     vec![Record { balance: 11, active: true }, Record { balance: 19, active: false }],
+//! ];
+//!
+//! let mut csv = Vec::new();
+//! export::write_csv(&mut csv, &records, &addresses).unwrap();
+//! ```
+
+use alloy_primitives::{hex, Address, U256};
+use serde::ser::{self, Serializer};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Name of the column containing the simulation step
+pub const STEP_COLUMN: &str = "step";
+/// Name of the column containing the agent address
+pub const AGENT_COLUMN: &str = "agent";
+/// Name of the column used for records that are not structs
+pub const VALUE_COLUMN: &str = "value";
+
+/// Error raised when exporting records
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("step {step} has {n_records} records but {n_addresses} agent addresses")]
+    AddressMismatch {
+        step: usize,
+        n_records: usize,
+        n_addresses: usize,
+    },
+    #[error("record field '{0}' clashes with a generated column")]
+    ReservedColumn(String),
+    #[error("failed to serialize record: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "arrow")]
+    #[error(transparent)]
+    Arrow(#[from] arrow::error::ArrowError),
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+}
+
+/// Serializer converting record values to JSON values
+///
+/// Similar to [serde_json::to_value], but is not human
+/// readable, so [U256] values are serialized as bytes,
+/// and can be exported as decimal strings. Keys of
+/// a top-level map or struct are appended to `keys`
+/// in the order they are serialized.
+struct RecordSerializer<'a> {
+    keys: Option<&'a mut Vec<String>>,
+}
+
+impl RecordSerializer<'_> {
+    fn nested() -> RecordSerializer<'static> {
+        RecordSerializer { keys: None }
+    }
+
+    fn push_key(keys: &mut Option<&mut Vec<String>>, key: &str) {
+        if let Some(keys) = keys {
+            keys.push(key.to_string());
+        }
+    }
+}
+
+/// Serializer of sequences and tuples
+struct SeqSerializer(Vec<Value>);
+
+/// Serializer of maps and structs
+struct MapSerializer<'a> {
+    keys: Option<&'a mut Vec<String>>,
+    map: Map<String, Value>,
+    next_key: Option<String>,
+}
+
+/// Serializer of enum variants containing sequences or structs
+struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<'a> Serializer for RecordSerializer<'a> {
+    type Ok = Value;
+    type Error = serde_json::Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = MapSerializer<'a>;
+    type SerializeStructVariant = VariantSerializer<MapSerializer<'static>>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Self::Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, Self::Error> {
+        serde_json::value::Serializer.serialize_i128(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, Self::Error> {
+        serde_json::value::Serializer.serialize_u128(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Self::Error> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Self::Error> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Self::Error> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Self::Error> {
+        Ok(Value::String(match v.len() {
+            32 => U256::from_be_slice(v).to_string(),
+            _ => hex::encode_prefixed(v),
+        }))
+    }
+
+    fn serialize_none(self) -> Result<Value, Self::Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Self::Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Self::Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Self::Error> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        mut self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, Self::Error> {
+        Self::push_key(&mut self.keys, variant);
+        let mut map = Map::new();
+        map.insert(
+            variant.to_string(),
+            value.serialize(RecordSerializer::nested())?,
+        );
+        Ok(Value::Object(map))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Self::Error> {
+        Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        mut self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Self::push_key(&mut self.keys, variant);
+        Ok(VariantSerializer {
+            variant,
+            inner: SeqSerializer(Vec::with_capacity(len)),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer<'a>, Self::Error> {
+        Ok(MapSerializer {
+            keys: self.keys,
+            map: Map::new(),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer<'a>, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        mut self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Self::push_key(&mut self.keys, variant);
+        Ok(VariantSerializer {
+            variant,
+            inner: RecordSerializer::nested().serialize_map(Some(len))?,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.0.push(value.serialize(RecordSerializer::nested())?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        Ok(Value::Array(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Value;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        let mut map = Map::new();
+        map.insert(
+            self.variant.to_string(),
+            ser::SerializeSeq::end(self.inner)?,
+        );
+        Ok(Value::Object(map))
+    }
+}
+
+impl ser::SerializeMap for MapSerializer<'_> {
+    type Ok = Value;
+    type Error = serde_json::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        let key = match key.serialize(serde_json::value::Serializer)? {
+            Value::String(s) => s,
+            v @ (Value::Number(_) | Value::Bool(_)) => v.to_string(),
+            _ => return Err(ser::Error::custom("map keys must be strings or numbers")),
+        };
+        self.next_key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| ser::Error::custom("map value serialized before its key"))?;
+        RecordSerializer::push_key(&mut self.keys, &key);
+        self.map
+            .insert(key, value.serialize(RecordSerializer::nested())?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        Ok(Value::Object(self.map))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer<'_> {
+    type Ok = Value;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        RecordSerializer::push_key(&mut self.keys, key);
+        self.map.insert(
+            key.to_string(),
+            value.serialize(RecordSerializer::nested())?,
+        );
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer<'static>> {
+    type Ok = Value;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        let mut map = Map::new();
+        map.insert(
+            self.variant.to_string(),
+            ser::SerializeMap::end(self.inner)?,
+        );
+        Ok(Value::Object(map))
+    }
+}
+
+/// Flatten agent records into rows
+///
+/// Converts a time-series of agent records into a
+/// vector of rows, with a row for each step and
+/// agent. Each row is a map from column name to value.
+///
+/// # Arguments
+///
+/// * `records` - Time-series of records, as returned by
+///   [crate::agent::RecordedAgentSet::take_records]
+/// * `addresses` - Addresses of the agents in the set, in
+///   the same order as the records of each step
+///
+pub fn records_to_rows<R: Serialize>(
+    records: &[Vec<R>],
+    addresses: &[Address],
+) -> Result<Vec<Map<String, Value>>, ExportError> {
+    Ok(records_to_table(records, addresses)?.1)
+}
+
+/// Column names and rows of exported records
+type Table = (Vec<String>, Vec<Map<String, Value>>);
+
+/// Flatten agent records into rows, along with the
+/// union of columns in the order they first appear
+fn records_to_table<R: Serialize>(
+    records: &[Vec<R>],
+    addresses: &[Address],
+) -> Result<Table, ExportError> {
+    let mut columns = vec![STEP_COLUMN.to_string(), AGENT_COLUMN.to_string()];
+    let mut rows = Vec::with_capacity(records.len() * addresses.len());
+    let mut keys = Vec::new();
+
+    for (step, step_records) in records.iter().enumerate() {
+        if step_records.len() != addresses.len() {
+            return Err(ExportError::AddressMismatch {
+                step,
+                n_records: step_records.len(),
+                n_addresses: addresses.len(),
+            });
+        }
+
+        for (address, record) in addresses.iter().zip(step_records) {
+            let mut row = Map::new();
+            row.insert(STEP_COLUMN.to_string(), Value::from(step));
+            row.insert(AGENT_COLUMN.to_string(), Value::from(address.to_string()));
+
+            keys.clear();
+            let value = record.serialize(RecordSerializer {
+                keys: Some(&mut keys),
+            })?;
+            match value {
+                Value::Object(fields) => {
+                    for k in keys.iter() {
+                        if k == STEP_COLUMN || k == AGENT_COLUMN {
+                            return Err(ExportError::ReservedColumn(k.clone()));
+                        }
+                        if !columns.contains(k) {
+                            columns.push(k.clone());
+                        }
+                    }
+                    row.extend(fields);
+                }
+                value => {
+                    if !columns.iter().any(|c| c == VALUE_COLUMN) {
+                        columns.push(VALUE_COLUMN.to_string());
+                    }
+                    row.insert(VALUE_COLUMN.to_string(), value);
+                }
+            }
+
+            rows.push(row);
+        }
+    }
+
+    Ok((columns, rows))
+}
+
+fn csv_field(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+    }
+}
+
+/// Write agent records to CSV
+///
+/// Nested values (i.e. sequences and structs) are
+/// written as JSON strings, and missing values are
+/// left empty.
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the CSV data
+/// * `records` - Time-series of agent records
+/// * `addresses` - Addresses of the agents in the set
+///
+pub fn write_csv<R: Serialize, W: Write>(
+    writer: W,
+    records: &[Vec<R>],
+    addresses: &[Address],
+) -> Result<(), ExportError> {
+    let (columns, rows) = records_to_table(records, addresses)?;
+
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(&columns)?;
+
+    for row in rows.iter() {
+        writer.write_record(columns.iter().map(|c| csv_field(row.get(c))))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write agent records to a CSV file
+///
+/// # Arguments
+///
+/// * `path` - Path of the file to create
+/// * `records` - Time-series of agent records
+/// * `addresses` - Addresses of the agents in the set
+///
+pub fn write_csv_file<R: Serialize, P: AsRef<Path>>(
+    path: P,
+    records: &[Vec<R>],
+    addresses: &[Address],
+) -> Result<(), ExportError> {
+    write_csv(File::create(path)?, records, addresses)
+}
+
+/// Convert agent records to an Arrow [arrow::record_batch::RecordBatch]
+///
+/// The schema of the batch is inferred from the
+/// serialized records, with columns in the order
+/// they are serialized.
+///
+/// # Arguments
+///
+/// * `records` - Time-series of agent records
+/// * `addresses` - Addresses of the agents in the set
+///
+#[cfg(feature = "arrow")]
+pub fn records_to_record_batch<R: Serialize>(
+    records: &[Vec<R>],
+    addresses: &[Address],
+) -> Result<arrow::record_batch::RecordBatch, ExportError> {
+    use arrow::datatypes::{Schema, SchemaRef};
+    use arrow::json::reader::{infer_json_schema_from_iterator, ReaderBuilder};
+    use arrow::record_batch::RecordBatch;
+    use std::sync::Arc;
+
+    let (columns, rows) = records_to_table(records, addresses)?;
+    let inferred =
+        infer_json_schema_from_iterator(rows.iter().map(|r| Ok(Value::Object(r.clone()))))?;
+    let fields = columns
+        .iter()
+        .map(|c| inferred.field_with_name(c).cloned())
+        .collect::<Result<Vec<_>, _>>()?;
+    let schema: SchemaRef = Arc::new(Schema::new(fields));
+
+    let mut decoder = ReaderBuilder::new(schema.clone())
+        .with_batch_size(rows.len().max(1))
+        .build_decoder()?;
+    decoder.serialize(&rows)?;
+
+    Ok(decoder
+        .flush()?
+        .unwrap_or_else(|| RecordBatch::new_empty(schema)))
+}
+
+/// Write agent records to an Arrow IPC file
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the IPC data
+/// * `records` - Time-series of agent records
+/// * `addresses` - Addresses of the agents in the set
+///
+#[cfg(feature = "arrow")]
+pub fn write_arrow_ipc<R: Serialize, W: Write>(
+    writer: W,
+    records: &[Vec<R>],
+    addresses: &[Address],
+) -> Result<(), ExportError> {
+    let batch = records_to_record_batch(records, addresses)?;
+    let mut writer = arrow::ipc::writer::FileWriter::try_new(writer, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Write agent records to a Parquet file
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the Parquet data
+/// * `records` - Time-series of agent records
+/// * `addresses` - Addresses of the agents in the set
+///
+#[cfg(feature = "parquet")]
+pub fn write_parquet<R: Serialize, W: Write + Send>(
+    writer: W,
+    records: &[Vec<R>],
+    addresses: &[Address],
+) -> Result<(), ExportError> {
+    let batch = records_to_record_batch(records, addresses)?;
+    let mut writer = parquet::arrow::ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U256;
+
+    #[derive(Serialize)]
+    struct TestRecord {
+        balance: U256,
+        active: bool,
+        position: Option<u64>,
+    }
+
+    fn test_data() -> (Vec<Vec<TestRecord>>, Vec<Address>) {
+        let addresses = vec![Address::ZERO, Address::repeat_byte(1)];
+        let records = vec![
+            vec![
+                TestRecord {
+                    balance: U256::from(10),
+                    active: true,
+                    position: Some(1),
+                },
+                TestRecord {
+                    balance: U256::from(20),
+                    active: false,
+                    position: None,
+                },
+            ],
+            vec![
+                TestRecord {
+                    balance: U256::from(11),
+                    active: true,
+                    position: Some(2),
+                },
+                TestRecord {
+                    balance: U256::from(19),
+                    active: false,
+                    position: None,
+                },
+            ],
+        ];
+        (records, addresses)
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let (records, addresses) = test_data();
+
+        let mut buffer = Vec::new();
+        write_csv(&mut buffer, &records, &addresses).unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "step,agent,balance,active,position");
+        assert_eq!(
+            lines[1],
+            "0,0x0000000000000000000000000000000000000000,10,true,1"
+        );
+        assert_eq!(
+            lines[4],
+            "1,0x0101010101010101010101010101010101010101,19,false,"
+        );
+    }
+
+    #[test]
+    fn test_scalar_records() {
+        let addresses = vec![Address::ZERO];
+        let records = vec![vec![1u64], vec![2u64]];
+
+        let rows = records_to_rows(&records, &addresses).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][STEP_COLUMN], Value::from(1));
+        assert_eq!(rows[1][VALUE_COLUMN], Value::from(2));
+    }
+
+    #[test]
+    fn test_value_formats() {
+        #[derive(Serialize)]
+        struct Record {
+            owner: Address,
+            amounts: Vec<U256>,
+            value: u128,
+        }
+
+        let records = vec![vec![Record {
+            owner: Address::repeat_byte(0xab),
+            amounts: vec![U256::MAX],
+            value: 12,
+        }]];
+
+        let rows = records_to_rows(&records, &[Address::ZERO]).unwrap();
+
+        assert_eq!(
+            rows[0]["owner"],
+            Value::from(format!("0x{}", "ab".repeat(20)))
+        );
+        assert_eq!(rows[0]["amounts"][0], Value::from(U256::MAX.to_string()));
+        assert_eq!(rows[0]["value"], Value::from(12));
+    }
+
+    #[test]
+    fn test_mismatched_addresses() {
+        let (records, _) = test_data();
+        let result = records_to_rows(&records, &[Address::ZERO]);
+        assert!(matches!(
+            result,
+            Err(ExportError::AddressMismatch { step: 0, .. })
+        ));
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_record_batch() {
+        let (records, addresses) = test_data();
+
+        let batch = records_to_record_batch(&records, &addresses).unwrap();
+
+        assert_eq!(batch.num_rows(), 4);
+        assert_eq!(batch.num_columns(), 5);
+        assert_eq!(batch.schema().field(0).name(), STEP_COLUMN);
+        assert_eq!(batch.schema().field(1).name(), AGENT_COLUMN);
+        assert_eq!(batch.schema().field(2).name(), "balance");
+    }
+}
@@ -50,11 +50,29 @@
 //! ContractName::getValueCall {};
 //! ```
 
+// Allows code generated by verbs-macros to refer to `verbs_rs`
+// from inside this crate.
+extern crate self as verbs_rs;
+
 pub mod agent;
+pub mod batch_runner;
+pub mod checkpoint;
 pub mod contract;
 mod db;
 pub mod env;
+pub mod export;
+pub mod metrics;
+pub mod rng;
+#[cfg(feature = "rpc")]
+pub mod rpc_server;
 pub mod sim_runner;
+pub mod state_diff;
 pub mod utils;
 
-pub use db::{ForkDb, LocalDB, RequestCache, DB};
+pub use db::{
+    AnvilAccount, AnvilState, AnvilStateError, CassetteError, DatabaseError, FileBackend,
+    ForkBackend, ForkBlock, ForkDb, ForkMode, GenesisAccount, GenesisError, GenesisState,
+    HistoricalBlock, HistoricalTransaction, LayeredDB, LocalDB, MockBackend, RequestCache,
+    RequestCacheError, RpcBackend, Snapshot, SnapshotError, StorageDump, StorageDumpError, DB,
+    SNAPSHOT_VERSION,
+};
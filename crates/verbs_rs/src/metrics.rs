@@ -0,0 +1,134 @@
+//! Block level simulation metrics
+//!
+//! Statistics collected for each block (i.e. step)
+//! of a simulation by [crate::sim_runner::SimRunner],
+//! and returned as a time-series in
+//! [crate::sim_runner::SimResult::metrics].
+//!
+
+use crate::contract::Event;
+use alloy_primitives::{Selector, U256};
+use revm::primitives::BlockEnv;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Statistics of a single simulated block
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockMetrics {
+    /// Simulation step
+    pub step: usize,
+    /// Block number
+    pub block_number: U256,
+    /// Block timestamp
+    pub timestamp: U256,
+    /// Block base fee
+    pub base_fee: U256,
+    /// Number of transactions processed
+    pub n_transactions: usize,
+    /// Number of reverted transactions
+    pub n_reverts: usize,
+    /// Total gas used by the transactions
+    pub gas_used: u64,
+    /// Total ETH (in wei) transferred by successful transactions
+    pub value_transferred: U256,
+    /// Number of transactions for each function selector
+    /// (serialized with hex encoded selectors as keys)
+    pub selector_counts: BTreeMap<Selector, usize>,
+    /// Wall-clock time spent updating agents
+    pub agent_time: Duration,
+    /// Wall-clock time spent executing transactions
+    pub evm_time: Duration,
+}
+
+impl BlockMetrics {
+    /// Collect metrics from the events of a block
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - Simulation step
+    /// * `block` - Environment of the block
+    /// * `events` - Events generated by the
+    ///   transactions in the block
+    ///
+    pub fn from_events(step: usize, block: &BlockEnv, events: &[Event]) -> Self {
+        let mut metrics = BlockMetrics {
+            step,
+            block_number: block.number,
+            timestamp: block.timestamp,
+            base_fee: block.basefee,
+            n_transactions: events.len(),
+            ..Default::default()
+        };
+
+        for event in events.iter() {
+            metrics.gas_used += event.gas_used;
+            *metrics
+                .selector_counts
+                .entry(Selector::from(event.function_selector))
+                .or_default() += 1;
+
+            if event.success {
+                metrics.value_transferred += event.value;
+            } else {
+                metrics.n_reverts += 1;
+            }
+        }
+
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(success: bool, function_selector: [u8; 4], value: u64) -> Event {
+        Event {
+            success,
+            function_selector,
+            logs: Vec::default(),
+            step: 1,
+            sequence: 0,
+            gas_used: 21_000,
+            value: U256::from(value),
+        }
+    }
+
+    #[test]
+    fn test_block_metrics() {
+        let block = BlockEnv {
+            number: U256::from(10),
+            basefee: U256::from(7),
+            ..Default::default()
+        };
+        let events = vec![
+            event(true, [1, 0, 0, 0], 100),
+            event(false, [1, 0, 0, 0], 200),
+            event(true, [2, 0, 0, 0], 300),
+        ];
+
+        let metrics = BlockMetrics::from_events(1, &block, &events);
+
+        assert_eq!(metrics.block_number, U256::from(10));
+        assert_eq!(metrics.base_fee, U256::from(7));
+        assert_eq!(metrics.n_transactions, 3);
+        assert_eq!(metrics.n_reverts, 1);
+        assert_eq!(metrics.gas_used, 63_000);
+        assert_eq!(metrics.value_transferred, U256::from(400));
+        assert_eq!(metrics.selector_counts[&Selector::from([1, 0, 0, 0])], 2);
+        assert_eq!(metrics.selector_counts[&Selector::from([2, 0, 0, 0])], 1);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let events = vec![event(true, [1, 0, 0, 0], 100)];
+        let metrics = BlockMetrics::from_events(1, &BlockEnv::default(), &events);
+
+        let json = serde_json::to_string(&metrics).unwrap();
+        assert!(json.contains(r#""selector_counts":{"0x01000000":1}"#));
+
+        let decoded: BlockMetrics = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, metrics);
+    }
+}
@@ -0,0 +1,119 @@
+//! Reproducible random streams
+//!
+//! Utilities to split a single simulation seed into
+//! independent random streams, each identified by a
+//! stable id (e.g. the address of an agent, or the name
+//! of a set of agents). Since a stream only depends on
+//! the seed and its id, adding an agent to a simulation,
+//! or an agent drawing additional random values, does not
+//! change the random values drawn by other agents. This
+//! allows common-random-number experiments across
+//! simulations.
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::Address;
+//! use rand::RngCore;
+//! use verbs_rs::rng;
+//!
+//! let seed = 101;
+//! let mut agent_rng = rng::stream(seed, rng::address_id(Address::ZERO));
+//! let x = agent_rng.next_u64();
+//! ```
+
+use alloy_primitives::Address;
+use rand::SeedableRng;
+use rand_xoshiro::Xoroshiro128StarStar;
+
+/// Stream id of random values used to sample agent updates
+pub const AGENTS_STREAM: u64 = 0;
+/// Stream id of random values used to update the block
+pub const BLOCK_STREAM: u64 = 1;
+/// Stream id of random values used by the validator
+pub const VALIDATOR_STREAM: u64 = 2;
+
+/// SplitMix64 mixing function
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// 64 bit FNV-1a hash
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Derive a new seed from a parent seed and an id
+///
+/// # Arguments
+///
+/// * `seed` - Parent seed
+/// * `id` - Id of the derived seed
+///
+pub fn split_seed(seed: u64, id: u64) -> u64 {
+    splitmix64(splitmix64(seed) ^ id)
+}
+
+/// Create an independent random generator
+///
+/// # Arguments
+///
+/// * `seed` - Parent seed
+/// * `id` - Id of the stream
+///
+pub fn stream(seed: u64, id: u64) -> Xoroshiro128StarStar {
+    Xoroshiro128StarStar::seed_from_u64(split_seed(seed, id))
+}
+
+/// Stable stream id from a name
+///
+/// # Arguments
+///
+/// * `name` - Stream name, e.g. the name of an agent-set
+///
+pub fn name_id(name: &str) -> u64 {
+    fnv1a(name.as_bytes())
+}
+
+/// Stable stream id from an address
+///
+/// # Arguments
+///
+/// * `address` - Address, e.g. of an agent
+///
+pub fn address_id(address: Address) -> u64 {
+    fnv1a(address.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn test_streams() {
+        let a = stream(101, 1).next_u64();
+        let b = stream(101, 1).next_u64();
+        let c = stream(101, 2).next_u64();
+        let d = stream(102, 1).next_u64();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+    }
+
+    #[test]
+    fn test_ids() {
+        assert_eq!(name_id("agents"), name_id("agents"));
+        assert_ne!(name_id("agents"), name_id("other_agents"));
+        assert_ne!(
+            address_id(Address::ZERO),
+            address_id(Address::repeat_byte(1))
+        );
+    }
+}
@@ -0,0 +1,815 @@
+//! Local JSON-RPC server exposing a simulation
+//!
+//! Serves the state of a paused or finished simulation
+//! [Env] over a local HTTP JSON-RPC endpoint, so tools
+//! that speak the Ethereum JSON-RPC API (e.g. frontends
+//! or analysis scripts) can query the simulation directly.
+//! Requires the `rpc` feature.
+//!
+//! The supported methods are
+//!
+//! * `eth_chainId`
+//! * `eth_blockNumber`
+//! * `eth_getBalance`
+//! * `eth_getStorageAt`
+//! * `eth_getCode`
+//! * `eth_call` (using [Env::direct_call_raw])
+//! * `eth_sendTransaction` (using [Env::direct_execute_raw])
+//! * `eth_getTransactionReceipt`
+//! * `eth_getLogs`
+//!
+//! Requests are always made against the current state
+//! of the environment, and block parameters (other than
+//! in log filters) are ignored.
+//!
+//! Logs are taken from the events of transactions
+//! processed during the simulation (see
+//! [Env::event_history]). As events record the
+//! simulation step rather than a block number, the
+//! step is returned as the `blockNumber` of logs, and
+//! `fromBlock`/`toBlock` filters are applied to steps.
+//! Logs of transactions submitted with
+//! `eth_sendTransaction` are not included, but
+//! are returned in the receipts of the transactions.
+//!
+//! Transactions sent with `eth_sendTransaction` are
+//! executed immediately, and their receipts stored
+//! in [Receipts], so they can be retrieved with
+//! `eth_getTransactionReceipt`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use alloy_primitives::U256;
+//! use verbs_rs::env::{Env, RandomValidator};
+//! use verbs_rs::rpc_server::RpcServer;
+//! use verbs_rs::LocalDB;
+//!
+//! let mut env = Env::<LocalDB, RandomValidator>::init(
+//!     U256::ZERO, U256::ZERO, RandomValidator {}
+//! );
+//!
+//! let server = RpcServer::bind("127.0.0.1:8545").unwrap();
+//! // Blocks until the server is stopped
+//! server.serve(&mut env);
+//! ```
+
+use crate::contract::Event;
+use crate::env::{Env, RevertError, Validator};
+use crate::{DatabaseError, DB};
+use alloy_primitives::{keccak256, Address, Bloom, Bytes, B256, U256};
+use revm::primitives::{ExecutionResult, Log};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Error raised starting a JSON-RPC server
+#[derive(Debug, thiserror::Error)]
+pub enum RpcServerError {
+    #[error("failed to start RPC server: {0}")]
+    Bind(Box<dyn std::error::Error + Send + Sync>),
+    #[error("RPC server is not listening on an IP address")]
+    Address,
+}
+
+/// Receipts of transactions sent with `eth_sendTransaction`
+#[derive(Default)]
+pub struct Receipts(HashMap<B256, Value>);
+
+/// JSON-RPC error returned to clients
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    /// Revert data of reverted calls
+    data: Option<Bytes>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn parse_error(e: impl std::fmt::Display) -> Self {
+        Self::new(-32700, format!("parse error: {}", e))
+    }
+
+    fn invalid_request() -> Self {
+        Self::new(-32600, "invalid request")
+    }
+
+    fn method_not_found(method: &str) -> Self {
+        Self::new(-32601, format!("method {} is not supported", method))
+    }
+
+    fn invalid_params(e: impl std::fmt::Display) -> Self {
+        Self::new(-32602, format!("invalid params: {}", e))
+    }
+
+    fn server(e: impl std::fmt::Display) -> Self {
+        Self::new(-32000, e.to_string())
+    }
+
+    fn reverted(error: RevertError) -> Self {
+        let mut rpc_error = match error.output {
+            Some(reason) => Self::new(3, format!("execution reverted: {}", reason)),
+            None => Self::new(3, "execution reverted"),
+        };
+        rpc_error.data = Some(error.data);
+        rpc_error
+    }
+
+    fn into_value(self) -> Value {
+        match self.data {
+            Some(data) => json!({"code": self.code, "message": self.message, "data": data}),
+            None => json!({"code": self.code, "message": self.message}),
+        }
+    }
+}
+
+/// Transaction fields of `eth_call` and `eth_sendTransaction`
+#[derive(Deserialize)]
+struct TransactionRequest {
+    #[serde(default)]
+    from: Option<Address>,
+    #[serde(default)]
+    to: Option<Address>,
+    #[serde(default)]
+    input: Option<Bytes>,
+    #[serde(default)]
+    data: Option<Bytes>,
+    #[serde(default)]
+    value: Option<U256>,
+}
+
+impl TransactionRequest {
+    fn into_parts(self) -> Result<(Address, Address, Vec<u8>, U256), RpcError> {
+        let to = self
+            .to
+            .ok_or_else(|| RpcError::invalid_params("contract deployment is not supported"))?;
+        Ok((
+            self.from.unwrap_or_default(),
+            to,
+            self.input.or(self.data).unwrap_or_default().to_vec(),
+            self.value.unwrap_or_default(),
+        ))
+    }
+}
+
+/// Single value or list of values in a log filter
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T: PartialEq> OneOrMany<T> {
+    fn contains(&self, value: &T) -> bool {
+        match self {
+            OneOrMany::One(x) => x == value,
+            OneOrMany::Many(x) => x.is_empty() || x.contains(value),
+        }
+    }
+}
+
+/// Log filter of `eth_getLogs`
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct LogFilter {
+    #[serde(default)]
+    from_block: Option<Value>,
+    #[serde(default)]
+    to_block: Option<Value>,
+    #[serde(default)]
+    address: Option<OneOrMany<Address>>,
+    #[serde(default)]
+    topics: Vec<Option<OneOrMany<B256>>>,
+}
+
+impl LogFilter {
+    fn matches(&self, step: usize, log: &Log) -> Result<bool, RpcError> {
+        let step = step as u64;
+        if let Some(from) = parse_block(self.from_block.as_ref())? {
+            if step < from {
+                return Ok(false);
+            }
+        }
+        if let Some(to) = parse_block(self.to_block.as_ref())? {
+            if step > to {
+                return Ok(false);
+            }
+        }
+        if let Some(address) = &self.address {
+            if !address.contains(&log.address) {
+                return Ok(false);
+            }
+        }
+        let topics = log.topics();
+        for (i, filter) in self.topics.iter().enumerate() {
+            if let Some(filter) = filter {
+                match topics.get(i) {
+                    Some(topic) if filter.contains(topic) => {}
+                    _ => return Ok(false),
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Parse a block number, with named blocks treated as unbounded
+fn parse_block(block: Option<&Value>) -> Result<Option<u64>, RpcError> {
+    match block {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) if s == "earliest" => Ok(Some(0)),
+        Some(Value::String(s))
+            if matches!(s.as_str(), "latest" | "pending" | "safe" | "finalized") =>
+        {
+            Ok(None)
+        }
+        Some(v) => {
+            let n: U256 = serde_json::from_value(v.clone()).map_err(RpcError::invalid_params)?;
+            Ok(Some(n.saturating_to()))
+        }
+    }
+}
+
+/// Deserialize a positional parameter
+fn param<T: DeserializeOwned>(params: &[Value], i: usize) -> Result<T, RpcError> {
+    let value = params.get(i).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(RpcError::invalid_params)
+}
+
+fn log_to_value(log: &Log, event: &Event, log_index: usize) -> Value {
+    json!({
+        "address": log.address,
+        "topics": log.topics(),
+        "data": log.data.data,
+        "blockNumber": U256::from(event.step),
+        "transactionIndex": U256::from(event.sequence),
+        "logIndex": U256::from(log_index),
+        "removed": false,
+    })
+}
+
+/// Receipt of a transaction executed with `eth_sendTransaction`
+fn receipt_to_value(
+    hash: B256,
+    from: Address,
+    to: Address,
+    block_number: U256,
+    result: &ExecutionResult,
+) -> Value {
+    let mut bloom = Bloom::default();
+    let logs: Vec<Value> = result
+        .logs()
+        .iter()
+        .enumerate()
+        .map(|(i, log)| {
+            bloom.accrue_log(log);
+            json!({
+                "address": log.address,
+                "topics": log.topics(),
+                "data": log.data.data,
+                "blockNumber": block_number,
+                "blockHash": B256::ZERO,
+                "transactionHash": hash,
+                "transactionIndex": U256::ZERO,
+                "logIndex": U256::from(i),
+                "removed": false,
+            })
+        })
+        .collect();
+
+    json!({
+        "transactionHash": hash,
+        "transactionIndex": U256::ZERO,
+        "blockHash": B256::ZERO,
+        "blockNumber": block_number,
+        "from": from,
+        "to": to,
+        "contractAddress": null,
+        "cumulativeGasUsed": U256::from(result.gas_used()),
+        "gasUsed": U256::from(result.gas_used()),
+        "effectiveGasPrice": U256::ZERO,
+        "logs": logs,
+        "logsBloom": bloom,
+        "status": U256::from(u8::from(result.is_success())),
+        "type": U256::ZERO,
+    })
+}
+
+/// Execution result returned to clients, or the revert error
+fn output_or_revert(result: Result<ExecutionResult, RevertError>) -> Result<Bytes, RpcError> {
+    match result {
+        Ok(result) => Ok(result.into_output().unwrap_or_default()),
+        Err(e) => Err(RpcError::reverted(e)),
+    }
+}
+
+/// Process a single JSON-RPC method call
+fn dispatch<D: DB, V: Validator>(
+    env: &mut Env<D, V>,
+    receipts: &mut Receipts,
+    method: &str,
+    params: &[Value],
+) -> Result<Value, RpcError> {
+    match method {
+        "eth_chainId" => Ok(json!(U256::from(
+            env.evm_state().context.evm.env.cfg.chain_id
+        ))),
+        "eth_blockNumber" => Ok(json!(env.view().block().number)),
+        "eth_getBalance" => {
+            let address: Address = param(params, 0)?;
+            let info = env
+                .view()
+                .db()
+                .basic_ref(address)
+                .map_err(RpcError::server)?;
+            Ok(json!(info.map(|x| x.balance).unwrap_or_default()))
+        }
+        "eth_getStorageAt" => {
+            let address: Address = param(params, 0)?;
+            let slot: U256 = param(params, 1)?;
+            let value = match env.view().db().storage_ref(address, slot) {
+                Ok(value) => value,
+                // Slots missing from the DB are unset
+                Err(DatabaseError::GetStorage(..)) => U256::ZERO,
+                Err(e) => return Err(RpcError::server(e)),
+            };
+            Ok(json!(B256::from(value)))
+        }
+        "eth_getCode" => {
+            let address: Address = param(params, 0)?;
+            let view = env.view();
+            let code = match view.db().basic_ref(address).map_err(RpcError::server)? {
+                Some(info) => match info.code {
+                    Some(code) if !code.is_empty() => code.original_bytes(),
+                    _ => view
+                        .db()
+                        .code_by_hash_ref(info.code_hash)
+                        .map_err(RpcError::server)?
+                        .original_bytes(),
+                },
+                None => Bytes::new(),
+            };
+            Ok(json!(code))
+        }
+        "eth_call" => {
+            let (from, to, data, value) = param::<TransactionRequest>(params, 0)?.into_parts()?;
+            let output = output_or_revert(env.direct_call_raw(from, to, data, value))?;
+            Ok(json!(output))
+        }
+        "eth_sendTransaction" => {
+            let (from, to, data, value) = param::<TransactionRequest>(params, 0)?.into_parts()?;
+            let nonce = env
+                .view()
+                .db()
+                .basic_ref(from)
+                .map_err(RpcError::server)?
+                .map(|x| x.nonce)
+                .unwrap_or_default();
+            let hash = keccak256(
+                [
+                    from.as_slice(),
+                    &nonce.to_be_bytes(),
+                    to.as_slice(),
+                    &value.to_be_bytes::<32>(),
+                    data.as_slice(),
+                ]
+                .concat(),
+            );
+            let result = env
+                .direct_execute_raw(from, to, data, value)
+                .map_err(RpcError::reverted)?;
+            let block_number = env.view().block().number;
+            receipts.0.insert(
+                hash,
+                receipt_to_value(hash, from, to, block_number, &result),
+            );
+            Ok(json!(hash))
+        }
+        "eth_getTransactionReceipt" => {
+            let hash: B256 = param(params, 0)?;
+            Ok(receipts.0.get(&hash).cloned().unwrap_or(Value::Null))
+        }
+        "eth_getLogs" => {
+            let filter: LogFilter = match params.first() {
+                Some(_) => param(params, 0)?,
+                None => LogFilter::default(),
+            };
+            let mut logs = Vec::new();
+            for event in env.event_history.iter().chain(env.last_events.iter()) {
+                for (i, log) in event.logs.iter().enumerate() {
+                    if filter.matches(event.step, log)? {
+                        logs.push(log_to_value(log, event, i));
+                    }
+                }
+            }
+            Ok(Value::Array(logs))
+        }
+        m => Err(RpcError::method_not_found(m)),
+    }
+}
+
+/// Process a JSON-RPC request
+///
+/// Processes a single request, or a batch of requests,
+/// against the current state of the environment,
+/// returning the JSON-RPC response.
+///
+/// # Arguments
+///
+/// * `env` - Simulation environment
+/// * `receipts` - Receipts of sent transactions
+/// * `request` - JSON-RPC request or batch of requests
+///
+pub fn handle_request<D: DB, V: Validator>(
+    env: &mut Env<D, V>,
+    receipts: &mut Receipts,
+    request: &Value,
+) -> Value {
+    match request {
+        Value::Array(requests) => Value::Array(
+            requests
+                .iter()
+                .map(|request| handle_request(env, receipts, request))
+                .collect(),
+        ),
+        request => {
+            let id = request.get("id").cloned().unwrap_or(Value::Null);
+            let result = match (
+                request.get("method").and_then(Value::as_str),
+                request.get("params"),
+            ) {
+                (Some(method), Some(Value::Array(params))) => {
+                    dispatch(env, receipts, method, params)
+                }
+                (Some(method), None | Some(Value::Null)) => dispatch(env, receipts, method, &[]),
+                _ => Err(RpcError::invalid_request()),
+            };
+            match result {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                Err(e) => json!({"jsonrpc": "2.0", "id": id, "error": e.into_value()}),
+            }
+        }
+    }
+}
+
+/// Local HTTP JSON-RPC server
+///
+/// The server can be cloned, and a clone used to
+/// stop a running server from another thread.
+#[derive(Clone)]
+pub struct RpcServer {
+    server: Arc<Server>,
+}
+
+impl RpcServer {
+    /// Start listening on an address
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address to listen on, e.g. `127.0.0.1:8545`
+    ///   (a port of `0` assigns a free port)
+    ///
+    pub fn bind<A: ToSocketAddrs>(address: A) -> Result<Self, RpcServerError> {
+        let server = Server::http(address).map_err(RpcServerError::Bind)?;
+        Ok(RpcServer {
+            server: Arc::new(server),
+        })
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> Result<SocketAddr, RpcServerError> {
+        self.server
+            .server_addr()
+            .to_ip()
+            .ok_or(RpcServerError::Address)
+    }
+
+    /// URL of the JSON-RPC endpoint
+    pub fn url(&self) -> Result<String, RpcServerError> {
+        Ok(format!("http://{}", self.local_addr()?))
+    }
+
+    /// Serve requests against an environment
+    ///
+    /// Blocks the current thread, processing requests
+    /// in the order they are received, until the server
+    /// is stopped using [RpcServer::stop]. Receipts of
+    /// transactions are kept until the server is stopped.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Simulation environment
+    ///
+    pub fn serve<D: DB, V: Validator>(&self, env: &mut Env<D, V>) {
+        let headers = [
+            Header::from_bytes("Content-Type", "application/json").unwrap(),
+            Header::from_bytes("Access-Control-Allow-Origin", "*").unwrap(),
+            Header::from_bytes("Access-Control-Allow-Headers", "*").unwrap(),
+            Header::from_bytes("Access-Control-Allow-Methods", "POST, OPTIONS").unwrap(),
+        ];
+        let mut receipts = Receipts::default();
+
+        for mut request in self.server.incoming_requests() {
+            let body = match request.method() {
+                // CORS pre-flight requests from browsers
+                Method::Options => String::new(),
+                _ => {
+                    let mut body = String::new();
+                    let parsed = request
+                        .as_reader()
+                        .read_to_string(&mut body)
+                        .map_err(RpcError::parse_error)
+                        .and_then(|_| {
+                            serde_json::from_str::<Value>(&body).map_err(RpcError::parse_error)
+                        });
+                    match parsed {
+                        Ok(value) => handle_request(env, &mut receipts, &value).to_string(),
+                        Err(e) => json!({"jsonrpc": "2.0", "id": null, "error": e.into_value()})
+                            .to_string(),
+                    }
+                }
+            };
+            let mut response = Response::from_string(body);
+            for header in headers.iter() {
+                response.add_header(header.clone());
+            }
+            if let Err(e) = request.respond(response) {
+                log::warn!("Failed to send RPC response: {}", e);
+            }
+        }
+    }
+
+    /// Stop the server
+    ///
+    /// Unblocks any thread running [RpcServer::serve].
+    pub fn stop(&self) {
+        self.server.unblock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::RandomValidator;
+    use crate::LocalDB;
+    use revm::db::AccountState;
+    use revm::primitives::{AccountInfo, Bytecode, LogData};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    /// Runtime code storing the call value in slot 0, or
+    /// returning the value of slot 0 if no value is sent
+    /// (reverting if the value is zero)
+    const CODE: [u8; 32] = [
+        0x34, 0x15, 0x60, 0x0a, 0x57, // jump to 0x0a if callvalue is 0
+        0x34, 0x60, 0x00, 0x55, 0x00, // sstore(0, callvalue) and stop
+        0x5b, 0x60, 0x00, 0x54, // sload(0)
+        0x80, 0x15, 0x60, 0x1b, 0x57, // jump to 0x1b if value is 0
+        0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3, // return value
+        0x5b, 0x60, 0x00, 0x80, 0xfd, // revert
+    ];
+
+    fn test_env() -> Env<LocalDB, RandomValidator> {
+        let mut env =
+            Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::from(5), RandomValidator {});
+
+        let code = Bytecode::new_raw(Bytes::from_static(&CODE));
+
+        let db = &mut env.evm_state().context.evm.db;
+        db.insert_account_info(
+            Address::repeat_byte(1),
+            AccountInfo::new(U256::ZERO, 0, code.hash_slow(), code),
+        );
+        db.accounts
+            .get_mut(&Address::repeat_byte(1))
+            .unwrap()
+            .account_state = AccountState::StorageCleared;
+
+        env.last_events.push(Event {
+            success: true,
+            function_selector: [0; 4],
+            logs: vec![Log {
+                address: Address::repeat_byte(1),
+                data: LogData::new_unchecked(vec![B256::repeat_byte(2)], Bytes::new()),
+            }],
+            step: 3,
+            sequence: 0,
+            gas_used: 0,
+            value: U256::ZERO,
+        });
+
+        env
+    }
+
+    fn call(
+        env: &mut Env<LocalDB, RandomValidator>,
+        receipts: &mut Receipts,
+        method: &str,
+        params: Value,
+    ) -> Value {
+        handle_request(
+            env,
+            receipts,
+            &json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}),
+        )
+    }
+
+    #[test]
+    fn test_methods() {
+        let mut env = test_env();
+        let mut receipts = Receipts::default();
+        let contract = Address::repeat_byte(1);
+
+        assert_eq!(
+            call(&mut env, &mut receipts, "eth_blockNumber", json!([]))["result"],
+            "0x5"
+        );
+        assert_eq!(
+            call(
+                &mut env,
+                &mut receipts,
+                "eth_getBalance",
+                json!([Address::ZERO, "latest"])
+            )["result"],
+            json!(U256::from(10u128.pow(22)))
+        );
+
+        // Call reverts while slot 0 is zero
+        let response = call(
+            &mut env,
+            &mut receipts,
+            "eth_call",
+            json!([{"to": contract}, "latest"]),
+        );
+        assert_eq!(response["error"]["code"], 3);
+        assert_eq!(response["error"]["data"], "0x");
+
+        let response = call(
+            &mut env,
+            &mut receipts,
+            "eth_sendTransaction",
+            json!([{"from": Address::ZERO, "to": contract, "value": "0x2a"}]),
+        );
+        let hash = response["result"].clone();
+        assert!(hash.is_string());
+
+        let receipt = call(
+            &mut env,
+            &mut receipts,
+            "eth_getTransactionReceipt",
+            json!([hash]),
+        );
+        assert_eq!(receipt["result"]["transactionHash"], hash);
+        assert_eq!(receipt["result"]["status"], "0x1");
+        assert_eq!(receipt["result"]["blockNumber"], "0x5");
+        assert_eq!(
+            call(
+                &mut env,
+                &mut receipts,
+                "eth_getTransactionReceipt",
+                json!([B256::ZERO])
+            )["result"],
+            Value::Null
+        );
+
+        let response = call(
+            &mut env,
+            &mut receipts,
+            "eth_call",
+            json!([{"to": contract, "data": "0x"}]),
+        );
+        assert_eq!(response["result"], json!(B256::from(U256::from(42))));
+        assert_eq!(
+            call(
+                &mut env,
+                &mut receipts,
+                "eth_getStorageAt",
+                json!([contract, "0x0", "latest"])
+            )["result"],
+            json!(B256::from(U256::from(42)))
+        );
+
+        // Unset slots of accounts without cleared storage
+        env.insert_account(Address::repeat_byte(5), U256::ZERO);
+        env.evm_state()
+            .context
+            .evm
+            .db
+            .accounts
+            .get_mut(&Address::repeat_byte(5))
+            .unwrap()
+            .account_state = AccountState::None;
+        assert_eq!(
+            call(
+                &mut env,
+                &mut receipts,
+                "eth_getStorageAt",
+                json!([Address::repeat_byte(5), "0x1", "latest"])
+            )["result"],
+            json!(B256::ZERO)
+        );
+
+        let code = call(
+            &mut env,
+            &mut receipts,
+            "eth_getCode",
+            json!([contract, "latest"]),
+        );
+        assert!(code["result"].as_str().unwrap().starts_with("0x3415"));
+
+        let response = call(
+            &mut env,
+            &mut receipts,
+            "eth_getLogs",
+            json!([{"address": contract}]),
+        );
+        assert_eq!(response["result"].as_array().unwrap().len(), 1);
+        assert_eq!(response["result"][0]["blockNumber"], "0x3");
+        let response = call(
+            &mut env,
+            &mut receipts,
+            "eth_getLogs",
+            json!([{"fromBlock": "0x4"}]),
+        );
+        assert!(response["result"].as_array().unwrap().is_empty());
+        let response = call(
+            &mut env,
+            &mut receipts,
+            "eth_getLogs",
+            json!([{"topics": [[B256::repeat_byte(3), B256::repeat_byte(2)]]}]),
+        );
+        assert_eq!(response["result"].as_array().unwrap().len(), 1);
+
+        assert_eq!(
+            call(&mut env, &mut receipts, "eth_mine", json!([]))["error"]["code"],
+            -32601
+        );
+    }
+
+    /// Send a request body to a test server, returning the response body
+    fn post(body: Vec<u8>) -> Value {
+        let server = RpcServer::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        let handle = server.clone();
+
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            write!(
+                stream,
+                "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                address,
+                body.len(),
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            handle.stop();
+            response
+        });
+
+        let mut env = test_env();
+        server.serve(&mut env);
+
+        let response = client.join().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn test_http_server() {
+        let body = post(
+            json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber"},
+                {"jsonrpc": "2.0", "id": 2, "method": "eth_getBalance", "params": [Address::ZERO]},
+            ])
+            .to_string()
+            .into_bytes(),
+        );
+
+        assert_eq!(body[0]["id"], 1);
+        assert_eq!(body[0]["result"], "0x5");
+        assert_eq!(body[1]["result"], json!(U256::from(10u128.pow(22))));
+
+        // Bodies that are not UTF-8 or JSON are parse errors
+        let body = post(vec![0xff, 0xfe]);
+        assert_eq!(body["error"]["code"], -32700);
+        let body = post(b"{".to_vec());
+        assert_eq!(body["error"]["code"], -32700);
+        assert_eq!(body["id"], Value::Null);
+    }
+}
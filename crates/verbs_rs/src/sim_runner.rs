@@ -1,21 +1,477 @@
 //! Simulation execution
 //!
-//! The sim-runner function updates the simulation
-//! state (i.e. the agents and EVM) over a fixed
+//! The sim-runner updates the simulation
+//! state (i.e. the agents and EVM) over a
 //! number of steps, where each step represents
 //! a new block on the simulated chain.
 //!
+//! [run] executes a simulation for a fixed number
+//! of steps, [SimRunner] allows the simulation loop
+//! to be customised with hooks, stop conditions
+//! and progress reporting.
+//!
 
 use crate::agent::SimState;
+use crate::checkpoint::{self, CheckpointError};
+use crate::contract::Event;
 use crate::env::{Env, Validator};
+use crate::metrics::BlockMetrics;
+use crate::rng;
 use crate::DB;
-use kdam::tqdm;
-use rand::SeedableRng;
-use rand_xoshiro::Xoroshiro128StarStar;
+use kdam::{tqdm, Bar, BarExt};
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 // Represents blocks updating every 15s
 const BLOCK_INTERVAL: u64 = 15;
 
+/// Function called with access to the environment each step
+///
+/// Called with the environment and the current step.
+pub type Hook<'a, D, V> = Box<dyn FnMut(&mut Env<D, V>, usize) + 'a>;
+
+/// Predicate that will stop the simulation when it returns `true`
+///
+/// Called with the agents, the environment and the
+/// current step, after each step of the simulation.
+pub type StopCondition<'a, S, D, V> = Box<dyn FnMut(&S, &Env<D, V>, usize) -> bool + 'a>;
+
+/// Function called to checkpoint the state of the simulation
+///
+/// Called with the agents, the environment, the random seed
+/// and the step the simulation would resume from.
+type CheckpointFn<'a, S, D, V> =
+    Box<dyn FnMut(&S, &Env<D, V>, u64, usize) -> Result<(), CheckpointError> + 'a>;
+
+/// Reports the progress of a simulation
+pub trait ProgressReporter {
+    /// Called before the first step of the simulation
+    ///
+    /// # Arguments
+    ///
+    /// * `n_steps` - Maximum number of steps of the
+    ///   simulation (if known)
+    ///
+    fn start(&mut self, n_steps: Option<usize>);
+    /// Called after each step of the simulation
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - Step that has just been completed
+    ///
+    fn update(&mut self, step: usize);
+    /// Called when the simulation is finished
+    fn finish(&mut self);
+}
+
+/// Progress reporter displaying a [kdam] progress bar
+#[derive(Default)]
+pub struct ProgressBar {
+    bar: Option<Bar>,
+}
+
+impl ProgressReporter for ProgressBar {
+    fn start(&mut self, n_steps: Option<usize>) {
+        self.bar = Some(match n_steps {
+            Some(n) => tqdm!(total = n),
+            None => tqdm!(),
+        });
+    }
+
+    fn update(&mut self, _step: usize) {
+        if let Some(bar) = &mut self.bar {
+            let _ = bar.update(1);
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(bar) = &mut self.bar {
+            let _ = bar.refresh();
+            eprintln!();
+        }
+        self.bar = None;
+    }
+}
+
+/// Reason a simulation was stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The maximum number of steps were run
+    MaxSteps,
+    /// A stop condition returned `true`
+    Condition,
+    /// The wall-clock time budget was used up
+    TimeBudget,
+}
+
+/// Results and summary of a simulation run
+pub struct SimResult {
+    /// Number of steps run (excluding steps
+    /// before the start step)
+    pub n_steps: usize,
+    /// Reason the simulation stopped
+    pub stop_reason: StopReason,
+    /// Events generated over the course of the run
+    pub events: Vec<Event>,
+    /// Total number of transactions processed
+    pub n_transactions: usize,
+    /// Number of reverted transactions
+    pub n_reverts: usize,
+    /// Statistics of each block of the run
+    pub metrics: Vec<BlockMetrics>,
+    /// Wall-clock time taken by the run
+    pub elapsed: Duration,
+    /// Checkpoints that could not be saved, and the
+    /// step they would have resumed from
+    pub checkpoint_errors: Vec<(usize, CheckpointError)>,
+}
+
+/// Configurable simulation runner
+///
+/// Builder used to configure and run a simulation,
+/// each step of the simulation:
+///
+/// * Calls any pre-block hooks
+/// * Updates the state of all the agents and
+///   collects transactions to be submitted into
+///   the next block
+/// * Updates the block number and timestamp
+/// * Sorts and processes the transactions
+/// * Records the state of the agents
+/// * Calls any post-block hooks
+///
+/// The simulation runs until the maximum number of steps
+/// is reached, the time budget is used up, or any of the
+/// stop conditions returns `true`. At least one of these
+/// should be set.
+///
+/// # Examples
+///
+/// ```
+/// use alloy_primitives::U256;
+/// use std::time::Duration;
+/// use verbs_rs::agent::SimState;
+/// use verbs_rs::env::{Env, RandomValidator};
+/// use verbs_rs::sim_runner::{SimRunner, StopReason};
+/// use verbs_rs::LocalDB;
+///
+/// #[derive(Default)]
+/// struct EmptyState {}
+///
+/// impl SimState for EmptyState {
+///     fn call_agents<D: verbs_rs::DB, V: verbs_rs::env::Validator, R: rand::RngCore>(
+///         &mut self, _rng: &mut R, _env: &mut Env<D, V>
+///     ) -> Vec<verbs_rs::contract::Transaction> {
+///         Vec::default()
+///     }
+///     fn record_agents<D: verbs_rs::DB, V: verbs_rs::env::Validator>(
+///         &mut self, _env: &mut Env<D, V>
+///     ) {}
+/// }
+///
+/// let mut env = Env::<LocalDB, RandomValidator>::init(
+///     U256::ZERO, U256::ZERO, RandomValidator {}
+/// );
+/// let mut state = EmptyState::default();
+///
+/// let result = SimRunner::new(101)
+///     .max_steps(100)
+///     .time_budget(Duration::from_secs(60))
+///     .post_block(|_env, _step| {
+///         // Inspect or modify the environment
+///     })
+///     .run(&mut env, &mut state);
+///
+/// assert_eq!(result.n_steps, 100);
+/// assert_eq!(result.stop_reason, StopReason::MaxSteps);
+/// ```
+pub struct SimRunner<'a, S: SimState, D: DB, V: Validator> {
+    /// Random seed
+    seed: u64,
+    /// Step to start the simulation from
+    start_step: usize,
+    /// Maximum number of steps
+    max_steps: Option<usize>,
+    /// Wall-clock time budget
+    time_budget: Option<Duration>,
+    /// Conditions that stop the simulation
+    stop_conditions: Vec<StopCondition<'a, S, D, V>>,
+    /// Functions called at the start of each step
+    pre_block_hooks: Vec<Hook<'a, D, V>>,
+    /// Functions called at the end of each step
+    post_block_hooks: Vec<Hook<'a, D, V>>,
+    /// Optional progress reporter
+    progress: Option<Box<dyn ProgressReporter + 'a>>,
+    /// Optional checkpoint interval and function
+    checkpoint: Option<(usize, CheckpointFn<'a, S, D, V>)>,
+}
+
+impl<'a, S: SimState, D: DB, V: Validator> SimRunner<'a, S, D, V> {
+    /// Initialise a runner with no stop conditions
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Random seed
+    ///
+    pub fn new(seed: u64) -> Self {
+        SimRunner {
+            seed,
+            start_step: 0,
+            max_steps: None,
+            time_budget: None,
+            stop_conditions: Vec::new(),
+            pre_block_hooks: Vec::new(),
+            post_block_hooks: Vec::new(),
+            progress: None,
+            checkpoint: None,
+        }
+    }
+
+    /// Start the simulation from a given step
+    ///
+    /// Used to resume a simulation, for example from a
+    /// [crate::checkpoint::Checkpoint]. Since the random
+    /// values used in each step are derived from the seed
+    /// and the step number, resuming a simulation from the
+    /// state at a step will reproduce the original run.
+    pub fn start_step(mut self, step: usize) -> Self {
+        self.start_step = step;
+        self
+    }
+
+    /// Stop the simulation after a number of steps
+    ///
+    /// The number of steps includes any steps before
+    /// the start step, i.e. a resumed simulation stops
+    /// at the same step as the original run.
+    pub fn max_steps(mut self, n_steps: usize) -> Self {
+        self.max_steps = Some(n_steps);
+        self
+    }
+
+    /// Stop the simulation after a wall-clock duration
+    ///
+    /// The budget is checked at the end of each step, so
+    /// the current step will always be completed.
+    pub fn time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Stop the simulation when a predicate returns `true`
+    pub fn stop_when<F: FnMut(&S, &Env<D, V>, usize) -> bool + 'a>(mut self, f: F) -> Self {
+        self.stop_conditions.push(Box::new(f));
+        self
+    }
+
+    /// Add a hook called at the start of each step
+    pub fn pre_block<F: FnMut(&mut Env<D, V>, usize) + 'a>(mut self, f: F) -> Self {
+        self.pre_block_hooks.push(Box::new(f));
+        self
+    }
+
+    /// Add a hook called at the end of each step
+    pub fn post_block<F: FnMut(&mut Env<D, V>, usize) + 'a>(mut self, f: F) -> Self {
+        self.post_block_hooks.push(Box::new(f));
+        self
+    }
+
+    /// Report progress using a custom reporter
+    pub fn progress<P: ProgressReporter + 'a>(mut self, reporter: P) -> Self {
+        self.progress = Some(Box::new(reporter));
+        self
+    }
+
+    /// Display a progress bar while running
+    pub fn progress_bar(self) -> Self {
+        self.progress(ProgressBar::default())
+    }
+
+    /// Check if the simulation should stop after a step
+    fn check_stop(&mut self, agents: &S, env: &Env<D, V>, step: usize) -> Option<StopReason> {
+        if self.max_steps.is_some_and(|n| step + 1 >= n) {
+            return Some(StopReason::MaxSteps);
+        }
+        if self
+            .stop_conditions
+            .iter_mut()
+            .any(|f| f(agents, env, step))
+        {
+            return Some(StopReason::Condition);
+        }
+        None
+    }
+
+    /// Run the simulation
+    ///
+    /// Random values used by the agents, the block update
+    /// and the validator each step are drawn from independent
+    /// streams derived from the seed and the step number
+    /// (see [crate::rng]).
+    ///
+    /// Events generated during the run are moved from the
+    /// environment into the returned [SimResult], along with
+    /// statistics of each block (see [BlockMetrics]).
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Reference to an [Env] simulation environment
+    /// * `agents` - Reference to a set of agents implementing
+    ///   the [SimState] trait
+    ///
+    /// # Panics
+    ///
+    /// Panics if no stop conditions have been set.
+    ///
+    pub fn run(&mut self, env: &mut Env<D, V>, agents: &mut S) -> SimResult {
+        assert!(
+            self.max_steps.is_some()
+                || self.time_budget.is_some()
+                || !self.stop_conditions.is_empty(),
+            "Simulation requires at least one stop condition"
+        );
+
+        let start_time = Instant::now();
+
+        env.clear_events();
+        let n_prev_events = env.event_history.len();
+
+        if let Some(p) = &mut self.progress {
+            p.start(self.max_steps.map(|n| n.saturating_sub(self.start_step)));
+        }
+
+        let mut metrics = Vec::<BlockMetrics>::new();
+        let mut checkpoint_errors = Vec::new();
+        let mut step = self.start_step;
+        let mut stop_reason = match self.max_steps {
+            Some(n) if n <= step => Some(StopReason::MaxSteps),
+            _ => None,
+        };
+
+        while stop_reason.is_none() {
+            let step_seed = rng::split_seed(self.seed, step as u64);
+
+            for hook in self.pre_block_hooks.iter_mut() {
+                hook(env, step);
+            }
+            // Move the events from the previous block into historical storage
+            env.clear_events();
+            // Update all agents
+            let agent_start = Instant::now();
+            let transactions =
+                agents.call_agents(&mut rng::stream(step_seed, rng::AGENTS_STREAM), env);
+            let agent_time = agent_start.elapsed();
+            // Update the block-time and number
+            env.increment_time(
+                &mut rng::stream(step_seed, rng::BLOCK_STREAM),
+                BLOCK_INTERVAL,
+            );
+            // Process calls in order
+            let evm_start = Instant::now();
+            env.process_transactions(
+                transactions,
+                &mut rng::stream(step_seed, rng::VALIDATOR_STREAM),
+                step,
+            );
+            let evm_time = evm_start.elapsed();
+            // Collect block statistics
+            let mut block_metrics =
+                BlockMetrics::from_events(step, env.view().block(), &env.last_events);
+            block_metrics.agent_time = agent_time;
+            block_metrics.evm_time = evm_time;
+            metrics.push(block_metrics);
+            // Record data from agents
+            agents.record_agents(env);
+
+            for hook in self.post_block_hooks.iter_mut() {
+                hook(env, step);
+            }
+
+            if let Some(p) = &mut self.progress {
+                p.update(step);
+            }
+
+            if let Some((interval, f)) = &mut self.checkpoint {
+                if (step + 1).is_multiple_of(*interval) {
+                    if let Err(e) = f(agents, env, self.seed, step + 1) {
+                        log::warn!("Failed to save checkpoint at step {}: {}", step + 1, e);
+                        checkpoint_errors.push((step + 1, e));
+                    }
+                }
+            }
+
+            stop_reason = self.check_stop(agents, env, step);
+
+            if stop_reason.is_none()
+                && self
+                    .time_budget
+                    .is_some_and(|budget| start_time.elapsed() >= budget)
+            {
+                stop_reason = Some(StopReason::TimeBudget);
+            }
+
+            step += 1;
+        }
+
+        if let Some(p) = &mut self.progress {
+            p.finish();
+        }
+
+        env.clear_events();
+        let events = env.event_history.split_off(n_prev_events);
+        let n_reverts = events.iter().filter(|e| !e.success).count();
+
+        SimResult {
+            n_steps: step - self.start_step,
+            stop_reason: stop_reason.unwrap(),
+            n_transactions: events.len(),
+            n_reverts,
+            events,
+            metrics,
+            elapsed: start_time.elapsed(),
+            checkpoint_errors,
+        }
+    }
+}
+
+impl<'a, S, D, V> SimRunner<'a, S, D, V>
+where
+    S: SimState + Serialize,
+    D: DB + Serialize,
+    V: Validator + Serialize,
+{
+    /// Periodically checkpoint the simulation to a file
+    ///
+    /// Saves the agents and environment to a file every
+    /// `interval` steps, overwriting the previous checkpoint.
+    /// The simulation can be resumed from the checkpoint
+    /// using [crate::checkpoint::load_checkpoint].
+    /// Checkpoints that cannot be written do not stop the
+    /// simulation, and are reported in
+    /// [SimResult::checkpoint_errors].
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Number of steps between checkpoints
+    /// * `path` - Path of the checkpoint file
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is 0
+    ///
+    pub fn checkpoint_every<P: AsRef<Path> + 'a>(mut self, interval: usize, path: P) -> Self {
+        assert!(interval > 0, "Checkpoint interval should be greater than 0");
+        self.checkpoint = Some((
+            interval,
+            Box::new(move |agents, env, seed, step| {
+                checkpoint::save_checkpoint(&path, seed, step, agents, env)
+            }),
+        ));
+        self
+    }
+}
+
 /// Simulation execution function
 ///
 /// Run a simulation for a fixed number of steps,
@@ -29,6 +485,14 @@ const BLOCK_INTERVAL: u64 = 15;
 /// * Process the transactions
 /// * Record the state of the agents
 ///
+/// Random values used by the agents, the block update
+/// and the validator each step are drawn from independent
+/// streams derived from the seed and the step number
+/// (see [crate::rng]).
+///
+/// See [SimRunner] for a configurable version of
+/// the simulation loop.
+///
 /// # Arguments
 ///
 /// * `env` - Reference to an [Env] simulation environment
@@ -43,18 +507,112 @@ pub fn run<S: SimState, D: DB, V: Validator>(
     seed: u64,
     n_steps: usize,
 ) {
-    let mut rng = Xoroshiro128StarStar::seed_from_u64(seed);
+    let result = SimRunner::new(seed)
+        .max_steps(n_steps)
+        .progress_bar()
+        .run(env, agents);
+    // Events are left in the environment history
+    env.event_history.extend(result.events);
+}
 
-    for i in tqdm!(0..n_steps) {
-        // Move the events from the previous block into historical storage
-        env.clear_events();
-        // Update all agents
-        let transactions = agents.call_agents(&mut rng, env);
-        // Update the block-time and number
-        env.increment_time(&mut rng, BLOCK_INTERVAL);
-        // Process calls in order
-        env.process_transactions(transactions, &mut rng, i);
-        // Record data from agents
-        agents.record_agents(env);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentSet, AgentVec, RecordedAgent};
+    use crate::contract::Transaction;
+    use crate::env::RandomValidator;
+    use crate::LocalDB;
+    use alloy_primitives::{Address, Uint, U256};
+    use rand::RngCore;
+    use rstest::*;
+    use std::cell::Cell;
+
+    struct TestAgent {
+        address: Address,
+        value: u64,
+    }
+
+    impl crate::agent::Agent for TestAgent {
+        fn update<D: DB, V: Validator, R: RngCore>(
+            &mut self,
+            _rng: &mut R,
+            _env: &mut Env<D, V>,
+        ) -> Vec<Transaction> {
+            self.value += 1;
+            Vec::default()
+        }
+
+        fn get_address(&self) -> Address {
+            self.address
+        }
+    }
+
+    impl RecordedAgent<u64> for TestAgent {
+        fn record<D: DB, V: Validator>(&mut self, _env: &mut Env<D, V>) -> u64 {
+            self.value
+        }
+    }
+
+    #[derive(SimState)]
+    struct TestState {
+        agents: AgentVec<u64, TestAgent>,
+    }
+
+    #[fixture]
+    fn env() -> Env<LocalDB, RandomValidator> {
+        Env::<LocalDB, RandomValidator>::init(U256::ZERO, U256::ZERO, RandomValidator {})
+    }
+
+    #[fixture]
+    fn state() -> TestState {
+        TestState {
+            agents: AgentVec::from(vec![TestAgent {
+                address: Address::from(Uint::from(101u128)),
+                value: 0,
+            }]),
+        }
+    }
+
+    #[rstest]
+    fn test_max_steps(mut env: Env<LocalDB, RandomValidator>, mut state: TestState) {
+        let pre_calls = Cell::new(0);
+        let post_calls = Cell::new(0);
+
+        let result = SimRunner::new(101)
+            .max_steps(10)
+            .pre_block(|_, _| pre_calls.set(pre_calls.get() + 1))
+            .post_block(|_, _| post_calls.set(post_calls.get() + 1))
+            .run(&mut env, &mut state);
+
+        assert_eq!(result.n_steps, 10);
+        assert_eq!(result.stop_reason, StopReason::MaxSteps);
+        assert_eq!(pre_calls.get(), 10);
+        assert_eq!(post_calls.get(), 10);
+        assert_eq!(state.agents.get_records().len(), 10);
+        assert_eq!(env.evm_state().context.evm.env.block.number, U256::from(10));
+        assert_eq!(result.metrics.len(), 10);
+        assert_eq!(result.metrics[9].step, 9);
+        assert_eq!(result.metrics[9].block_number, U256::from(10));
+    }
+
+    #[rstest]
+    fn test_stop_condition(mut env: Env<LocalDB, RandomValidator>, mut state: TestState) {
+        let result = SimRunner::new(101)
+            .max_steps(10)
+            .stop_when(|s: &TestState, _, _| s.agents.get_records().len() == 5)
+            .run(&mut env, &mut state);
+
+        assert_eq!(result.n_steps, 5);
+        assert_eq!(result.stop_reason, StopReason::Condition);
+    }
+
+    #[rstest]
+    fn test_time_budget(mut env: Env<LocalDB, RandomValidator>, mut state: TestState) {
+        let result = SimRunner::new(101)
+            .time_budget(Duration::ZERO)
+            .run(&mut env, &mut state);
+
+        assert_eq!(result.n_steps, 1);
+        assert_eq!(result.stop_reason, StopReason::TimeBudget);
     }
 }
@@ -0,0 +1,283 @@
+//! Differences between DB states
+//!
+//! Compares the accounts of two [DB] states, e.g.
+//! the state before and after a block of simulation
+//! transactions (see
+//! [crate::env::Env::process_transactions_with_diff]),
+//! reporting the changed balance, nonce, code and
+//! storage of each account.
+//!
+//! # Examples
+//!
+//! ```
+//! use alloy_primitives::{Address, U256};
+//! use revm::primitives::AccountInfo;
+//! use verbs_rs::{state_diff::StateDiff, LocalDB, DB};
+//!
+//! let mut before = LocalDB::new();
+//! before.insert_account_info(
+//!     Address::repeat_byte(1), AccountInfo::from_balance(U256::from(100))
+//! );
+//!
+//! let mut after = before.clone();
+//! after.insert_account_info(
+//!     Address::repeat_byte(1), AccountInfo::from_balance(U256::from(50))
+//! );
+//!
+//! let diff = StateDiff::new(&before, &after).unwrap();
+//! let balance = diff.accounts[&Address::repeat_byte(1)].balance.as_ref().unwrap();
+//!
+//! assert_eq!(balance.before, U256::from(100));
+//! assert_eq!(balance.after, U256::from(50));
+//! ```
+
+use crate::{DatabaseError, DB};
+use alloy_primitives::{Address, B256, U256};
+use revm::primitives::{AccountInfo, KECCAK_EMPTY};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Value before and after a change
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Change<T> {
+    /// Initial value
+    pub before: T,
+    /// Updated value
+    pub after: T,
+}
+
+impl<T: PartialEq> Change<T> {
+    /// Create a change if the values differ
+    fn from_values(before: T, after: T) -> Option<Self> {
+        match before == after {
+            true => None,
+            false => Some(Change { before, after }),
+        }
+    }
+}
+
+/// Changes to a single account
+///
+/// Accounts that do not exist are treated as having
+/// zero balance and nonce, and no code or storage.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct AccountDiff {
+    /// Change of account balance
+    pub balance: Option<Change<U256>>,
+    /// Change of account nonce
+    pub nonce: Option<Change<u64>>,
+    /// Change of the hash of the account code
+    pub code_hash: Option<Change<B256>>,
+    /// Changed storage slots
+    pub storage: BTreeMap<U256, Change<U256>>,
+}
+
+impl AccountDiff {
+    /// Whether the account is unchanged
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_none()
+            && self.nonce.is_none()
+            && self.code_hash.is_none()
+            && self.storage.is_empty()
+    }
+}
+
+/// Changes between two DB states
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StateDiff {
+    /// Changes of each changed account
+    pub accounts: BTreeMap<Address, AccountDiff>,
+}
+
+impl StateDiff {
+    /// Compare two DB states
+    ///
+    /// Compares all the accounts and storage slots
+    /// stored in either DB. Values missing from one
+    /// of the DBs are read using [revm::DatabaseRef],
+    /// so values lazily loaded by a [crate::ForkDb]
+    /// are compared against the fork, rather than
+    /// being reported as changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - Initial DB state
+    /// * `after` - Updated DB state
+    ///
+    pub fn new<A: DB, B: DB>(before: &A, after: &B) -> Result<Self, DatabaseError> {
+        let addresses: BTreeSet<&Address> = before
+            .accounts()
+            .keys()
+            .chain(after.accounts().keys())
+            .collect();
+
+        let mut accounts = BTreeMap::new();
+
+        for address in addresses {
+            let info_before = before.basic_ref(*address)?;
+            let info_after = after.basic_ref(*address)?;
+            // Accounts missing from a DB have empty storage
+            let exists_before = info_before.is_some() || before.accounts().contains_key(address);
+            let exists_after = info_after.is_some() || after.accounts().contains_key(address);
+            let info_before = info_before.unwrap_or_else(empty_account);
+            let info_after = info_after.unwrap_or_else(empty_account);
+
+            let indices: BTreeSet<&U256> = before
+                .accounts()
+                .get(address)
+                .into_iter()
+                .chain(after.accounts().get(address))
+                .flat_map(|a| a.storage.keys())
+                .collect();
+
+            let mut storage = BTreeMap::new();
+            for index in indices {
+                let value_before = match exists_before {
+                    true => before.storage_ref(*address, *index)?,
+                    false => U256::ZERO,
+                };
+                let value_after = match exists_after {
+                    true => after.storage_ref(*address, *index)?,
+                    false => U256::ZERO,
+                };
+                let change = Change::from_values(value_before, value_after);
+                if let Some(change) = change {
+                    storage.insert(*index, change);
+                }
+            }
+
+            let account = AccountDiff {
+                balance: Change::from_values(info_before.balance, info_after.balance),
+                nonce: Change::from_values(info_before.nonce, info_after.nonce),
+                code_hash: Change::from_values(info_before.code_hash, info_after.code_hash),
+                storage,
+            };
+
+            if !account.is_empty() {
+                accounts.insert(*address, account);
+            }
+        }
+
+        Ok(StateDiff { accounts })
+    }
+
+    /// Whether the states are the same
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
+
+fn empty_account() -> AccountInfo {
+    AccountInfo {
+        code_hash: KECCAK_EMPTY,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ForkDb, LocalDB, MockBackend};
+    use revm::primitives::{Bytecode, Bytes};
+    use revm::Database;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_local_diff() {
+        let mut before = LocalDB::new();
+        before.insert_account_info(
+            Address::repeat_byte(1),
+            AccountInfo::from_balance(U256::from(1)),
+        );
+        before
+            .insert_account_storage(Address::repeat_byte(1), U256::from(1), U256::from(2))
+            .unwrap();
+        before
+            .insert_account_storage(Address::repeat_byte(1), U256::from(2), U256::from(3))
+            .unwrap();
+
+        let mut after = before.clone();
+        assert!(StateDiff::new(&before, &after).unwrap().is_empty());
+
+        after
+            .insert_account_storage(Address::repeat_byte(1), U256::from(2), U256::from(4))
+            .unwrap();
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x00]));
+        after.insert_account_info(
+            Address::repeat_byte(2),
+            AccountInfo::new(U256::from(5), 1, code.hash_slow(), code),
+        );
+        after
+            .insert_account_storage(Address::repeat_byte(2), U256::from(1), U256::from(6))
+            .unwrap();
+
+        let diff = StateDiff::new(&before, &after).unwrap();
+
+        assert_eq!(diff.accounts.len(), 2);
+        assert_eq!(
+            diff.accounts[&Address::repeat_byte(1)],
+            AccountDiff {
+                storage: BTreeMap::from([(
+                    U256::from(2),
+                    Change {
+                        before: U256::from(3),
+                        after: U256::from(4)
+                    }
+                )]),
+                ..Default::default()
+            }
+        );
+
+        let created = &diff.accounts[&Address::repeat_byte(2)];
+        assert_eq!(created.balance.as_ref().unwrap().after, U256::from(5));
+        assert_eq!(created.nonce.as_ref().unwrap().before, 0);
+        assert_eq!(created.code_hash.as_ref().unwrap().before, KECCAK_EMPTY);
+        assert_eq!(
+            created.storage[&U256::from(1)],
+            Change {
+                before: U256::ZERO,
+                after: U256::from(6)
+            }
+        );
+
+        // Removed accounts are also compared against empty storage
+        let diff = StateDiff::new(&after, &before).unwrap();
+        assert_eq!(
+            diff.accounts[&Address::repeat_byte(2)].storage[&U256::from(1)].after,
+            U256::ZERO
+        );
+    }
+
+    #[test]
+    fn test_fork_diff() {
+        let mut backend = MockBackend::new(U256::from(100), U256::from(1_700_000_000));
+        backend.insert_account(
+            Address::repeat_byte(1),
+            AccountInfo::from_balance(U256::from(1)),
+        );
+        backend.insert_storage(Address::repeat_byte(1), U256::from(1), U256::from(2));
+
+        let before = ForkDb::from_backend(Arc::new(backend));
+        let mut after = before.clone();
+
+        // Values loaded from the fork are unchanged
+        after.basic(Address::repeat_byte(1)).unwrap();
+        after
+            .storage(Address::repeat_byte(1), U256::from(1))
+            .unwrap();
+        assert!(StateDiff::new(&before, &after).unwrap().is_empty());
+
+        after
+            .insert_account_storage(Address::repeat_byte(1), U256::from(1), U256::from(3))
+            .unwrap();
+        let diff = StateDiff::new(&before, &after).unwrap();
+
+        assert_eq!(
+            diff.accounts[&Address::repeat_byte(1)].storage[&U256::from(1)],
+            Change {
+                before: U256::from(2),
+                after: U256::from(3)
+            }
+        );
+    }
+}
@@ -1,6 +1,6 @@
 //! Numerical and Ethereum utilities
 
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{address, hex_literal::hex, Address, B256, U256};
 use revm::primitives::bitvec::macros::internal::funty::Fundamental;
 
 /// Create a revm address from a hex string.
@@ -106,6 +106,33 @@ pub fn constructor_data(bytecode_hex: &str, mut args: Option<Vec<u8>>) -> Vec<u8
     bytecode
 }
 
+/// Address of the deterministic deployment proxy
+///
+/// Canonical CREATE2 factory (deployed on most chains)
+/// that deploys the init code passed as call data after
+/// a 32 byte salt.
+pub const CREATE2_DEPLOYER: Address = address!("4e59b44847b379578588920cA78FbF26c0B4956C");
+
+/// Runtime code of the deterministic deployment proxy
+pub const CREATE2_DEPLOYER_CODE: [u8; 69] = hex!(
+    "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe0"
+    "3601600081602082378035828234f58015156039578182fd5b8082525050506014600cf3"
+);
+
+/// Address of a contract deployed with CREATE2
+///
+/// # Arguments
+///
+/// * `deployer` - Address of the deploying contract, e.g.
+///   [CREATE2_DEPLOYER]
+/// * `salt` - CREATE2 salt
+/// * `init_code` - Deployment bytecode, and abi encoded
+///   constructor arguments if required
+///
+pub fn create2_address(deployer: Address, salt: B256, init_code: &[u8]) -> Address {
+    deployer.create2_from_code(salt, init_code)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -153,6 +180,15 @@ mod tests {
         assert_approx_eq!(z, expected_b);
     }
 
+    #[test]
+    fn create2_deployment_address() {
+        // Example 1 of EIP-1014
+        assert_eq!(
+            create2_address(Address::ZERO, B256::ZERO, &[0x00]),
+            address!("4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38")
+        );
+    }
+
     #[test]
     fn div_out_of_bounds() {
         let x = U256::from(10).pow(U256::from(30));
@@ -1,10 +1,11 @@
 use super::snapshot::{
-    create_py_snapshot, load_block_env, load_cache, load_snapshot, PyDbState, PyRequests,
+    create_py_snapshot, load_block_env, load_snapshot, py_to_request_cache, PyDbState, PyRequests,
+    PySnapshot,
 };
 use crate::types::{
     event_to_py, result_to_py, PyAddress, PyEvent, PyExecutionResult, PyTransaction,
 };
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{Address, B256, U256};
 use pyo3::prelude::*;
 use rand::seq::SliceRandom;
 use rand::SeedableRng;
@@ -13,7 +14,7 @@ use rand_xoshiro::Xoroshiro128StarStar;
 use std::mem;
 use verbs_rs::contract::Transaction;
 use verbs_rs::env::{Env, RevertError, Validator};
-use verbs_rs::{ForkDb, LocalDB, DB};
+use verbs_rs::{AnvilState, AnvilStateError, ForkDb, LocalDB, Snapshot, SnapshotError, DB};
 
 // Represents blocks updating every 15s
 const BLOCK_INTERVAL: u64 = 15;
@@ -45,7 +46,33 @@ impl<V: Validator> BaseEnv<LocalDB, V> {
         }
     }
 
-    pub fn from_snapshot(seed: u64, snapshot: PyDbState, validator: V) -> Self {
+    pub fn from_snapshot(
+        seed: u64,
+        snapshot: PySnapshot,
+        validator: V,
+    ) -> Result<Self, SnapshotError> {
+        match snapshot {
+            PySnapshot::Bytes(b) => Self::from_snapshot_bytes(seed, b.as_bytes(), validator),
+            PySnapshot::State(s) => Ok(Self::from_snapshot_state(seed, s, validator)),
+        }
+    }
+
+    pub fn from_snapshot_bytes(
+        seed: u64,
+        bytes: &[u8],
+        validator: V,
+    ) -> Result<Self, SnapshotError> {
+        let snapshot = Snapshot::from_bytes(bytes)?;
+
+        Ok(BaseEnv {
+            env: Env::<LocalDB, V>::from_snapshot(snapshot, validator),
+            call_queue: Vec::new(),
+            rng: Xoroshiro128StarStar::seed_from_u64(seed),
+            step: 0,
+        })
+    }
+
+    pub fn from_snapshot_state(seed: u64, snapshot: PyDbState, validator: V) -> Self {
         let block_env = load_block_env(&snapshot);
 
         let mut env = Env::<LocalDB, V>::init(block_env.timestamp, block_env.number, validator);
@@ -62,12 +89,8 @@ impl<V: Validator> BaseEnv<LocalDB, V> {
     }
 
     pub fn from_cache(seed: u64, requests: PyRequests, validator: V) -> Self {
-        let mut env = Env::<LocalDB, V>::init(
-            U256::try_from(requests.0).unwrap(),
-            U256::try_from(requests.1).unwrap(),
-            validator,
-        );
-        load_cache(requests, &mut env.evm_state().context.evm.db);
+        let cache = py_to_request_cache(requests);
+        let env = Env::<LocalDB, V>::from_request_cache(&cache, validator);
 
         BaseEnv {
             env,
@@ -125,6 +148,14 @@ impl<D: DB, V: Validator> BaseEnv<D, V> {
         create_py_snapshot(py, &self.env)
     }
 
+    pub fn export_state_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        Snapshot::from_env(&self.env).to_bytes()
+    }
+
+    pub fn export_anvil_state(&self) -> Result<String, AnvilStateError> {
+        AnvilState::from_env(&self.env).to_json()
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn submit_transaction(
         &mut self,
@@ -178,6 +209,40 @@ impl<D: DB, V: Validator> BaseEnv<D, V> {
             .deploy_contract(Address::from_slice(&deployer), contract_name, bytecode)
     }
 
+    pub fn deploy_contract_at(
+        &mut self,
+        deployer: PyAddress,
+        contract_name: &str,
+        bytecode: Vec<u8>,
+        address: PyAddress,
+    ) -> Address {
+        self.env.deploy_contract_at(
+            Address::from_slice(&deployer),
+            contract_name,
+            bytecode,
+            Address::from_slice(&address),
+        )
+    }
+
+    pub fn deploy_contract_create2(
+        &mut self,
+        deployer: PyAddress,
+        contract_name: &str,
+        salt: Vec<u8>,
+        bytecode: Vec<u8>,
+    ) -> Address {
+        self.env.deploy_contract_create2(
+            Address::from_slice(&deployer),
+            contract_name,
+            B256::from_slice(&salt),
+            bytecode,
+        )
+    }
+
+    pub fn insert_code(&mut self, address: PyAddress, code: Vec<u8>) {
+        self.env.insert_code(Address::from_slice(&address), code)
+    }
+
     pub fn create_account(&mut self, address: PyAddress, start_balance: u128) {
         self.env
             .insert_account(Address::from_slice(&address), U256::from(start_balance))
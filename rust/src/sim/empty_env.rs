@@ -1,5 +1,3 @@
-use super::snapshot::PyDbState;
-
 use super::base_env::BaseEnv;
 use super::interface_macro::create_interface;
 use super::snapshot;
@@ -29,6 +27,8 @@ use verbs_rs::LocalDB;
 ///    env = EmptyEnvRandom(101)
 ///    # Or initialise from a snapshot
 ///    env = EmptyEnvRandom(101, snapshot=snapshot)
+///    # Or from a binary snapshot
+///    env = EmptyEnvRandom(101, snapshot=snapshot_bytes)
 ///    # Or load a cache from a previous forked run
 ///    env = EmptyEnvRandom(101, cache=cache)
 ///    ...
@@ -42,7 +42,7 @@ create_interface!(
     #[pyo3(signature = (seed, snapshot=None, cache=None))]
     pub fn new(
         seed: u64,
-        snapshot: Option<PyDbState>,
+        snapshot: Option<snapshot::PySnapshot>,
         cache: Option<snapshot::PyRequests>,
     ) -> PyResult<Self> {
         let validator = RandomValidator {};
@@ -54,9 +54,11 @@ create_interface!(
             (None, Some(c)) => Ok(Self(BaseEnv::<LocalDB, RandomValidator>::from_cache(
                 seed, c, validator,
             ))),
-            (Some(s), None) => Ok(Self(BaseEnv::<LocalDB, RandomValidator>::from_snapshot(
-                seed, s, validator,
-            ))),
+            (Some(s), None) => {
+                BaseEnv::<LocalDB, RandomValidator>::from_snapshot(seed, s, validator)
+                    .map(Self)
+                    .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+            }
             (Some(_), Some(_)) => Err(PyRuntimeError::new_err(
                 "Env must be initialised from either a snapshot or a cache but not both.",
             )),
@@ -89,6 +91,8 @@ create_interface!(
 ///    env = EmptyEnvGasPriority(101)
 ///    # Or initialise from a snapshot
 ///    env = EmptyEnvGasPriority(101, snapshot=snapshot)
+///    # Or from a binary snapshot
+///    env = EmptyEnvGasPriority(101, snapshot=snapshot_bytes)
 ///    # Or load a cache from a previous forked run
 ///    env = EmptyEnvGasPriority(101, cache=cache)
 ///    ...
@@ -102,7 +106,7 @@ create_interface!(
     #[pyo3(signature = (seed, snapshot=None, cache=None))]
     pub fn new(
         seed: u64,
-        snapshot: Option<PyDbState>,
+        snapshot: Option<snapshot::PySnapshot>,
         cache: Option<snapshot::PyRequests>,
     ) -> PyResult<Self> {
         let validator = GasPriorityValidator {};
@@ -114,9 +118,11 @@ create_interface!(
             (None, Some(c)) => Ok(Self(BaseEnv::<LocalDB, GasPriorityValidator>::from_cache(
                 seed, c, validator,
             ))),
-            (Some(s), None) => Ok(Self(
-                BaseEnv::<LocalDB, GasPriorityValidator>::from_snapshot(seed, s, validator),
-            )),
+            (Some(s), None) => {
+                BaseEnv::<LocalDB, GasPriorityValidator>::from_snapshot(seed, s, validator)
+                    .map(Self)
+                    .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+            }
             (Some(_), Some(_)) => Err(PyRuntimeError::new_err(
                 "Env must be initialised from either a snapshot or a cache but not both.",
             )),
@@ -18,6 +18,45 @@ macro_rules! create_interface {
                 Ok(self.0.export_state(py))
             }
 
+            /// Export a binary snap shot of the EVM state and block parameters
+            ///
+            /// Encodes the EVM storage and state of the current block in a
+            /// versioned binary format. The bytes can be saved to a file, and
+            /// used to initialise new simulation environments (in place of
+            /// a snapshot exported by ``export_snapshot``).
+            ///
+            /// Returns
+            /// -------
+            /// bytes
+            ///     Binary encoded snapshot.
+            ///
+            pub fn export_snapshot_bytes<'a>(&self, py: Python<'a>) -> PyResult<&'a PyBytes> {
+                let bytes = self
+                    .0
+                    .export_state_bytes()
+                    .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+                Ok(PyBytes::new(py, bytes.as_slice()))
+            }
+
+            /// Export the EVM state as an anvil state dump
+            ///
+            /// Encodes the accounts, code, storage and current block of the
+            /// simulation as JSON in the format used by anvil. The JSON can be
+            /// saved to a file and loaded into anvil (using ``--load-state``)
+            /// to inspect the state of the simulation with standard Ethereum
+            /// tooling.
+            ///
+            /// Returns
+            /// -------
+            /// str
+            ///     JSON encoded anvil state dump.
+            ///
+            pub fn export_anvil_state(&self) -> PyResult<String> {
+                self.0
+                    .export_anvil_state()
+                    .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
+            }
+
             /// Current step (i.e. block) of the simulation
             ///
             /// Returns
@@ -200,6 +239,113 @@ macro_rules! create_interface {
                 ))
             }
 
+            /// deploy_contract_at(deployer: bytes, contract_name: str, bytecode: bytes, address: bytes) -> bytes
+            ///
+            /// Deploy a contract to a given address
+            ///
+            /// Runs the contract constructor as if the contract
+            /// were deployed at the given address, e.g. to deploy
+            /// contracts at canonical addresses.
+            ///
+            /// Parameters
+            /// ----------
+            /// deployer: bytes
+            ///     Byte encoded address of the deployer.
+            /// contract_name: str
+            ///     Name of the contract to deploy, only used for
+            ///     logging/debugging purposes.
+            /// bytecode: bytes
+            ///     Contract deployment bytecode and ABI encoded
+            ///     constructor arguments.
+            /// address: bytes
+            ///     Byte encoded address to deploy the contract to.
+            ///
+            /// Returns
+            /// -------
+            /// bytes
+            ///     Byte encoded address that contract is deployed to.
+            ///
+            pub fn deploy_contract_at<'a>(
+                &mut self,
+                py: Python<'a>,
+                deployer: PyAddress,
+                contract_name: &str,
+                bytecode: Vec<u8>,
+                address: PyAddress,
+            ) -> PyResult<&'a PyBytes> {
+                Ok(PyBytes::new(
+                    py,
+                    self.0
+                        .deploy_contract_at(deployer, contract_name, bytecode, address)
+                        .as_slice(),
+                ))
+            }
+
+            /// deploy_contract_create2(deployer: bytes, contract_name: str, salt: bytes, bytecode: bytes) -> bytes
+            ///
+            /// Deploy a contract using CREATE2
+            ///
+            /// Deploys a contract from the deterministic deployment
+            /// proxy (``0x4e59b44847b379578588920cA78FbF26c0B4956C``),
+            /// which is inserted if it has not been deployed.
+            ///
+            /// Parameters
+            /// ----------
+            /// deployer: bytes
+            ///     Byte encoded address sending the deployment.
+            /// contract_name: str
+            ///     Name of the contract to deploy, only used for
+            ///     logging/debugging purposes.
+            /// salt: bytes
+            ///     32 byte CREATE2 salt.
+            /// bytecode: bytes
+            ///     Contract deployment bytecode and ABI encoded
+            ///     constructor arguments.
+            ///
+            /// Returns
+            /// -------
+            /// bytes
+            ///     Byte encoded address that contract is deployed to.
+            ///
+            pub fn deploy_contract_create2<'a>(
+                &mut self,
+                py: Python<'a>,
+                deployer: PyAddress,
+                contract_name: &str,
+                salt: Vec<u8>,
+                bytecode: Vec<u8>,
+            ) -> PyResult<&'a PyBytes> {
+                if salt.len() != 32 {
+                    return Err(pyo3::exceptions::PyValueError::new_err(
+                        "Salt should be 32 bytes",
+                    ));
+                }
+                Ok(PyBytes::new(
+                    py,
+                    self.0
+                        .deploy_contract_create2(deployer, contract_name, salt, bytecode)
+                        .as_slice(),
+                ))
+            }
+
+            /// insert_code(address: bytes, code: bytes)
+            ///
+            /// Place runtime code at an address
+            ///
+            /// Sets the code of an account without running a
+            /// constructor, keeping its balance and nonce.
+            ///
+            /// Parameters
+            /// ----------
+            /// address: bytes
+            ///     Byte encoded address to place the code at.
+            /// code: bytes
+            ///     Contract runtime bytecode.
+            ///
+            pub fn insert_code(&mut self, address: PyAddress, code: Vec<u8>) {
+                self.0.insert_code(address, code)
+            }
+
             /// create_account(address: bytes, start_balance: int)
             ///
             /// Create an account
@@ -1,5 +1,5 @@
 use alloy_primitives::{Address, Bytes, LogData, B256, U256};
-use pyo3::{types::PyBytes, Python};
+use pyo3::{types::PyBytes, FromPyObject, Python};
 use revm::{
     db::{AccountState, DbAccount},
     primitives::{AccountInfo, BlobExcessGasAndPrice, BlockEnv, Bytecode, Log},
@@ -61,6 +61,14 @@ pub type PyDbState<'a> = (
     Vec<(&'a PyBytes, &'a PyBytes)>,
 );
 
+/// Snapshot passed from Python, either as binary
+/// encoded bytes or as a tuple of DB state
+#[derive(FromPyObject)]
+pub enum PySnapshot<'a> {
+    Bytes(&'a PyBytes),
+    State(PyDbState<'a>),
+}
+
 fn account_state_to_int(account_state: &AccountState) -> u8 {
     match account_state {
         AccountState::NotExisting => 0,
@@ -139,10 +147,17 @@ pub fn create_py_snapshot<'a, D: DB, V: Validator>(
             .map(|x| (x.excess_blob_gas, x.blob_gasprice)),
     );
 
-    let db = &env.evm_state.as_ref().unwrap().context.evm.db;
+    let db = env
+        .evm_state
+        .as_ref()
+        .unwrap()
+        .context
+        .evm
+        .db
+        .export_state();
 
     let accounts: Vec<(&'a PyBytes, PyDbAccount<'a>)> = db
-        .accounts()
+        .accounts
         .iter()
         .map(|(k, v)| {
             (
@@ -165,7 +180,7 @@ pub fn create_py_snapshot<'a, D: DB, V: Validator>(
         .collect();
 
     let contracts: Vec<(&'a PyBytes, &'a PyBytes)> = db
-        .contracts()
+        .contracts
         .iter()
         .map(|(k, v)| {
             (
@@ -176,7 +191,7 @@ pub fn create_py_snapshot<'a, D: DB, V: Validator>(
         .collect();
 
     let logs: Vec<PyLog> = db
-        .logs()
+        .logs
         .iter()
         .map(|x| {
             (
@@ -191,7 +206,7 @@ pub fn create_py_snapshot<'a, D: DB, V: Validator>(
         .collect();
 
     let block_hashes: Vec<(&'a PyBytes, &'a PyBytes)> = db
-        .block_hashes()
+        .block_hashes
         .iter()
         .map(|(k, v)| {
             (
@@ -204,23 +219,26 @@ pub fn create_py_snapshot<'a, D: DB, V: Validator>(
     (block_env, accounts, contracts, logs, block_hashes)
 }
 
-pub fn load_cache(cache: PyRequests, db: &mut LocalDB) {
-    for (address, info) in cache.2.into_iter() {
-        db.accounts.insert(
-            Address::from_slice(address.as_bytes()),
-            DbAccount {
-                info: py_to_info(info),
-                ..Default::default()
-            },
-        );
-    }
-
-    for (address, idx, value) in cache.3.into_iter() {
-        let address = Address::from_slice(address.as_bytes());
-        db.accounts.get_mut(&address).unwrap().storage.insert(
-            U256::from_le_slice(idx.as_bytes()),
-            U256::from_le_slice(value.as_bytes()),
-        );
+pub fn py_to_request_cache(cache: PyRequests) -> RequestCache {
+    RequestCache {
+        start_timestamp: U256::from(cache.0),
+        start_block_number: U256::from(cache.1),
+        accounts: cache
+            .2
+            .into_iter()
+            .map(|(address, info)| (Address::from_slice(address.as_bytes()), py_to_info(info)))
+            .collect(),
+        storage: cache
+            .3
+            .into_iter()
+            .map(|(address, idx, value)| {
+                (
+                    Address::from_slice(address.as_bytes()),
+                    U256::from_le_slice(idx.as_bytes()),
+                    U256::from_le_slice(value.as_bytes()),
+                )
+            })
+            .collect(),
     }
 }
 
@@ -79,9 +79,12 @@ def batch_run(
         List of dictionaries containing simulation
         parameters. These parameters will be passed as
         keyword arguments to the ``sim_func`` function.
-    snapshot: tuple , optional
+    snapshot: tuple or bytes, optional
         Optional snapshot used to initialise the
         simulation environment for each execution.
+        Either a snapshot exported by ``export_snapshot``
+        or a binary snapshot exported by
+        ``export_snapshot_bytes``.
     cache: verbs.types.Cache, optional
         Optional cache used to initialise the simulation
         environment for each execution.
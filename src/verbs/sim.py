@@ -168,8 +168,8 @@ class Sim:
             value is an empty list, allowing agents to be pushed
             after the simulation is initialised.
         snapshot
-            Optional snapshot used to initialise the simulation
-            environment.
+            Optional snapshot (tuple or bytes) used to initialise
+            the simulation environment.
         cache:
             Optional cache used to initialise the simulation
             environment.
@@ -32,6 +32,30 @@ def test_deploy_and_call_contract(env, bytecode, constructor_args, test_abi):
     assert result_2[0] == 202
 
 
+def test_deploy_at_address(env, bytecode, constructor_args, test_abi):
+    admin = utils.int_to_address(99)
+    env.create_account(admin, int(1e19))
+    a = abi.get_abi("ABI", test_abi)
+
+    target = utils.int_to_address(1234)
+    address = env.deploy_contract_at(
+        admin, "test_contract", bytecode + constructor_args, target
+    )
+    assert address == target
+
+    result, _, _ = a.getValue.call(env, admin, address, [])
+    assert result[0] == INITIAL_VALUE
+
+    salt = bytes(32)
+    create2_address = env.deploy_contract_create2(
+        admin, "test_contract", salt, bytecode + constructor_args
+    )
+    assert create2_address != address
+
+    result, _, _ = a.getValue.call(env, admin, create2_address, [])
+    assert result[0] == INITIAL_VALUE
+
+
 def test_sim_update(env, bytecode, constructor_args, test_abi):
     # Add deployment account
     admin = utils.int_to_address(99)
@@ -1,3 +1,5 @@
+import json
+
 from verbs import abi, envs, sim, utils
 
 
@@ -33,3 +35,57 @@ def test_snapshot_is_consistent(env, bytecode, constructor_args, test_abi, agent
     assert sorted(snapshot[4], key=lambda x: x[0]) == sorted(
         new_snapshot[4], key=lambda x: x[0]
     )
+
+
+def test_binary_snapshot_is_consistent(
+    env, bytecode, constructor_args, test_abi, agent_type
+):
+
+    test_abi = abi.get_abi("TEST", test_abi)
+
+    admin = utils.int_to_address(99)
+    env.create_account(admin, int(1e19))
+
+    address = env.deploy_contract(admin, "test_contract", bytecode + constructor_args)
+
+    agent = agent_type(1, address, test_abi)
+
+    sim_runner = sim.Sim(101, env, [agent])
+
+    sim_runner.run(10)
+
+    snapshot = env.export_snapshot()
+    snapshot_bytes = env.export_snapshot_bytes()
+
+    assert isinstance(snapshot_bytes, bytes)
+
+    env_from_bytes = envs.EmptyEnvRandom(101, snapshot_bytes)
+    new_snapshot = env_from_bytes.export_snapshot()
+
+    assert snapshot[0] == new_snapshot[0]
+
+    assert sorted(snapshot[1], key=lambda x: x[0]) == sorted(
+        new_snapshot[1], key=lambda x: x[0]
+    )
+    assert sorted(snapshot[2], key=lambda x: x[0]) == sorted(
+        new_snapshot[2], key=lambda x: x[0]
+    )
+    assert snapshot[3] == new_snapshot[3]
+    assert sorted(snapshot[4], key=lambda x: x[0]) == sorted(
+        new_snapshot[4], key=lambda x: x[0]
+    )
+
+
+def test_anvil_state_export(env, bytecode, constructor_args):
+
+    admin = utils.int_to_address(99)
+    env.create_account(admin, int(1e19))
+
+    address = env.deploy_contract(admin, "test_contract", bytecode + constructor_args)
+
+    state = json.loads(env.export_anvil_state())
+    accounts = {k.lower(): v for k, v in state["accounts"].items()}
+
+    assert "timestamp" in state["block"]
+    assert int(accounts["0x" + admin.hex()]["balance"], 16) == int(1e19)
+    assert accounts["0x" + address.hex()]["code"] != "0x"